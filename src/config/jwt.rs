@@ -0,0 +1,20 @@
+use crate::{
+    config::environment::CONFIG,
+    util::jwt::{Jwt, JwtSettings},
+};
+
+/// Load the bearer token issuer and verifier of the `JWT_*` settings.
+pub fn get_jwt() -> Jwt {
+    let config_env = &CONFIG;
+    Jwt::load(&JwtSettings {
+        algorithm: config_env.jwt_algorithm.clone(),
+        key: config_env.jwt_key.clone(),
+        public_key_file: config_env.jwt_public_key_file.clone(),
+        private_key_file: config_env.jwt_private_key_file.clone(),
+        issuer: config_env.jwt_issuer.clone(),
+        audience: config_env.jwt_audience.clone(),
+        leeway: config_env.jwt_leeway,
+        expiration: config_env.jwt_expiration,
+    })
+    .expect("load jwt settings failed")
+}
@@ -1,3 +1,7 @@
 pub mod logger;
 pub mod environment;
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod jwt;
+pub mod permission;
+pub mod search_index;
+pub mod watermark;
\ No newline at end of file
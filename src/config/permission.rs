@@ -0,0 +1,16 @@
+use crate::{
+    config::environment::CONFIG,
+    util::permission::{self, RolePermissions},
+};
+
+/// Load the role permissions of `ROLE_PERMISSION_FILE`, no role holds any permission when it is
+/// not set.
+pub fn get_role_permissions() -> RolePermissions {
+    let config_env = &CONFIG;
+    if config_env.role_permission_file.is_empty() {
+        log::warn!("ROLE_PERMISSION_FILE is not set, every file request is forbidden");
+        return RolePermissions::new();
+    }
+    permission::load_role_permissions(std::path::Path::new(&config_env.role_permission_file))
+        .expect("load role permissions failed")
+}
@@ -0,0 +1,8 @@
+use crate::{config::environment::CONFIG, util::search_index::SearchIndex};
+
+/// Open the full-text index in `SEARCH_INDEX_DIR`, creating it on first start.
+pub fn get_search_index() -> SearchIndex {
+    let config_env = &CONFIG;
+    SearchIndex::open(std::path::Path::new(&config_env.search_index_dir))
+        .expect("open search index failed")
+}
\ No newline at end of file
@@ -0,0 +1,14 @@
+use crate::{
+    config::environment::CONFIG,
+    util::watermark::{self, WatermarkPolicies},
+};
+
+/// Load the per-module watermark policies of `WATERMARK_POLICY_FILE`, none when it is not set.
+pub fn get_watermark_policies() -> WatermarkPolicies {
+    let config_env = &CONFIG;
+    if config_env.watermark_policy_file.is_empty() {
+        return WatermarkPolicies::new();
+    }
+    watermark::load_policies(std::path::Path::new(&config_env.watermark_policy_file))
+        .expect("load watermark policies failed")
+}
@@ -78,6 +78,47 @@ diesel::table! {
         #[max_length = 50]
         modified_by -> Nullable<Bigint>,
         modified_on -> Nullable<Datetime>,
+        metadata -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    m_saved_search (id) {
+        id -> Bigint,
+        created_by -> Bigint,
+        created_on -> Datetime,
+        #[max_length = 100]
+        name -> Varchar,
+        query -> Text,
+        shared_with -> Nullable<Text>,
+        modified_by -> Nullable<Bigint>,
+        modified_on -> Nullable<Datetime>,
+    }
+}
+
+diesel::table! {
+    m_file_acl (id) {
+        id -> Bigint,
+        file_id -> Nullable<Bigint>,
+        #[max_length = 255]
+        folder -> Nullable<Varchar>,
+        user_id -> Nullable<Bigint>,
+        role_id -> Nullable<Bigint>,
+        #[max_length = 10]
+        access -> Varchar,
+        created_by -> Bigint,
+        created_on -> Datetime,
+    }
+}
+
+diesel::table! {
+    m_refresh_token (token_hash) {
+        #[max_length = 64]
+        token_hash -> Varchar,
+        user_id -> Bigint,
+        created_on -> Datetime,
+        expires_on -> Datetime,
+        revoked_on -> Nullable<Datetime>,
     }
 }
 
@@ -1,7 +1,22 @@
 use diesel::prelude::QueryableByName;
+use serde::{Deserialize, Serialize};
 
 #[derive(QueryableByName)]
 pub struct CountResult {
     #[diesel(sql_type = diesel::sql_types::BigInt)]
     pub count: i64,
 }
+
+#[derive(QueryableByName)]
+pub struct IdResult {
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub id: i64,
+}
+
+#[derive(QueryableByName, Debug, Deserialize, Serialize, Clone)]
+pub struct FacetCount {
+    #[diesel(sql_type = diesel::sql_types::Nullable<diesel::sql_types::Text>)]
+    pub value: Option<String>,
+    #[diesel(sql_type = diesel::sql_types::BigInt)]
+    pub count: i64,
+}
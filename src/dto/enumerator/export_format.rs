@@ -0,0 +1,21 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    CSV,
+    NDJSON,
+    XLSX,
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportFormat::CSV => write!(f, "csv"),
+            ExportFormat::NDJSON => write!(f, "ndjson"),
+            ExportFormat::XLSX => write!(f, "xlsx"),
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+// declared from the least to the most access, every level includes the ones before it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum FileAccess {
+    // download, stream and preview
+    VIEWER,
+    // replace, rename and move
+    EDITOR,
+    // delete and share
+    OWNER,
+}
+
+impl FileAccess {
+    /// This level and every level including it.
+    pub fn at_least(&self) -> Vec<FileAccess> {
+        [FileAccess::VIEWER, FileAccess::EDITOR, FileAccess::OWNER]
+            .into_iter()
+            .filter(|value| value >= self)
+            .collect()
+    }
+}
+
+impl fmt::Display for FileAccess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileAccess::VIEWER => write!(f, "viewer"),
+            FileAccess::EDITOR => write!(f, "editor"),
+            FileAccess::OWNER => write!(f, "owner"),
+        }
+    }
+}
@@ -24,4 +24,23 @@ impl fmt::Display for FileType {
             FileType::UNKNOWN => write!(f, "unknown"),
         }
     }
-}
\ No newline at end of file
+}
+
+impl FileType {
+    pub fn from_file_name(file_name: &str) -> FileType {
+        let path = std::path::Path::new(file_name);
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("")
+            .to_lowercase()
+            .as_str()
+        {
+            "mp3" | "ogg" | "wav" => FileType::AUDIO,
+            "mp4" | "mkv" | "avi" | "flv" => FileType::VIDEO,
+            "jpg" | "jpeg" | "png" | "gif" | "webp" => FileType::IMAGE,
+            "pdf" | "doc" | "docx" | "xlsx" | "pptx" | "odt" => FileType::DOCUMENT,
+            _ => FileType::UNKNOWN,
+        }
+    }
+}
@@ -10,4 +10,8 @@ pub enum FilterMatchMode {
     NOT,
     LT, // LESS THAN
     GT, // GREATER THAN
+    IN,
+    NOTIN, // NOT IN
+    ISNULL,
+    NOTNULL,
 }
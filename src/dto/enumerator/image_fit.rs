@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+    CONTAIN, // keep aspect ratio inside width x height
+    COVER,   // fill width x height, crop the overflow
+    FILL,    // stretch to width x height
+}
@@ -0,0 +1,21 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    JPEG,
+    PNG,
+    WEBP,
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageFormat::JPEG => write!(f, "jpeg"),
+            ImageFormat::PNG => write!(f, "png"),
+            ImageFormat::WEBP => write!(f, "webp"),
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    // copy the source into storage
+    COPY,
+    // hard link the source, it must be on the same file system as the storage
+    LINK,
+}
+
+impl fmt::Display for ImportMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportMode::COPY => write!(f, "copy"),
+            ImportMode::LINK => write!(f, "link"),
+        }
+    }
+}
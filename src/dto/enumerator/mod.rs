@@ -1,7 +1,16 @@
 pub mod database_type;
+pub mod export_format;
 
 pub mod filter_data_type;
 pub mod filter_match_mode;
 pub mod filter_mode;
 
-pub mod file_type;
\ No newline at end of file
+pub mod file_access;
+pub mod file_type;
+
+pub mod image_fit;
+pub mod image_format;
+pub mod import_mode;
+pub mod job_status;
+pub mod permission;
+pub mod watermark_position;
\ No newline at end of file
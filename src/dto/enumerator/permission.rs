@@ -0,0 +1,29 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    // list, download and preview files
+    READ,
+    // add files, copies and derived files included
+    UPLOAD,
+    // replace, rename and move files
+    UPDATE,
+    DELETE,
+    // every permission, plus imports and reindexing
+    ADMIN,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Permission::READ => write!(f, "read"),
+            Permission::UPLOAD => write!(f, "upload"),
+            Permission::UPDATE => write!(f, "update"),
+            Permission::DELETE => write!(f, "delete"),
+            Permission::ADMIN => write!(f, "admin"),
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+// where a single watermark is placed on the image, like a compass
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum WatermarkPosition {
+    NORTHWEST, // top left
+    NORTH,
+    NORTHEAST,
+    WEST,
+    CENTER,
+    EAST,
+    SOUTHWEST,
+    SOUTH,
+    SOUTHEAST, // bottom right
+}
@@ -7,6 +7,13 @@ use super::enumerator::database_type::DatabaseType;
 #[derive(Clone, Deserialize, Debug)]
 pub struct Environment {
     pub auth_salt: String,
+    // failed logins in a row before the account is locked
+    pub auth_max_login_attempt: i32,
+    // lifetime of refresh tokens, in seconds
+    pub auth_refresh_expiration: i64,
+    // permissions of each m_role code per module
+    #[serde(default)]
+    pub role_permission_file: String,
 
     pub database_type: DatabaseType,
     pub database_username: String,
@@ -20,6 +27,20 @@ pub struct Environment {
 
     pub jwt_expiration: i64,
     pub jwt_key: String,
+    // HS256 signs with jwt_key, RS256 verifies with jwt_public_key_file and signs with
+    // jwt_private_key_file
+    #[serde(default)]
+    pub jwt_algorithm: String,
+    #[serde(default)]
+    pub jwt_public_key_file: String,
+    #[serde(default)]
+    pub jwt_private_key_file: String,
+    #[serde(default)]
+    pub jwt_issuer: String,
+    #[serde(default)]
+    pub jwt_audience: String,
+    #[serde(default)]
+    pub jwt_leeway: u64,
 
     pub redis_host: String,
     pub redis_port: u16,
@@ -50,6 +71,31 @@ pub struct Environment {
     pub session_timeout: i64,
 
     pub file_root_dir: String,
+
+    pub archive_max_entries: usize,
+    pub archive_max_size: u64,
+    pub archive_max_ratio: u64,
+
+    pub thumbnail_sizes: Vec<u32>,
+    pub thumbnail_on_upload: bool,
+
+    pub image_max_dimension: u32,
+    #[serde(default)]
+    pub image_strip_metadata_module_ids: Vec<i64>,
+
+    pub waveform_resolutions: Vec<usize>,
+
+    pub search_index_dir: String,
+
+    pub preview_max_size: u64,
+    pub preview_max_rows: usize,
+
+    #[serde(default)]
+    pub watermark_policy_file: String,
+
+    // manifest imports may only read files below this dir, imports are off when empty
+    #[serde(default)]
+    pub import_root_dir: String,
 }
 
 impl Environment {
@@ -2,4 +2,5 @@ pub mod request;
 pub mod response;
 pub mod environment;
 pub mod enumerator;
-pub mod database;
\ No newline at end of file
+pub mod database;
+pub mod principal;
\ No newline at end of file
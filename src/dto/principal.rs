@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::{enumerator::permission::Permission, response::app_error::AppError},
+    util::permission::Grants,
+};
+
+/// The user a request is made by, taken from its bearer token by the auth middleware.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Principal {
+    pub user_id: i64,
+    pub role_id: Option<i64>,
+    pub email: Option<String>,
+    // permissions of the role, filled in by the authorize middleware
+    #[serde(skip)]
+    pub grants: Arc<Grants>,
+}
+
+impl Principal {
+    /// Fail with 403 unless the permission is held on the module.
+    pub fn check(&self, permission: Permission, module_id: Option<i64>) -> Result<(), AppError> {
+        if self.grants.allows(permission, module_id) {
+            return Ok(());
+        }
+        Err(match module_id {
+            Some(value) => AppError::Forbidden(format!(
+                "{} permission on module {} required",
+                permission, value
+            )),
+            None => AppError::Forbidden(format!("{} permission required", permission)),
+        })
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Principal {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<Principal>()
+            .cloned()
+            .ok_or_else(|| AppError::Unauthorized("missing bearer token".to_string()))
+    }
+}
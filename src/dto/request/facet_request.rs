@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+pub struct Facets {
+    // comma separated facet names, e.g. `file_type,upload_month`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[validate(length(max = 255))]
+    pub _facet: Option<String>,
+}
+
+impl Facets {
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for name in self._facet.as_deref().unwrap_or_default().split(',') {
+            let name = name.trim().to_string();
+            if !name.is_empty() && !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+}
@@ -2,16 +2,42 @@ use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 use crate::{
-    dto::enumerator::{filter_data_type::FilterDataType, filter_match_mode::FilterMatchMode},
+    dto::enumerator::{
+        filter_data_type::FilterDataType, filter_match_mode::FilterMatchMode,
+        filter_mode::FilterMode,
+    },
     util::serializer::filters_serializer,
 };
 
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
 pub struct Filter {
     pub id: String,
+    // unused by ISNULL and NOTNULL
+    #[serde(default)]
     pub value: String,
+    // the two bounds of BETWEEN, the list of IN and NOTIN
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub values: Option<Vec<String>>,
     pub match_mode: FilterMatchMode,
     pub data_type: FilterDataType,
+    // compare TEXT values regardless of case
+    #[serde(default)]
+    pub ignore_case: bool,
+}
+
+/// Filters combined with AND or OR, groups may be nested.
+#[derive(Deserialize, Serialize, Debug, Clone, Validate)]
+pub struct FilterGroup {
+    pub mode: FilterMode,
+    pub filters: Vec<FilterNode>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum FilterNode {
+    Group(FilterGroup),
+    Filter(Filter),
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Validate)]
@@ -19,5 +45,19 @@ pub struct Filters {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     #[serde(with = "filters_serializer")]
-    pub _filter: Option<Vec<Filter>>,
+    pub _filter: Option<Vec<FilterNode>>,
+    // how the top level filters are combined, AND by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub _filter_mode: Option<FilterMode>,
+}
+
+impl Filters {
+    /// The filters of the request as one group.
+    pub fn to_group(&self) -> FilterGroup {
+        FilterGroup {
+            mode: self._filter_mode.clone().unwrap_or(FilterMode::AND),
+            filters: self._filter.clone().unwrap_or_default(),
+        }
+    }
 }
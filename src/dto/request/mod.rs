@@ -1,3 +1,4 @@
+pub mod facet_request;
 pub mod filter_request;
 pub mod pagination_request;
 pub mod search_request;
@@ -5,8 +5,15 @@ use validator::Validate;
 pub struct Pagination {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
+    #[validate(range(min = 0, message = "must not be negative"))]
     pub page: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
+    #[validate(range(min = 1, max = 1000, message = "must be between 1-1000"))]
     pub size: Option<i64>,
+    // `next` or `prev` of a previous response, replaces page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[validate(length(max = 4096))]
+    pub cursor: Option<String>,
 }
@@ -12,6 +12,9 @@ use crate::dto::response::app_response::AppResponse;
 #[derive(Debug, PartialEq)]
 pub enum AppError {
     InvalidRequest(ValidationErrors),
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
     DataExist,
     NotFound,
     InternalServerError,
@@ -21,6 +24,49 @@ pub enum AppError {
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         match self {
+            AppError::BadRequest(message) => {
+                let status_code = StatusCode::BAD_REQUEST;
+                (
+                    status_code,
+                    Json(AppResponse {
+                        status: status_code.as_u16(),
+                        message: "error".to_owned(),
+                        timestamp: chrono::Utc::now().naive_utc(),
+                        error: Some(message),
+                        data: None,
+                    }),
+                )
+                    .into_response()
+            }
+            AppError::Unauthorized(message) => {
+                let status_code = StatusCode::UNAUTHORIZED;
+                (
+                    status_code,
+                    [("WWW-Authenticate", "Bearer")],
+                    Json(AppResponse {
+                        status: status_code.as_u16(),
+                        message: "error".to_owned(),
+                        timestamp: chrono::Utc::now().naive_utc(),
+                        error: Some(message),
+                        data: None,
+                    }),
+                )
+                    .into_response()
+            }
+            AppError::Forbidden(message) => {
+                let status_code = StatusCode::FORBIDDEN;
+                (
+                    status_code,
+                    Json(AppResponse {
+                        status: status_code.as_u16(),
+                        message: "error".to_owned(),
+                        timestamp: chrono::Utc::now().naive_utc(),
+                        error: Some(message),
+                        data: None,
+                    }),
+                )
+                    .into_response()
+            }
             AppError::DataExist => {
                 let status_code = StatusCode::BAD_REQUEST;
                 (
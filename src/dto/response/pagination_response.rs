@@ -1,8 +1,23 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::dto::database::FacetCount;
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct PaginatedResponse<T> {
     pub total_of_elements: i64,
     pub total_of_pages: i64,
     pub content: Vec<T>,
+    // opaque cursors of the following and preceding pages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub next: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub prev: Option<String>,
+    // requested facet -> rows per value, for the same filters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub facets: Option<BTreeMap<String, Vec<FacetCount>>>,
 }
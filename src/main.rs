@@ -6,7 +6,7 @@ use axum::{
     }, middleware::from_fn, Extension, Router
 };
 use axum_file_management_service::{
-    config::{self, environment::CONFIG, logger}, dto::environment::Environment, middleware::logger_middleware, module::{health, m_file}, state::AppState
+    config::{self, environment::CONFIG, logger}, dto::environment::Environment, middleware::{auth_middleware, logger_middleware}, module::{auth, health, m_file}, state::AppState, util::{import_job::ImportJobs, search_index::IndexQueue}
 };
 // use diesel_async::pooled_connection::AsyncDieselConnectionManager;
 use tokio::{net::TcpListener, signal};
@@ -29,8 +29,22 @@ async fn main() {
     // let config_state = AsyncDieselConnectionManager::<diesel_async::AsyncPgConnection>::new(db_url);
     // let pool_async = bb8::Pool::builder().build(config_state).await.unwrap();
     let diesel_pool = config::database::get_diesel_mysql_db_pool();
+    let search_index = Arc::new(config::search_index::get_search_index());
+    let search_index_queue = IndexQueue::start(search_index.clone());
+    let watermarks = config::watermark::get_watermark_policies();
+    let jwt = config::jwt::get_jwt();
+    let role_permissions = config::permission::get_role_permissions();
 
-    let state = AppState { diesel_pool_mysql: Arc::new(diesel_pool), status: "up".to_string() };
+    let state = AppState {
+        diesel_pool_mysql: Arc::new(diesel_pool),
+        status: "up".to_string(),
+        search_index,
+        search_index_queue: Arc::new(search_index_queue),
+        watermarks: Arc::new(watermarks),
+        import_jobs: Arc::new(ImportJobs::default()),
+        jwt: Arc::new(jwt),
+        role_permissions: Arc::new(role_permissions),
+    };
     let shared_state = Arc::new(state);
 
     let cors = CorsLayer::new()
@@ -40,7 +54,11 @@ async fn main() {
 
     let api = Router::new()
         .nest("/health", health::router::new())
-        .nest("/m-file", m_file::router::new());
+        .nest("/auth", auth::router::new())
+        .nest(
+            "/m-file",
+            m_file::router::new().route_layer(from_fn(auth_middleware::authenticate)),
+        );
 
     let router = Router::new()
         .merge(api)
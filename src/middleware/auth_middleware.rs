@@ -0,0 +1,92 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Request, State},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    response::Response,
+};
+
+use crate::{
+    dto::{enumerator::permission::Permission, principal::Principal, response::app_error::AppError},
+    module::auth::repository,
+    state::AppState,
+};
+
+/// Reject requests without a valid `Authorization: Bearer <token>` header, and make the user of
+/// the token available to handlers as a `Principal`.
+pub async fn authenticate(
+    Extension(_state): Extension<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let token = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value
+                .strip_prefix("Bearer ")
+                .or_else(|| value.strip_prefix("bearer "))
+        })
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .ok_or_else(|| AppError::Unauthorized("missing bearer token".to_string()))?;
+
+    let claims = _state.jwt.verify(token).map_err(AppError::Unauthorized)?;
+    let user_id: i64 = claims
+        .sub
+        .parse()
+        .map_err(|_| AppError::Unauthorized("invalid token subject".to_string()))?;
+    req.extensions_mut().insert(Principal {
+        user_id,
+        role_id: claims.role_id,
+        email: claims.email,
+        grants: Default::default(),
+    });
+
+    Ok(next.run(req).await)
+}
+
+/// Reject requests whose user has the permission on no module, with 403. Runs after
+/// `authenticate`, rejects users locked or deleted since the token was issued with 401, and gives
+/// the `Principal` the permissions of its role, so handlers can check the module of the files they
+/// touch.
+pub async fn authorize(
+    State(permission): State<Permission>,
+    Extension(_state): Extension<Arc<AppState>>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let Some(mut principal) = req.extensions().get::<Principal>().cloned() else {
+        return Err(AppError::Unauthorized("missing bearer token".to_string()));
+    };
+
+    let mut db_conn = _state
+        .diesel_pool_mysql
+        .get()
+        .map_err(|error| AppError::Other(format!("get connection failed {error}")))?;
+    // the user is read on every request, a locked or deleted user loses access before the token
+    // expires
+    match repository::find_user_by_id(&mut db_conn, principal.user_id)? {
+        Some(user) if !user.is_locked() => {}
+        _ => {
+            log::info!("token of locked or deleted user {}", principal.user_id);
+            return Err(AppError::Unauthorized("account is not active".to_string()));
+        }
+    }
+    // the role is read on every request, a deleted role loses its permissions right away
+    if let Some(role_id) = principal.role_id
+        && let Some(role_code) = repository::find_role_code(&mut db_conn, role_id)?
+        && let Some(grants) = _state.role_permissions.get(&role_code)
+    {
+        principal.grants = grants.clone();
+    }
+    if !principal.grants.allows_any(permission) {
+        log::info!("user {} lacks {} permission", principal.user_id, permission);
+        return Err(AppError::Forbidden(format!("{} permission required", permission)));
+    }
+    req.extensions_mut().insert(principal);
+
+    Ok(next.run(req).await)
+}
@@ -1,8 +1,9 @@
 use axum::{ extract::Request, middleware::Next, response::Response};
 
+// headers are left out, they carry the access token
 pub async fn log_request(req: Request, next: Next) -> Response {
-    log::info!("Request: {:?}", req);
+    log::info!("Request: {} {}", req.method(), req.uri());
     let response = next.run(req).await;
-    log::info!("Response: {:?}", response);
+    log::info!("Response: {}", response.status());
     response
-}
\ No newline at end of file
+}
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Json},
+    http::StatusCode,
+};
+use diesel::MysqlConnection;
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    config::environment::CONFIG,
+    dto::response::{app_error::AppError, app_response::AppResponse},
+    module::auth::{
+        repository,
+        schema::{MAuthLoginRequest, MAuthRefreshRequest, MAuthTokenResponse, MRefreshToken, MUser},
+    },
+    state::AppState,
+    util::password,
+};
+
+const INVALID_CREDENTIALS: &str = "invalid email or password";
+
+lazy_static! {
+    // checked against when the email is unknown, so the response takes as long as a wrong password
+    static ref DUMMY_HASH: String = password::hash("dummy password", &CONFIG.auth_salt).unwrap_or_default();
+}
+
+/// Exchange an email and password for an access token and a refresh token. Passwords are argon2
+/// hashes salted with `AUTH_SALT`. After `AUTH_MAX_LOGIN_ATTEMPT` failures in a row the account is
+/// locked.
+pub async fn login(
+    Extension(_state): Extension<Arc<AppState>>,
+    Json(m_auth_login_request): Json<MAuthLoginRequest>,
+) -> Result<(StatusCode, Json<AppResponse<MAuthTokenResponse>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = m_auth_login_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let _email = m_auth_login_request.email.unwrap_or_default();
+    let _password = m_auth_login_request.password.unwrap_or_default();
+    let _user = match repository::find_user_by_email(&mut db_conn, &_email)? {
+        Some(value) => value,
+        None => {
+            password::verify(&_password, &DUMMY_HASH);
+            return Err(AppError::Unauthorized(INVALID_CREDENTIALS.to_string()));
+        }
+    };
+    // the password is checked before the lock, a locked account answers like a wrong password
+    let is_valid = match _user.password.as_deref() {
+        Some(value) => password::verify(&_password, value),
+        None => false,
+    };
+    if _user.is_locked() {
+        log::info!("login of locked user {}", _user.id);
+        return Err(AppError::Unauthorized(INVALID_CREDENTIALS.to_string()));
+    }
+    if !is_valid {
+        repository::add_login_attempt(&mut db_conn, _user.id, CONFIG.auth_max_login_attempt)?;
+        log::info!("failed login of user {}", _user.id);
+        return Err(AppError::Unauthorized(INVALID_CREDENTIALS.to_string()));
+    }
+
+    repository::update_last_login(&mut db_conn, _user.id)?;
+    repository::delete_expired_refresh_tokens(&mut db_conn, _user.id)?;
+    let token_response = issue_tokens(&_state, &mut db_conn, &_user)?;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(token_response),
+            error: None,
+        }),
+    ))
+}
+
+/// Exchange a refresh token for a new access token and refresh token, the old refresh token is
+/// revoked. A revoked refresh token used again means it leaked, every refresh token of its user is
+/// revoked then.
+pub async fn refresh(
+    Extension(_state): Extension<Arc<AppState>>,
+    Json(m_auth_refresh_request): Json<MAuthRefreshRequest>,
+) -> Result<(StatusCode, Json<AppResponse<MAuthTokenResponse>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = m_auth_refresh_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let _token_hash = token_hash(&m_auth_refresh_request.refresh_token.unwrap_or_default());
+    let _refresh_token = match repository::find_refresh_token(&mut db_conn, &_token_hash)? {
+        Some(value) => value,
+        None => {
+            return Err(AppError::Unauthorized("invalid refresh token".to_string()));
+        }
+    };
+    if _refresh_token.expires_on < chrono::Utc::now().naive_utc() {
+        return Err(AppError::Unauthorized("refresh token expired".to_string()));
+    }
+    // revoked before, or by a concurrent request with the same token
+    if _refresh_token.revoked_on.is_some()
+        || repository::revoke_refresh_token(&mut db_conn, &_token_hash)?.is_none()
+    {
+        log::warn!("revoked refresh token reused by user {}", _refresh_token.user_id);
+        repository::revoke_refresh_tokens_by_user(&mut db_conn, _refresh_token.user_id)?;
+        return Err(AppError::Unauthorized("refresh token revoked".to_string()));
+    }
+
+    let _user = match repository::find_user_by_id(&mut db_conn, _refresh_token.user_id)? {
+        Some(value) if !value.is_locked() => value,
+        Some(_) => {
+            return Err(AppError::Unauthorized("account is locked".to_string()));
+        }
+        None => {
+            return Err(AppError::Unauthorized("invalid refresh token".to_string()));
+        }
+    };
+    let token_response = issue_tokens(&_state, &mut db_conn, &_user)?;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(token_response),
+            error: None,
+        }),
+    ))
+}
+
+/// Revoke a refresh token. Access tokens already issued stay valid until they expire.
+pub async fn logout(
+    Extension(_state): Extension<Arc<AppState>>,
+    Json(m_auth_refresh_request): Json<MAuthRefreshRequest>,
+) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = m_auth_refresh_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    // unknown and revoked tokens are logged out already
+    let _token_hash = token_hash(&m_auth_refresh_request.refresh_token.unwrap_or_default());
+    repository::revoke_refresh_token(&mut db_conn, &_token_hash)?;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: None,
+            error: None,
+        }),
+    ))
+}
+
+fn issue_tokens(
+    state: &AppState,
+    db_conn: &mut MysqlConnection,
+    user: &MUser,
+) -> Result<MAuthTokenResponse, AppError> {
+    let (access_token, expires_in) = state
+        .jwt
+        .issue(user.id, user.role_id, user.email.clone())
+        .map_err(AppError::Other)?;
+
+    // 244 random bits
+    let refresh_token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    let refresh_expires_in = CONFIG.auth_refresh_expiration;
+    repository::insert_refresh_token(
+        db_conn,
+        MRefreshToken::new(token_hash(&refresh_token), user.id, refresh_expires_in),
+    )?;
+
+    Ok(MAuthTokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in,
+        refresh_token,
+        refresh_expires_in,
+    })
+}
+
+// refresh tokens are random, a plain SHA-256 is enough to keep them out of the database
+fn token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,4 @@
+pub mod controller;
+pub mod repository;
+pub mod router;
+pub mod schema;
@@ -0,0 +1,149 @@
+use diesel::{dsl::insert_into, prelude::*, sql_query, update};
+
+use crate::{
+    diesel_schema::{m_refresh_token, m_role, m_user},
+    dto::response::app_error::AppError,
+    module::auth::schema::{MRefreshToken, MUser},
+};
+
+/// The user with the email, deleted users are left out.
+pub fn find_user_by_email(
+    conn: &mut MysqlConnection,
+    user_email: &str,
+) -> Result<Option<MUser>, AppError> {
+    let user = m_user::table
+        .filter(m_user::email.eq(user_email))
+        .filter(m_user::is_delete.eq(false))
+        .select(MUser::as_select())
+        .first::<MUser>(conn)
+        .optional()
+        .map_err(|error| AppError::Other(format!("query failed: {}, email: {}", error, user_email)))?;
+
+    Ok(user)
+}
+
+pub fn find_user_by_id(conn: &mut MysqlConnection, user_id: i64) -> Result<Option<MUser>, AppError> {
+    let user = m_user::table
+        .filter(m_user::id.eq(user_id))
+        .filter(m_user::is_delete.eq(false))
+        .select(MUser::as_select())
+        .first::<MUser>(conn)
+        .optional()
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, user_id)))?;
+
+    Ok(user)
+}
+
+/// Code of the role, deleted roles are left out.
+pub fn find_role_code(conn: &mut MysqlConnection, role_id: i64) -> Result<Option<String>, AppError> {
+    let role_code = m_role::table
+        .filter(m_role::id.eq(role_id))
+        .filter(m_role::is_delete.eq(false))
+        .select(m_role::code)
+        .first::<Option<String>>(conn)
+        .optional()
+        .map_err(|error| AppError::Other(format!("query failed: {}, role_id: {}", error, role_id)))?;
+
+    Ok(role_code.flatten())
+}
+
+/// Count a failed login and lock the user once `max_attempt` failures are reached. The counter
+/// is increased in the statement itself so concurrent attempts are all counted.
+pub fn add_login_attempt(
+    conn: &mut MysqlConnection,
+    user_id: i64,
+    max_attempt: i32,
+) -> Result<(), AppError> {
+    // MySQL assigns left to right, is_locked sees the increased login_attempt
+    let statement = "UPDATE m_user
+            SET login_attempt = COALESCE(login_attempt, 0) + 1,
+            is_locked = COALESCE(is_locked, FALSE) OR login_attempt >= ?
+            WHERE id = ?";
+
+    sql_query(statement)
+        .bind::<diesel::sql_types::Integer, _>(max_attempt)
+        .bind::<diesel::sql_types::BigInt, _>(user_id)
+        .execute(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, user_id)))?;
+    Ok(())
+}
+
+/// Reset the failed login counter and record the login time.
+pub fn update_last_login(conn: &mut MysqlConnection, user_id: i64) -> Result<(), AppError> {
+    update(m_user::table.filter(m_user::id.eq(user_id)))
+        .set((
+            m_user::login_attempt.eq(0),
+            m_user::last_login.eq(chrono::Utc::now().naive_utc()),
+        ))
+        .execute(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, user_id)))?;
+    Ok(())
+}
+
+pub fn find_refresh_token(
+    conn: &mut MysqlConnection,
+    hash: &str,
+) -> Result<Option<MRefreshToken>, AppError> {
+    let refresh_token = m_refresh_token::table
+        .filter(m_refresh_token::token_hash.eq(hash))
+        .select(MRefreshToken::as_select())
+        .first::<MRefreshToken>(conn)
+        .optional()
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+
+    Ok(refresh_token)
+}
+
+pub fn insert_refresh_token(
+    conn: &mut MysqlConnection,
+    refresh_token: MRefreshToken,
+) -> Result<Option<()>, AppError> {
+    let rows_affected = insert_into(m_refresh_token::table)
+        .values(&refresh_token)
+        .execute(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    if rows_affected > 0 {
+        return Ok(Some(()));
+    }
+    Ok(None)
+}
+
+/// Revoke the token unless it already is. Returns `None` when another request revoked it first.
+pub fn revoke_refresh_token(conn: &mut MysqlConnection, hash: &str) -> Result<Option<()>, AppError> {
+    let rows_affected = update(
+        m_refresh_token::table
+            .filter(m_refresh_token::token_hash.eq(hash))
+            .filter(m_refresh_token::revoked_on.is_null()),
+    )
+    .set(m_refresh_token::revoked_on.eq(chrono::Utc::now().naive_utc()))
+    .execute(conn)
+    .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    if rows_affected > 0 {
+        return Ok(Some(()));
+    }
+    Ok(None)
+}
+
+/// Revoke every refresh token of the user.
+pub fn revoke_refresh_tokens_by_user(conn: &mut MysqlConnection, user_id: i64) -> Result<(), AppError> {
+    update(
+        m_refresh_token::table
+            .filter(m_refresh_token::user_id.eq(user_id))
+            .filter(m_refresh_token::revoked_on.is_null()),
+    )
+    .set(m_refresh_token::revoked_on.eq(chrono::Utc::now().naive_utc()))
+    .execute(conn)
+    .map_err(|error| AppError::Other(format!("query failed: {}, user_id: {}", error, user_id)))?;
+    Ok(())
+}
+
+pub fn delete_expired_refresh_tokens(conn: &mut MysqlConnection, user_id: i64) -> Result<(), AppError> {
+    diesel::delete(
+        m_refresh_token::table
+            .filter(m_refresh_token::user_id.eq(user_id))
+            .filter(m_refresh_token::expires_on.lt(chrono::Utc::now().naive_utc())),
+    )
+    .execute(conn)
+    .map_err(|error| AppError::Other(format!("query failed: {}, user_id: {}", error, user_id)))?;
+    Ok(())
+}
@@ -0,0 +1,10 @@
+use axum::{routing::post, Router};
+
+use crate::module::auth::controller::{login, logout, refresh};
+
+pub fn new() -> Router {
+    Router::new()
+        .route("/login", post(login))
+        .route("/refresh", post(refresh))
+        .route("/logout", post(logout))
+}
@@ -0,0 +1,85 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::{Insertable, Queryable};
+use diesel::Selectable;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::diesel_schema::{m_refresh_token, m_user};
+
+/// The columns of m_user a login needs.
+#[derive(Debug, Clone, Queryable, Selectable)]
+#[diesel(table_name = m_user)]
+pub struct MUser {
+    pub id: i64,
+    pub email: Option<String>,
+    pub password: Option<String>,
+    pub role_id: Option<i64>,
+    pub is_locked: Option<bool>,
+    pub login_attempt: Option<i32>,
+    pub last_login: Option<NaiveDateTime>,
+}
+
+impl MUser {
+    pub fn is_locked(&self) -> bool {
+        self.is_locked.unwrap_or(false)
+    }
+}
+
+/// A refresh token handed out at login, only its SHA-256 is stored.
+#[derive(Debug, Clone, Queryable, Insertable, Selectable)]
+#[diesel(table_name = m_refresh_token)]
+pub struct MRefreshToken {
+    pub token_hash: String,
+    pub user_id: i64,
+    pub created_on: NaiveDateTime,
+    pub expires_on: NaiveDateTime,
+    pub revoked_on: Option<NaiveDateTime>,
+}
+
+impl MRefreshToken {
+    pub fn new(token_hash: String, user_id: i64, lifetime: i64) -> MRefreshToken {
+        let date_now = chrono::Utc::now().naive_utc();
+        MRefreshToken {
+            token_hash,
+            user_id,
+            created_on: date_now,
+            expires_on: date_now + chrono::Duration::seconds(lifetime),
+            revoked_on: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MAuthLoginRequest {
+    #[validate(
+        email(message = "must be an email"),
+        length(max = 100, message = "must be at most 100 chars"),
+        required(message = "mandatory")
+    )]
+    pub email: Option<String>,
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1-255 chars"),
+        required(message = "mandatory")
+    )]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MAuthRefreshRequest {
+    #[validate(
+        length(min = 1, max = 255, message = "must be between 1-255 chars"),
+        required(message = "mandatory")
+    )]
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MAuthTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    // in seconds
+    pub expires_in: i64,
+    pub refresh_token: String,
+    // in seconds
+    pub refresh_expires_in: i64,
+}
@@ -8,17 +8,21 @@ use validator::Validate;
 
 use crate::{
     dto::{
+        enumerator::{file_access::FileAccess, permission::Permission},
+        principal::Principal,
         request::{
-            filter_request::Filters, pagination_request::Pagination, search_request::Search,
-            sort_request::Sorts,
+            facet_request::Facets, filter_request::Filters, pagination_request::Pagination,
+            search_request::Search, sort_request::Sorts,
         },
         response::{
             app_error::AppError, app_response::AppResponse, pagination_response::PaginatedResponse,
         },
     },
     module::m_file::{
-        repository,
+        repository::{self, FileScope},
         schema::{MFile, MFileRequest},
+        search::controller::{index_file, unindex_file},
+        share::{controller::check_access, repository as share_repository},
     },
     state::AppState,
 };
@@ -26,6 +30,7 @@ use crate::{
 pub async fn find_by_id(
     Path(id): Path<i64>,
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
 ) -> Result<(StatusCode, Json<AppResponse<MFile>>), AppError> {
     log::info!("status: {}", _state.status);
 
@@ -44,6 +49,7 @@ pub async fn find_by_id(
     let result = repository::find_by_id(&mut db_conn, id);
     match result {
         Ok(Some(value)) => {
+            check_access(&mut db_conn, &_principal, &value, Permission::READ, FileAccess::VIEWER)?;
             let status_code = StatusCode::OK;
             return Ok((
                 status_code,
@@ -67,6 +73,7 @@ pub async fn find_by_id(
 
 pub async fn find_all(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
 ) -> Result<(StatusCode, Json<AppResponse<Vec<MFile>>>), AppError> {
     log::info!("status: {}", _state.status);
 
@@ -82,7 +89,7 @@ pub async fn find_all(
         }
     };
 
-    let result = repository::find_all(&mut db_conn);
+    let result = repository::find_all(&mut db_conn, &FileScope::readable_by(&_principal));
     match result {
         Ok(value) => {
             let status_code = StatusCode::OK;
@@ -106,6 +113,7 @@ pub async fn find_all(
 pub async fn delete_by_id(
     Path(id): Path<i64>,
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
 ) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
     log::info!("status: {}", _state.status);
 
@@ -121,9 +129,22 @@ pub async fn delete_by_id(
         }
     };
 
+    match repository::find_by_id(&mut db_conn, id)? {
+        Some(value) => check_access(
+            &mut db_conn,
+            &_principal,
+            &value,
+            Permission::DELETE,
+            FileAccess::OWNER,
+        )?,
+        None => return Err(AppError::NotFound),
+    };
+
     let result = repository::delete_by_id(&mut db_conn, id);
     match result {
         Ok(Some(_)) => {
+            unindex_file(&_state.search_index_queue, id);
+            share_repository::delete_by_file_id(&mut db_conn, id)?;
             let status_code = StatusCode::OK;
             return Ok((
                 status_code,
@@ -147,9 +168,11 @@ pub async fn delete_by_id(
 
 pub async fn create(
     Extension(_state): Extension<Arc<AppState>>,
-    Json(m_file_request): Json<MFileRequest>,
+    _principal: Principal,
+    Json(mut m_file_request): Json<MFileRequest>,
 ) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
     log::info!("status: {}", _state.status);
+    m_file_request.user_id = Some(_principal.user_id);
 
     let _is_valid = match m_file_request.validate() {
         Ok(value) => value,
@@ -170,6 +193,7 @@ pub async fn create(
         }
     };
 
+    _principal.check(Permission::UPLOAD, m_file_request.module_id)?;
     let new_m_file = MFile::from_create_request(m_file_request);
     let existing_biodata_result = repository::find_by_id(&mut db_conn, new_m_file.id);
     match existing_biodata_result {
@@ -182,10 +206,11 @@ pub async fn create(
         }
     };
 
-    let result = repository::insert_mfile(&mut db_conn, new_m_file);
+    let result = repository::insert_mfile(&mut db_conn, new_m_file.clone());
 
     match result {
         Ok(Some(_)) => {
+            index_file(&_state.search_index_queue, new_m_file);
             let status_code = StatusCode::OK;
             return Ok((
                 status_code,
@@ -209,9 +234,11 @@ pub async fn create(
 
 pub async fn update(
     Extension(_state): Extension<Arc<AppState>>,
-    Json(m_file_request): Json<MFileRequest>,
+    _principal: Principal,
+    Json(mut m_file_request): Json<MFileRequest>,
 ) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
     log::info!("status: {}", _state.status);
+    m_file_request.user_id = Some(_principal.user_id);
 
     let _is_valid = match m_file_request.validate() {
         Ok(value) => value,
@@ -239,6 +266,15 @@ pub async fn update(
             return Err(AppError::NotFound);
         }
         Ok(Some(value)) => {
+            // moving a file to another module needs the permission on both
+            check_access(
+                &mut db_conn,
+                &_principal,
+                &value,
+                Permission::UPDATE,
+                FileAccess::EDITOR,
+            )?;
+            _principal.check(Permission::UPDATE, m_file_request.module_id)?;
             _new_m_file = <MFile>::from_update_request(m_file_request, value);
         }
         Err(err) => {
@@ -246,10 +282,11 @@ pub async fn update(
         }
     };
 
-    let result = repository::update_mfile(&mut db_conn, _new_m_file);
+    let result = repository::update_mfile(&mut db_conn, _new_m_file.clone());
 
     match result {
         Ok(Some(_)) => {
+            index_file(&_state.search_index_queue, _new_m_file);
             let status_code = StatusCode::OK;
             return Ok((
                 status_code,
@@ -273,10 +310,12 @@ pub async fn update(
 
 pub async fn find_page(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     Query(_pagination): Query<Pagination>,
     Query(_sort): Query<Sorts>,
     Query(_filter): Query<Filters>,
     Query(_global_search): Query<Search>,
+    Query(_facet): Query<Facets>,
 ) -> Result<(StatusCode, Json<AppResponse<PaginatedResponse<MFile>>>), AppError> {
     log::info!("status: {}", _state.status);
 
@@ -289,6 +328,9 @@ pub async fn find_page(
     if let Err(err) = _sort.validate() {
         return Err(AppError::InvalidRequest(err).into());
     };
+    if let Err(err) = _facet.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
 
     let mut _page = _pagination.page.unwrap_or(0);
     if _page < 0 {
@@ -298,14 +340,14 @@ pub async fn find_page(
     if _size < 1 {
         _size = 1;
     }
-    let _filters = _filter._filter.clone().unwrap_or_default();
+    let _filter_group = _filter.to_group();
     let _sorts = _sort._sort.clone().unwrap_or_default();
     let _q = _global_search._q.clone().unwrap_or_default();
     log::info!(
         "page {:?}, size {:?}, filters {:?}, sorts {:?}, global_search {:?}",
         _page,
         _size,
-        _filters,
+        _filter_group,
         _sorts,
         _q
     );
@@ -322,7 +364,27 @@ pub async fn find_page(
         }
     };
 
-    let result = repository::pagination(&mut db_conn, _page, _size, _filters, _sorts, _q);
+    // facets count every matching row, they are not paged
+    let _scope = FileScope::readable_by(&_principal);
+    let _facet_names = _facet.names();
+    let mut _facets = None;
+    if !_facet_names.is_empty() {
+        _facets = Some(repository::facets(
+            &mut db_conn,
+            _filter_group.clone(),
+            _q.clone(),
+            _facet_names,
+            &_scope,
+        )?);
+    }
+
+    let result = repository::pagination(
+        &mut db_conn,
+        _page,
+        _size,
+        _pagination.cursor.clone(),
+        repository::pagination_query(_filter_group, _sorts, _q, &_scope)?,
+    );
     match result {
         Ok(value) => {
             let mut total_of_pages = value.1 / _size;
@@ -335,6 +397,9 @@ pub async fn find_page(
                 content: value.0,
                 total_of_elements: value.1,
                 total_of_pages: total_of_pages,
+                next: value.2.next,
+                prev: value.2.prev,
+                facets: _facets,
             };
             return Ok((
                 status_code,
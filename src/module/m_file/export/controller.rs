@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{Extension, Query},
+    response::IntoResponse,
+};
+use chrono::{format::StrftimeItems, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use validator::Validate;
+
+use crate::{
+    dto::{
+        enumerator::export_format::ExportFormat,
+        principal::Principal,
+        request::{filter_request::Filters, search_request::Search, sort_request::Sorts},
+        response::app_error::AppError,
+    },
+    module::m_file::{
+        repository::{self, FileScope},
+        schema::{MFile, MFileExportRequest},
+    },
+    state::AppState,
+    util::export::{self, ExportValue},
+};
+
+// rows read from the database at a time
+const EXPORT_BATCH_SIZE: i64 = 500;
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+
+const EXPORT_COLUMNS: [&str; 14] = [
+    "id",
+    "file_name",
+    "file_type",
+    "file_path",
+    "file_size",
+    "module_id",
+    "created_by",
+    "created_on",
+    "modified_by",
+    "modified_on",
+    "deleted_by",
+    "deleted_on",
+    "is_delete",
+    "metadata",
+];
+
+/// Stream every file matching the filters, sorts and global search of `find_page` as CSV,
+/// NDJSON or XLSX. Rows are read in keyset batches, so memory use does not grow with the result.
+pub async fn export(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Query(_sort): Query<Sorts>,
+    Query(_filter): Query<Filters>,
+    Query(_global_search): Query<Search>,
+    Query(_export): Query<MFileExportRequest>,
+) -> impl IntoResponse {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = _filter.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+    if let Err(err) = _sort.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+    if let Err(err) = _export.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    let _format = _export.format.clone().unwrap_or(ExportFormat::CSV);
+    let _columns = export_columns(_export.columns.as_deref())?;
+    let _timezone: Tz = match _export.timezone.as_deref() {
+        Some(value) => value
+            .parse()
+            .map_err(|_| AppError::BadRequest(format!("unknown timezone {}", value)))?,
+        None => Tz::UTC,
+    };
+    let _date_format = _export
+        .date_format
+        .clone()
+        .unwrap_or(DEFAULT_DATE_FORMAT.to_string());
+    if StrftimeItems::new(&_date_format).parse().is_err() {
+        return Err(AppError::BadRequest(format!(
+            "invalid date_format {}",
+            _date_format
+        )));
+    }
+
+    let _filter_group = _filter.to_group();
+    let _sorts = _sort._sort.clone().unwrap_or_default();
+    let _q = _global_search._q.clone().unwrap_or_default();
+    log::info!(
+        "export {}, columns {:?}, timezone {}, filters {:?}, sorts {:?}, global_search {:?}",
+        _format,
+        _columns,
+        _timezone,
+        _filter_group,
+        _sorts,
+        _q
+    );
+    // reject unknown filter and sort columns before the response starts
+    let _scope = FileScope::readable_by(&_principal);
+    repository::pagination_query(_filter_group.clone(), _sorts.clone(), _q.clone(), &_scope)?;
+
+    // each batch reads on a connection of its own, slow clients never hold one
+    let pool = _state.diesel_pool_mysql.clone();
+    let row_columns = _columns.clone();
+    let mut cursor: Option<String> = None;
+    let mut is_done = false;
+    let next_rows = move || -> Result<Option<Vec<Vec<ExportValue>>>, String> {
+        if is_done {
+            return Ok(None);
+        }
+        let mut db_conn = pool
+            .get()
+            .map_err(|error| format!("get connection failed {error}"))?;
+        let (data_vec, next) = repository::batch(
+            &mut db_conn,
+            EXPORT_BATCH_SIZE,
+            cursor.take(),
+            _filter_group.clone(),
+            _sorts.clone(),
+            _q.clone(),
+            &_scope,
+        )
+        .map_err(|error| format!("{:?}", error))?;
+        is_done = next.is_none();
+        cursor = next;
+        let rows = data_vec
+            .iter()
+            .map(|value| {
+                row_columns
+                    .iter()
+                    .map(|column| export_value(value, column, &_timezone, &_date_format))
+                    .collect()
+            })
+            .collect();
+        Ok(Some(rows))
+    };
+
+    let file_name = format!(
+        "m_file_{}.{}",
+        Utc::now().format("%Y%m%d%H%M%S"),
+        _format
+    );
+    let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", file_name),
+        )
+        .header("Content-Type", export::content_type(&_format))
+        .body(Body::from_stream(export::export_stream(
+            _format, _columns, next_rows,
+        )))
+        .unwrap();
+
+    Ok(response_builder)
+}
+
+// requested columns in the requested order, every column when none are given
+fn export_columns(columns: Option<&str>) -> Result<Vec<String>, AppError> {
+    let mut export_columns: Vec<String> = Vec::new();
+    for column in columns.unwrap_or_default().split(',') {
+        let column = column.trim();
+        if column.is_empty() || export_columns.iter().any(|value| value == column) {
+            continue;
+        }
+        if !EXPORT_COLUMNS.contains(&column) {
+            return Err(AppError::BadRequest(format!("unknown column {}", column)));
+        }
+        export_columns.push(column.to_string());
+    }
+    if export_columns.is_empty() {
+        return Ok(EXPORT_COLUMNS.iter().map(|column| column.to_string()).collect());
+    }
+    Ok(export_columns)
+}
+
+fn export_value(m_file: &MFile, column: &str, timezone: &Tz, date_format: &str) -> ExportValue {
+    let text = |value: &Option<String>| match value {
+        Some(value) => ExportValue::Text(value.clone()),
+        None => ExportValue::Null,
+    };
+    let integer = |value: Option<i64>| match value {
+        Some(value) => ExportValue::Integer(value),
+        None => ExportValue::Null,
+    };
+    // stored as UTC
+    let date = |value: Option<NaiveDateTime>| match value {
+        Some(value) => ExportValue::Text(
+            Utc.from_utc_datetime(&value)
+                .with_timezone(timezone)
+                .format(date_format)
+                .to_string(),
+        ),
+        None => ExportValue::Null,
+    };
+    match column {
+        "id" => ExportValue::Integer(m_file.id),
+        "file_name" => text(&m_file.file_name),
+        "file_type" => text(&m_file.file_type),
+        "file_path" => text(&m_file.file_path),
+        // stored as text
+        "file_size" => match m_file.file_size.as_deref().map(str::parse::<i64>) {
+            Some(Ok(value)) => ExportValue::Integer(value),
+            _ => text(&m_file.file_size),
+        },
+        "module_id" => integer(m_file.module_id),
+        "created_by" => ExportValue::Integer(m_file.created_by),
+        "created_on" => date(Some(m_file.created_on)),
+        "modified_by" => integer(m_file.modified_by),
+        "modified_on" => date(m_file.modified_on),
+        "deleted_by" => integer(m_file.deleted_by),
+        "deleted_on" => date(m_file.deleted_on),
+        "is_delete" => ExportValue::Boolean(m_file.is_delete),
+        "metadata" => match m_file
+            .metadata
+            .as_deref()
+            .and_then(|value| serde_json::from_str(value).ok())
+        {
+            Some(value) => ExportValue::Json(value),
+            None => ExportValue::Null,
+        },
+        _ => ExportValue::Null,
+    }
+}
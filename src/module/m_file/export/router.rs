@@ -0,0 +1,11 @@
+use axum::{middleware::from_fn_with_state, routing::get, Router};
+
+use crate::{
+    dto::enumerator::permission::Permission, middleware::auth_middleware::authorize,
+    module::m_file::export::controller::export,
+};
+
+pub fn new() -> Router {
+    Router::new()
+        .route("/", get(export).route_layer(from_fn_with_state(Permission::READ, authorize)))
+}
@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+
+use crate::{
+    config::environment::CONFIG,
+    dto::{
+        enumerator::{file_access::FileAccess, file_type::FileType, permission::Permission},
+        principal::Principal,
+        response::{app_error::AppError, app_response::AppResponse},
+    },
+    module::m_file::{
+        repository,
+        schema::{MFile, MFileWaveformRequest},
+        share::controller::check_access,
+    },
+    state::AppState,
+    util::{
+        file_cache,
+        waveform::{self, Waveform},
+    },
+};
+
+const WAVEFORM_CACHE_KEY: &str = "waveform.json";
+
+pub async fn waveform(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Path(id): Path<i64>,
+    Query(_waveform_request): Query<MFileWaveformRequest>,
+) -> impl IntoResponse {
+    let config = &CONFIG;
+    let resolution = _waveform_request.resolution;
+    if resolution.is_some_and(|value| !config.waveform_resolutions.contains(&value)) {
+        return Err(AppError::BadRequest(format!(
+            "resolution must be one of {:?}",
+            config.waveform_resolutions
+        )));
+    }
+
+    let _existing_data = find_audio(&_state, &_principal, id)?;
+    let _file_path_string = _existing_data.file_path.unwrap_or_default();
+    let _file_name = _existing_data.file_name.unwrap_or_default();
+
+    let waveform_bytes = match file_cache::read(&_file_path_string, WAVEFORM_CACHE_KEY).await {
+        Some(value) => value,
+        None => build_waveform(&_file_path_string, &_file_name).await?,
+    };
+    let mut waveform_data: Waveform = serde_json::from_slice(&waveform_bytes)
+        .map_err(|error| AppError::Other(format!("read waveform failed: {error}")))?;
+    if let Some(value) = resolution {
+        waveform_data.peaks.retain(|resolution, _| *resolution == value);
+    }
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(waveform_data),
+            error: None,
+        }),
+    ))
+}
+
+/// Compute and cache the waveform peaks of an uploaded audio file.
+pub async fn create_waveform(file_path: String, file_name: String) {
+    if let Err(error) = build_waveform(&file_path, &file_name).await {
+        log::error!("create waveform failed: {:?}, file: {}", error, file_path);
+    }
+}
+
+async fn build_waveform(file_path: &str, file_name: &str) -> Result<Vec<u8>, AppError> {
+    let source_path = std::path::PathBuf::from(file_path);
+    let source_name = file_name.to_string();
+    let waveform_data = tokio::task::spawn_blocking(move || {
+        waveform::generate(&source_path, &source_name, &CONFIG.waveform_resolutions)
+    })
+    .await
+    .map_err(|error| AppError::Other(format!("create waveform failed: {error}")))?
+    .map_err(|error| AppError::BadRequest(format!("unsupported audio: {error}")))?;
+
+    let waveform_bytes = serde_json::to_vec(&waveform_data)
+        .map_err(|error| AppError::Other(format!("create waveform failed: {error}")))?;
+    file_cache::write(file_path, WAVEFORM_CACHE_KEY, &waveform_bytes).await;
+    Ok(waveform_bytes)
+}
+
+fn find_audio(_state: &AppState, _principal: &Principal, id: i64) -> Result<MFile, AppError> {
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!(
+                "get connection failed {error}, id: {id}"
+            )));
+        }
+    };
+
+    let find_by_id_result = repository::find_by_id(&mut db_conn, id);
+    match find_by_id_result {
+        Ok(Some(value)) => {
+            if value.file_type != Some(FileType::AUDIO.to_string()) {
+                return Err(AppError::BadRequest(format!("file is not an audio, id: {id}")));
+            }
+            check_access(&mut db_conn, _principal, &value, Permission::READ, FileAccess::VIEWER)?;
+            Ok(value)
+        }
+        Ok(None) => Err(AppError::NotFound),
+        Err(error) => Err(error),
+    }
+}
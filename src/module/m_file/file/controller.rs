@@ -1,16 +1,18 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::HashSet, path::PathBuf, sync::Arc};
 
 use axum::{
     body::{Body, Bytes},
     extract::{Extension, Json, Multipart, Path},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
 };
 use chrono::{Datelike, Local};
+use diesel::MysqlConnection;
 use tokio::{
     fs::File,
     io::{AsyncReadExt, AsyncWriteExt},
 };
+use tokio_stream::StreamExt;
 use tokio_util::io::ReaderStream;
 use uuid::Uuid;
 use validator::Validate;
@@ -18,27 +20,45 @@ use validator::Validate;
 use crate::{
     config::environment::CONFIG,
     dto::{
-        enumerator::file_type::FileType,
+        enumerator::{file_access::FileAccess, file_type::FileType, permission::Permission},
+        principal::Principal,
         response::{app_error::AppError, app_response::AppResponse},
     },
     module::m_file::{
-        repository,
-        schema::{MFile, MFileCopyMoveRequest, MFileRenameRequest},
+        file::{audio, image},
+        repository::{self, FileScope},
+        search::controller::{index_file, unindex_file},
+        share::{controller::check_access, repository as share_repository},
+        schema::{
+            MFile, MFileArchiveRequest, MFileCopyMoveRequest, MFileEntryResponse,
+            MFileRenameRequest,
+        },
     },
     state::AppState,
+    util::{
+        archive::{self, ArchiveEntry, ExtractLimit},
+        file_cache, image_metadata, image_processing, media_metadata, text_extraction,
+        watermark::Watermark,
+    },
 };
 
+// largest image entry of an archive watermarked in memory
+const MAX_WATERMARK_ENTRY_SIZE: u64 = 64 * 1024 * 1024;
+
 pub async fn upload(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     mut multipart: Multipart,
-) -> Result<(StatusCode, Json<AppResponse<MFile>>), AppError> {
+) -> Result<Response, AppError> {
     let mut file_bytes: Bytes = <Bytes>::new();
     let mut file_name: String = String::new();
     let mut file_type: String = String::new();
     let mut file_size: String = String::new();
     let mut module_id: i64 = 0;
-    let mut user_id: i64 = 0;
+    let user_id: i64 = _principal.user_id;
     let mut id: i64 = 0;
+    let mut extract: bool = false;
+    let mut strip_metadata: Option<bool> = None;
 
     while let Some(field) = multipart.next_field().await.unwrap() {
         let field_name = field.name().unwrap().to_string();
@@ -59,7 +79,10 @@ pub async fn upload(
                 // Document types
                 "application/pdf"
                 | "application/msword"
-                | "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+                | "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+                | "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                | "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+                | "application/vnd.oasis.opendocument.text" => {
                     FileType::DOCUMENT
                 }
                 // Default case for unknown types
@@ -70,6 +93,7 @@ pub async fn upload(
                             "mp3" | "ogg" => FileType::AUDIO,
                             "mp4" | "mkv" => FileType::VIDEO,
                             "jpg" | "jpeg" | "png" | "webp" => FileType::IMAGE,
+                            "pdf" | "docx" | "xlsx" | "pptx" | "odt" => FileType::DOCUMENT,
                             _ => FileType::UNKNOWN,
                         }
                     } else {
@@ -81,33 +105,41 @@ pub async fn upload(
 
             continue;
         }
-        if field_name == "user_id".to_string() {
+        if field_name == "id".to_string() {
             let payload_tmp = field
                 .text()
                 .await
                 .map_err(|e| AppError::Other(e.to_string()))?;
-            user_id = payload_tmp.parse().unwrap();
+            id = payload_tmp.parse().unwrap();
 
             continue;
         }
-        if field_name == "id".to_string() {
+        if field_name == "module_id".to_string() {
             let payload_tmp = field
                 .text()
                 .await
                 .map_err(|e| AppError::Other(e.to_string()))?;
-            id = payload_tmp.parse().unwrap();
-
+            module_id = payload_tmp.parse().unwrap();
             continue;
         }
-        if field_name == "module_id".to_string() {
+        if field_name == "extract" {
             let payload_tmp = field
                 .text()
                 .await
                 .map_err(|e| AppError::Other(e.to_string()))?;
-            module_id = payload_tmp.parse().unwrap();
+            extract = payload_tmp.parse().unwrap_or(false);
+            continue;
+        }
+        if field_name == "strip_metadata" {
+            let payload_tmp = field
+                .text()
+                .await
+                .map_err(|e| AppError::Other(e.to_string()))?;
+            strip_metadata = payload_tmp.parse().ok();
             continue;
         }
     }
+    _principal.check(Permission::UPLOAD, Some(module_id))?;
 
     // get db connection
     let db_conn_result = _state.diesel_pool_mysql.get();
@@ -121,6 +153,32 @@ pub async fn upload(
         }
     };
 
+    if extract {
+        let m_files = upload_archive(
+            &mut db_conn,
+            module_id,
+            user_id,
+            file_name,
+            file_bytes,
+        )
+        .await?;
+        for m_file in m_files.iter() {
+            index_file(&_state.search_index_queue, m_file.clone());
+        }
+        let status_code = StatusCode::OK;
+        return Ok((
+            status_code,
+            Json(AppResponse {
+                status: status_code.as_u16(),
+                message: "success".to_owned(),
+                timestamp: chrono::Utc::now().naive_utc(),
+                data: Some(m_files),
+                error: None,
+            }),
+        )
+            .into_response());
+    }
+
     let today = Local::now();
     let _date_string = format!("{}/{:02}/{:02}", today.year(), today.month(), today.day());
 
@@ -167,6 +225,12 @@ pub async fn upload(
         }
     };
 
+    let mut metadata: Option<String> = None;
+    if file_type == FileType::IMAGE.to_string() {
+        (file_bytes, metadata) = process_image(file_bytes, module_id, strip_metadata).await;
+        file_size = file_bytes.len().to_string();
+    }
+
     // create file
     let file = File::create(&file_path).await.map_err(|e| {
         log::error!("Failed to create file: {}", e);
@@ -183,10 +247,18 @@ pub async fn upload(
         return Err(AppError::InternalServerError);
     }
 
+    if file_type == FileType::AUDIO.to_string() || file_type == FileType::VIDEO.to_string() {
+        metadata = read_media_metadata(file_path.clone(), file_name.clone()).await;
+    }
+    if text_extraction::is_supported(&file_name) {
+        metadata = extract_document(file_path.clone(), file_name.clone()).await;
+    }
+
     let mut new_m_file = MFile::new(
         file_name, file_type, file_path, file_size, module_id, user_id,
     );
     new_m_file.id = id;
+    new_m_file.metadata = metadata;
 
     let result = repository::insert_mfile(&mut db_conn, new_m_file.clone());
 
@@ -197,6 +269,9 @@ pub async fn upload(
         }
     };
 
+    create_derived_files(&new_m_file);
+    index_file(&_state.search_index_queue, new_m_file.clone());
+
     let status_code = StatusCode::OK;
     Ok((
         status_code,
@@ -207,19 +282,244 @@ pub async fn upload(
             data: Some(new_m_file),
             error: None,
         }),
-    ))
+    )
+        .into_response())
+}
+
+async fn upload_archive(
+    db_conn: &mut MysqlConnection,
+    module_id: i64,
+    user_id: i64,
+    file_name: String,
+    file_bytes: Bytes,
+) -> Result<Vec<MFile>, AppError> {
+    let Some(format) = archive::detect_format(&file_bytes) else {
+        return Err(AppError::BadRequest(format!(
+            "unsupported archive: {file_name}"
+        )));
+    };
+
+    let config = &CONFIG;
+    let mut archive_folder = file_name.clone();
+    for extension in [".tar.gz", ".tgz", ".tar", ".zip"] {
+        if let Some(value) = archive_folder.strip_suffix(extension) {
+            archive_folder = value.to_string();
+            break;
+        }
+    }
+    let archive_folder = match archive::sanitize_entry_path(&archive_folder) {
+        Some(value) => value.join("_"),
+        None => Uuid::new_v4().to_string(),
+    };
+    let dir_path = PathBuf::from(format!(
+        "{}/{}/{}/{}",
+        config.file_root_dir, module_id, user_id, archive_folder
+    ));
+
+    // check existing dir
+    let result_dir_exist = tokio::fs::try_exists(dir_path.clone()).await;
+    match result_dir_exist {
+        Ok(_value) => {
+            if _value {
+                log::info!("dir exist");
+                return Err(AppError::DataExist);
+            }
+        }
+        Err(_error) => {
+            return Err(AppError::Other(format!("find dir error: {_error}")));
+        }
+    };
+
+    // extract entries
+    let limit = ExtractLimit {
+        max_entries: config.archive_max_entries,
+        max_size: config.archive_max_size,
+        max_ratio: config.archive_max_ratio,
+    };
+    let target_dir = dir_path.clone();
+    let extract_result = tokio::task::spawn_blocking(move || {
+        archive::extract_archive(&file_bytes, format, &target_dir, &limit)
+    })
+    .await
+    .map_err(|error| AppError::Other(format!("extract archive failed: {error}")))?;
+    let entries = match extract_result {
+        Ok(value) => value,
+        Err(error) => {
+            let _ = tokio::fs::remove_dir_all(dir_path).await;
+            return Err(archive_error("extract archive failed", error));
+        }
+    };
+    if entries.is_empty() {
+        let _ = tokio::fs::remove_dir_all(dir_path).await;
+        return Err(AppError::BadRequest(format!("archive is empty: {file_name}")));
+    }
+
+    // one row per entry, ids are allocated on insert
+    let mut m_files: Vec<MFile> = Vec::new();
+    for entry in entries {
+        let entry_type = FileType::from_file_name(&entry.file_name);
+        let mut entry_metadata: Option<String> = None;
+        let mut entry_size = entry.size;
+        if entry_type == FileType::AUDIO || entry_type == FileType::VIDEO {
+            entry_metadata = read_media_metadata(
+                entry.path.to_string_lossy().to_string(),
+                entry.file_name.clone(),
+            )
+            .await;
+        }
+        if text_extraction::is_supported(&entry.file_name) {
+            entry_metadata = extract_document(
+                entry.path.to_string_lossy().to_string(),
+                entry.file_name.clone(),
+            )
+            .await;
+        }
+        if entry_type == FileType::IMAGE
+            && let Ok(entry_bytes) = tokio::fs::read(&entry.path).await
+        {
+            let entry_bytes = Bytes::from(entry_bytes);
+            let (processed_bytes, processed_metadata) =
+                process_image(entry_bytes.clone(), module_id, None).await;
+            if processed_bytes != entry_bytes
+                && tokio::fs::write(&entry.path, &processed_bytes).await.is_ok()
+            {
+                entry_size = processed_bytes.len() as u64;
+            }
+            entry_metadata = processed_metadata;
+        }
+        let mut new_m_file = MFile::new(
+            entry.file_name,
+            entry_type.to_string(),
+            entry.path.to_string_lossy().to_string(),
+            entry_size.to_string(),
+            module_id,
+            user_id,
+        );
+        // the folder of the entry inside the archive, '' for entries at its root
+        let mut metadata: serde_json::Value = entry_metadata
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_else(|| serde_json::json!({}));
+        metadata["archive"] = serde_json::json!({ "folder": entry.folder });
+        new_m_file.metadata = Some(metadata.to_string());
+        m_files.push(new_m_file);
+    }
+
+    let m_files = match repository::insert_mfiles_with_new_ids(db_conn, m_files) {
+        Ok(value) => value,
+        Err(error) => {
+            let _ = tokio::fs::remove_dir_all(dir_path).await;
+            return Err(error);
+        }
+    };
+
+    for m_file in m_files.iter() {
+        create_derived_files(m_file);
+    }
+
+    Ok(m_files)
+}
+
+/// Start background work that derives cached files from a freshly stored file.
+pub fn create_derived_files(m_file: &MFile) {
+    let config = &CONFIG;
+    let _file_path_string = m_file.file_path.clone().unwrap_or_default();
+    if config.thumbnail_on_upload && m_file.file_type == Some(FileType::IMAGE.to_string()) {
+        tokio::spawn(image::controller::create_thumbnails(_file_path_string));
+        return;
+    }
+    if m_file.file_type == Some(FileType::AUDIO.to_string()) {
+        let _file_name = m_file.file_name.clone().unwrap_or_default();
+        tokio::spawn(audio::controller::create_waveform(_file_path_string, _file_name));
+    }
+}
+
+/// Read EXIF/XMP of an uploaded image, rotate it upright and strip its metadata when the
+/// request or the module asks for it. Returns the bytes to store and the metadata column.
+async fn process_image(
+    file_bytes: Bytes,
+    module_id: i64,
+    strip_metadata: Option<bool>,
+) -> (Bytes, Option<String>) {
+    let config = &CONFIG;
+    let strip = strip_metadata
+        .unwrap_or_else(|| config.image_strip_metadata_module_ids.contains(&module_id));
+    let source = file_bytes.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        image_metadata::process(&source, strip, config.image_max_dimension)
+    })
+    .await;
+    match result {
+        Ok(Ok(value)) => {
+            let metadata = serde_json::json!({ "image": value.metadata }).to_string();
+            match value.bytes {
+                Some(bytes) => (Bytes::from(bytes), Some(metadata)),
+                None => (file_bytes, Some(metadata)),
+            }
+        }
+        Ok(Err(error)) => {
+            log::info!("read image metadata failed: {}", error);
+            (file_bytes, None)
+        }
+        Err(error) => {
+            log::error!("read image metadata failed: {}", error);
+            (file_bytes, None)
+        }
+    }
+}
+
+/// Read duration, codecs and tags of a stored audio or video file for the metadata column.
+async fn read_media_metadata(file_path: String, file_name: String) -> Option<String> {
+    let result = tokio::task::spawn_blocking(move || {
+        media_metadata::read(std::path::Path::new(&file_path), &file_name)
+    })
+    .await;
+    match result {
+        Ok(value) => value.map(|value| serde_json::json!({ "media": value }).to_string()),
+        Err(error) => {
+            log::error!("read media metadata failed: {}", error);
+            None
+        }
+    }
+}
+
+/// Extract the text of a stored document into the file cache, where the search index reads it,
+/// and return its properties for the metadata column.
+pub async fn extract_document(file_path: String, file_name: String) -> Option<String> {
+    let source_path = file_path.clone();
+    let result = tokio::task::spawn_blocking(move || {
+        text_extraction::extract(std::path::Path::new(&source_path), &file_name)
+    })
+    .await;
+    match result {
+        Ok(Some(value)) => {
+            file_cache::write(
+                &file_path,
+                text_extraction::TEXT_CACHE_KEY,
+                value.text.as_bytes(),
+            )
+            .await;
+            Some(serde_json::json!({ "document": value.properties }).to_string())
+        }
+        Ok(None) => None,
+        Err(error) => {
+            log::error!("extract document text failed: {}", error);
+            None
+        }
+    }
 }
 
 pub async fn update(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     mut multipart: Multipart,
 ) -> Result<(StatusCode, Json<AppResponse<MFile>>), AppError> {
     let mut file_bytes: Bytes = <Bytes>::new();
     let mut file_name: String = String::new();
     let mut file_type: String = String::new();
     let mut file_size: String = String::new();
-    let mut user_id: i64 = 0;
+    let user_id: i64 = _principal.user_id;
     let mut id: i64 = 0;
+    let mut strip_metadata: Option<bool> = None;
 
     while let Some(field) = multipart.next_field().await.unwrap() {
         let field_name = field.name().unwrap().to_string();
@@ -240,7 +540,10 @@ pub async fn update(
                 // Document types
                 "application/pdf"
                 | "application/msword"
-                | "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+                | "application/vnd.openxmlformats-officedocument.wordprocessingml.document"
+                | "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+                | "application/vnd.openxmlformats-officedocument.presentationml.presentation"
+                | "application/vnd.oasis.opendocument.text" => {
                     FileType::DOCUMENT
                 }
                 // Default case for unknown types
@@ -251,6 +554,7 @@ pub async fn update(
                             "mp3" | "ogg" => FileType::AUDIO,
                             "mp4" | "mkv" => FileType::VIDEO,
                             "jpg" | "jpeg" | "png" | "webp" => FileType::IMAGE,
+                            "pdf" | "docx" | "xlsx" | "pptx" | "odt" => FileType::DOCUMENT,
                             _ => FileType::UNKNOWN,
                         }
                     } else {
@@ -262,22 +566,21 @@ pub async fn update(
 
             continue;
         }
-        if field_name == "user_id".to_string() {
+        if field_name == "id".to_string() {
             let payload_tmp = field
                 .text()
                 .await
                 .map_err(|e| AppError::Other(e.to_string()))?;
-            user_id = payload_tmp.parse().unwrap();
+            id = payload_tmp.parse().unwrap();
 
             continue;
         }
-        if field_name == "id".to_string() {
+        if field_name == "strip_metadata" {
             let payload_tmp = field
                 .text()
                 .await
                 .map_err(|e| AppError::Other(e.to_string()))?;
-            id = payload_tmp.parse().unwrap();
-
+            strip_metadata = payload_tmp.parse().ok();
             continue;
         }
     }
@@ -309,6 +612,13 @@ pub async fn update(
             return Err(_error);
         }
     };
+    check_access(
+        &mut db_conn,
+        &_principal,
+        &_existing_data,
+        Permission::UPDATE,
+        FileAccess::EDITOR,
+    )?;
 
     let _existing_file_path = _existing_data.file_path.unwrap();
 
@@ -326,8 +636,16 @@ pub async fn update(
         }
     };
 
+    let mut metadata: Option<String> = None;
+    if file_type == FileType::IMAGE.to_string() {
+        let module_id = _existing_data.module_id.unwrap_or(0);
+        (file_bytes, metadata) = process_image(file_bytes, module_id, strip_metadata).await;
+        file_size = file_bytes.len().to_string();
+    }
+
     // remove exsiting data
     let _ = tokio::fs::remove_file(_existing_file_path.clone()).await;
+    file_cache::remove(&_existing_file_path).await;
 
     let uuid = Uuid::new_v4();
 
@@ -356,6 +674,13 @@ pub async fn update(
         return Err(AppError::InternalServerError);
     }
 
+    if file_type == FileType::AUDIO.to_string() || file_type == FileType::VIDEO.to_string() {
+        metadata = read_media_metadata(file_path.clone(), file_name.clone()).await;
+    }
+    if text_extraction::is_supported(&file_name) {
+        metadata = extract_document(file_path.clone(), file_name.clone()).await;
+    }
+
     let today_chrono = chrono::Utc::now().naive_utc();
 
     _existing_data.file_size = Some(file_size);
@@ -364,9 +689,13 @@ pub async fn update(
     _existing_data.file_path = Some(file_path.clone());
     _existing_data.modified_by = Some(user_id);
     _existing_data.modified_on = Some(today_chrono);
+    _existing_data.metadata = metadata;
 
     repository::update_mfile(&mut db_conn, _existing_data.clone())?;
 
+    create_derived_files(&_existing_data);
+    index_file(&_state.search_index_queue, _existing_data.clone());
+
     let status_code = StatusCode::OK;
     Ok((
         status_code,
@@ -382,11 +711,13 @@ pub async fn update(
 
 pub async fn download(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     Path(id): Path<i64>,
 ) -> impl IntoResponse {
     // find path file by id
     let mut _file_path_string = String::new();
     let mut _file_name = String::new();
+    let mut _watermark = None;
     // get db connection
     let db_conn_result = _state.diesel_pool_mysql.get();
     let mut db_conn;
@@ -401,6 +732,8 @@ pub async fn download(
     let find_by_id_result = repository::find_by_id(&mut db_conn, id);
     match find_by_id_result {
         Ok(Some(value)) => {
+            check_access(&mut db_conn, &_principal, &value, Permission::READ, FileAccess::VIEWER)?;
+            _watermark = image::controller::find_watermark(&_state, &value);
             _file_path_string = value.file_path.unwrap_or(String::new());
             _file_name = value.file_name.unwrap_or(String::new());
         }
@@ -412,6 +745,20 @@ pub async fn download(
         }
     };
 
+    // images of modules with a watermark policy are never served as stored
+    if let Some(watermark) = _watermark {
+        let contents = image::controller::watermark_image(&_file_path_string, watermark).await?;
+        let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+            .header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", _file_name),
+            )
+            .header("Content-Type", "application/octet-stream")
+            .body(contents.into())
+            .unwrap();
+        return Ok(response_builder);
+    }
+
     let file_path = PathBuf::from(_file_path_string);
 
     let open_file_response = match File::open(&file_path).await {
@@ -440,11 +787,13 @@ pub async fn download(
 
 pub async fn stream(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     Path(id): Path<i64>,
 ) -> impl IntoResponse {
     // find path file by id
     let mut _file_path_string = String::new();
     let mut _file_name = String::new();
+    let mut _watermark = None;
     // get db connection
     let db_conn_result = _state.diesel_pool_mysql.get();
     let mut db_conn;
@@ -459,6 +808,8 @@ pub async fn stream(
     let find_by_id_result = repository::find_by_id(&mut db_conn, id);
     match find_by_id_result {
         Ok(Some(value)) => {
+            check_access(&mut db_conn, &_principal, &value, Permission::READ, FileAccess::VIEWER)?;
+            _watermark = image::controller::find_watermark(&_state, &value);
             _file_path_string = value.file_path.unwrap_or(String::new());
             _file_name = value.file_name.unwrap_or(String::new());
         }
@@ -470,6 +821,20 @@ pub async fn stream(
         }
     };
 
+    // images of modules with a watermark policy are never served as stored
+    if let Some(watermark) = _watermark {
+        let contents = image::controller::watermark_image(&_file_path_string, watermark).await?;
+        let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+            .header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", _file_name),
+            )
+            .header("Content-Type", "application/octet-stream")
+            .body(contents.into())
+            .unwrap();
+        return Ok(response_builder);
+    }
+
     let file_path = PathBuf::from(_file_path_string);
 
     let file = File::open(&file_path)
@@ -492,6 +857,7 @@ pub async fn stream(
 
 pub async fn delete_file(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     Path(id): Path<i64>,
 ) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
     // find path file by id
@@ -511,6 +877,7 @@ pub async fn delete_file(
     let find_by_id_result = repository::find_by_id(&mut db_conn, id);
     match find_by_id_result {
         Ok(Some(value)) => {
+            check_access(&mut db_conn, &_principal, &value, Permission::DELETE, FileAccess::OWNER)?;
             _file_path_string = value.file_path.unwrap_or(String::new());
             _file_name = value.file_name.unwrap_or(String::new());
         }
@@ -523,7 +890,10 @@ pub async fn delete_file(
     };
 
     let _delete_result = repository::delete_by_id(&mut db_conn, id)?;
+    unindex_file(&_state.search_index_queue, id);
+    share_repository::delete_by_file_id(&mut db_conn, id)?;
 
+    file_cache::remove(&_file_path_string).await;
     let file_path = PathBuf::from(_file_path_string);
 
     let _remove_file_result = tokio::fs::remove_file(file_path.clone())
@@ -545,6 +915,7 @@ pub async fn delete_file(
 
 pub async fn rename(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     Json(m_file_rename_request): Json<MFileRenameRequest>,
 ) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
     let _is_valid = match m_file_rename_request.validate() {
@@ -581,6 +952,14 @@ pub async fn rename(
         }
     };
 
+    check_access(
+        &mut db_conn,
+        &_principal,
+        &_existing_data,
+        Permission::UPDATE,
+        FileAccess::EDITOR,
+    )?;
+
     // rename file
     let new_filename = m_file_rename_request.file_name.unwrap();
     let existing_file_path = _existing_data.file_path.unwrap();
@@ -589,18 +968,20 @@ pub async fn rename(
         new_file_path = format!("{}/{}", value.to_string(), new_filename.clone());
     }
 
-    tokio::fs::rename(existing_file_path, new_file_path.clone())
+    tokio::fs::rename(existing_file_path.clone(), new_file_path.clone())
         .await
         .map_err(|err| AppError::Other(format!("rename file error {err}")))?;
+    file_cache::remove(&existing_file_path).await;
 
     // update data in database
     let today_chrono = chrono::Utc::now().naive_utc();
     _existing_data.file_path = Some(new_file_path);
     _existing_data.file_name = Some(new_filename);
-    _existing_data.modified_by = Some(m_file_rename_request.user_id.unwrap());
+    _existing_data.modified_by = Some(_principal.user_id);
     _existing_data.modified_on = Some(today_chrono);
 
     repository::update_mfile(&mut db_conn, _existing_data.clone())?;
+    index_file(&_state.search_index_queue, _existing_data.clone());
 
     let status_code = StatusCode::OK;
     return Ok((
@@ -617,6 +998,7 @@ pub async fn rename(
 
 pub async fn copy(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     Json(m_file_copy_move_request): Json<MFileCopyMoveRequest>,
 ) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
     let _is_valid = match m_file_copy_move_request.validate() {
@@ -654,6 +1036,10 @@ pub async fn copy(
         }
     };
 
+    check_access(&mut db_conn, &_principal, &_existing_data, Permission::READ, FileAccess::VIEWER)?;
+    // the copy is owned by the principal
+    _principal.check(Permission::UPLOAD, _existing_data.module_id)?;
+
     // copy file
     let mut new_file_path: String = String::new();
     let mut new_file_name = String::new();
@@ -680,8 +1066,13 @@ pub async fn copy(
     _new_data.id = date_now.and_utc().timestamp();
     _new_data.file_path = Some(new_file_path);
     _new_data.file_name = Some(new_file_name);
+    _new_data.created_by = _principal.user_id;
+    _new_data.created_on = date_now;
+    _new_data.modified_by = None;
+    _new_data.modified_on = None;
 
     repository::insert_mfile(&mut db_conn, _new_data.clone())?;
+    index_file(&_state.search_index_queue, _new_data.clone());
 
     let status_code = StatusCode::OK;
     return Ok((
@@ -698,6 +1089,7 @@ pub async fn copy(
 
 pub async fn move_file(
     Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
     Json(m_file_copy_move_request): Json<MFileCopyMoveRequest>,
 ) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
     let _is_valid = match m_file_copy_move_request.validate() {
@@ -735,6 +1127,14 @@ pub async fn move_file(
         }
     };
 
+    check_access(
+        &mut db_conn,
+        &_principal,
+        &_existing_data,
+        Permission::UPDATE,
+        FileAccess::EDITOR,
+    )?;
+
     // move file
     let new_file_path = m_file_copy_move_request.file_path.unwrap();
     let existing_file_path = _existing_data.file_path.unwrap();
@@ -743,6 +1143,7 @@ pub async fn move_file(
         .await
         .map_err(|err| AppError::Other(format!("copy file error {err}")))?;
 
+    file_cache::remove(&existing_file_path).await;
     let file_path_buf = PathBuf::from(existing_file_path);
 
     let _remove_file_result = tokio::fs::remove_file(file_path_buf)
@@ -755,10 +1156,11 @@ pub async fn move_file(
         _existing_data.file_name = Some(value.to_string());
     }
     _existing_data.file_path = Some(new_file_path);
-    _existing_data.modified_by = Some(m_file_copy_move_request.user_id.unwrap());
+    _existing_data.modified_by = Some(_principal.user_id);
     _existing_data.modified_on = Some(today_chrono);
 
     repository::update_mfile(&mut db_conn, _existing_data.clone())?;
+    index_file(&_state.search_index_queue, _existing_data.clone());
 
     let status_code = StatusCode::OK;
     return Ok((
@@ -772,3 +1174,291 @@ pub async fn move_file(
         }),
     ));
 }
+
+pub async fn archive(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Json(m_file_archive_request): Json<MFileArchiveRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = m_file_archive_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    // find selected data, requested files must all be readable, selectors only pick the readable
+    // ones
+    let _data_vec: Vec<MFile> = match m_file_archive_request.ids {
+        Some(ids) => {
+            let value = repository::find_by_ids(&mut db_conn, ids)?;
+            for m_file in value.iter() {
+                check_access(
+                    &mut db_conn,
+                    &_principal,
+                    m_file,
+                    Permission::READ,
+                    FileAccess::VIEWER,
+                )?;
+            }
+            value
+        }
+        None => {
+            let mut path_prefix: Option<String> = None;
+            if let Some(folder) = m_file_archive_request.folder {
+                let folder = folder.trim_matches('/').to_string();
+                if folder.split('/').any(|value| value == "..") {
+                    return Err(AppError::BadRequest(format!("invalid folder: {folder}")));
+                }
+                path_prefix = Some(format!("{}/{}", CONFIG.file_root_dir, folder));
+            }
+            repository::find_by_selector(
+                &mut db_conn,
+                m_file_archive_request.module_id,
+                m_file_archive_request.user_id,
+                path_prefix,
+                &FileScope::readable_by(&_principal),
+            )?
+        }
+    };
+    if _data_vec.is_empty() {
+        return Err(AppError::NotFound);
+    }
+
+    // build archive entries, images of modules with a watermark policy are added watermarked
+    let mut used_names: HashSet<String> = HashSet::new();
+    let mut entries: Vec<ArchiveEntry> = Vec::new();
+    for value in _data_vec {
+        let _watermark = image::controller::find_watermark(&_state, &value);
+        let mut _file_path_string = value.file_path.unwrap_or(String::new());
+        if let Some(watermark) = _watermark {
+            let watermark_path =
+                image::controller::watermark_path(&_file_path_string, watermark).await?;
+            _file_path_string = watermark_path.to_string_lossy().to_string();
+        }
+        let metadata = match tokio::fs::metadata(&_file_path_string).await {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                log::info!("skip file {}: {}", value.id, error);
+                continue;
+            }
+        };
+        let _file_name = value.file_name.unwrap_or(value.id.to_string());
+        entries.push(ArchiveEntry {
+            name: archive::unique_entry_name(&mut used_names, &_file_name),
+            path: PathBuf::from(_file_path_string),
+            size: metadata.len(),
+            modified_on: Some(value.modified_on.unwrap_or(value.created_on)),
+        });
+    }
+    if entries.is_empty() {
+        return Err(AppError::NotFound);
+    }
+
+    let archive_name = attachment_name(&m_file_archive_request.archive_name.unwrap_or_default());
+    let archive_name = if archive_name.is_empty() {
+        "archive".to_string()
+    } else {
+        archive_name
+    };
+
+    let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.zip\"", archive_name),
+        )
+        .header("Content-Type", "application/zip")
+        .body(Body::from_stream(archive::zip_stream(entries)))
+        .map_err(|error| AppError::BadRequest(format!("invalid archive name: {error}")))?;
+
+    Ok(response_builder)
+}
+
+pub async fn list_entries(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Path(id): Path<i64>,
+) -> Result<(StatusCode, Json<AppResponse<Vec<MFileEntryResponse>>>), AppError> {
+    let (file_path, _) = find_archive_path(&_state, &_principal, id).await?;
+    let Some(format) = archive::detect_file_format(&file_path)
+        .map_err(|error| AppError::Other(format!("read file failed: {error}")))?
+    else {
+        return Err(AppError::BadRequest(format!("file is not an archive, id: {id}")));
+    };
+
+    let entries = tokio::task::spawn_blocking(move || archive::list_entries(&file_path, format))
+        .await
+        .map_err(|error| AppError::Other(format!("list entries failed: {error}")))?
+        .map_err(|error| archive_error("list entries failed", error))?;
+
+    let entries_response: Vec<MFileEntryResponse> = entries
+        .into_iter()
+        .map(|entry| MFileEntryResponse {
+            name: entry.name,
+            size: entry.size,
+            compressed_size: entry.compressed_size,
+            modified_on: entry.modified_on,
+            is_dir: entry.is_dir,
+        })
+        .collect();
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(entries_response),
+            error: None,
+        }),
+    ))
+}
+
+pub async fn download_entry(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Path((id, entry_name)): Path<(i64, String)>,
+) -> impl IntoResponse {
+    let (file_path, _watermark) = find_archive_path(&_state, &_principal, id).await?;
+    let Some(format) = archive::detect_file_format(&file_path)
+        .map_err(|error| AppError::Other(format!("read file failed: {error}")))?
+    else {
+        return Err(AppError::BadRequest(format!("file is not an archive, id: {id}")));
+    };
+
+    let entry_result = archive::entry_stream(file_path, format, entry_name.clone())
+        .await
+        .map_err(|error| archive_error("read entry failed", error))?;
+    let Some((entry_size, mut entry_stream)) = entry_result else {
+        return Err(AppError::NotFound);
+    };
+
+    let _file_name = attachment_name(entry_name.rsplit('/').next().unwrap_or(&entry_name));
+
+    // image entries of archives in modules with a watermark policy are never served as stored,
+    // images are told apart by their content, not by their name
+    let mut first_chunk = Bytes::new();
+    if _watermark.is_some() {
+        first_chunk = match entry_stream.next().await {
+            Some(value) => {
+                value.map_err(|error| AppError::Other(format!("read entry failed: {error}")))?
+            }
+            None => Bytes::new(),
+        };
+    }
+    if let Some(watermark) = _watermark
+        && ::image::guess_format(&first_chunk).is_ok()
+    {
+        if entry_size > MAX_WATERMARK_ENTRY_SIZE {
+            return Err(AppError::BadRequest(format!(
+                "image entry is larger than {} bytes",
+                MAX_WATERMARK_ENTRY_SIZE
+            )));
+        }
+        let mut entry_bytes = first_chunk.to_vec();
+        while let Some(chunk) = entry_stream.next().await {
+            let chunk =
+                chunk.map_err(|error| AppError::Other(format!("read entry failed: {error}")))?;
+            entry_bytes.extend_from_slice(&chunk);
+        }
+        let encoded_image = tokio::task::spawn_blocking(move || {
+            image_processing::watermark(&entry_bytes, &watermark, CONFIG.image_max_dimension)
+        })
+        .await
+        .map_err(|error| AppError::Other(format!("watermark image failed: {error}")))?
+        .map_err(|error| AppError::BadRequest(format!("watermark image failed: {error}")))?;
+
+        let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+            .header(
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", _file_name),
+            )
+            .header("Content-Type", "application/octet-stream")
+            .body(encoded_image.bytes.into())
+            .map_err(|error| AppError::BadRequest(format!("invalid entry name: {error}")))?;
+        return Ok(response_builder);
+    }
+
+    // the chunk read to recognise images goes out first
+    let entry_body = tokio_stream::iter(vec![Ok(first_chunk)]).chain(entry_stream);
+    let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"{}\"", _file_name),
+        )
+        .header("Content-Type", "application/octet-stream")
+        .header("Content-Length", entry_size)
+        .body(Body::from_stream(entry_body))
+        .map_err(|error| AppError::BadRequest(format!("invalid entry name: {error}")))?;
+
+    Ok(response_builder)
+}
+
+// archives that cannot be decoded or go over the extract limits are the client's fault
+fn archive_error(context: &str, error: std::io::Error) -> AppError {
+    if error.kind() == std::io::ErrorKind::InvalidData {
+        return AppError::BadRequest(error.to_string());
+    }
+    AppError::Other(format!("{context}: {error}"))
+}
+
+// a file name usable in a Content-Disposition header, quotes and path separators are replaced
+// and control characters, which are not allowed in a header value, removed
+fn attachment_name(name: &str) -> String {
+    name.replace(['"', '/', '\\'], "_")
+        .chars()
+        .filter(|value| !value.is_control())
+        .collect()
+}
+
+async fn find_archive_path(
+    _state: &AppState,
+    _principal: &Principal,
+    id: i64,
+) -> Result<(PathBuf, Option<Arc<Watermark>>), AppError> {
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}, id: {id}")));
+        }
+    };
+
+    let find_by_id_result = repository::find_by_id(&mut db_conn, id);
+    let (_file_path_string, _watermark) = match find_by_id_result {
+        Ok(Some(value)) => {
+            check_access(&mut db_conn, _principal, &value, Permission::READ, FileAccess::VIEWER)?;
+            let _watermark = value
+                .module_id
+                .and_then(|module_id| _state.watermarks.get(&module_id).cloned());
+            (value.file_path.unwrap_or(String::new()), _watermark)
+        }
+        Ok(None) => {
+            return Err(AppError::NotFound);
+        }
+        Err(error) => {
+            return Err(error);
+        }
+    };
+
+    let file_path = PathBuf::from(_file_path_string);
+    match tokio::fs::try_exists(&file_path).await {
+        Ok(true) => Ok((file_path, _watermark)),
+        Ok(false) => Err(AppError::NotFound),
+        Err(error) => Err(AppError::Other(format!("find file error: {error}"))),
+    }
+}
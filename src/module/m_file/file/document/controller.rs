@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use validator::Validate;
+
+use crate::{
+    config::environment::CONFIG,
+    dto::{
+        enumerator::{file_access::FileAccess, permission::Permission},
+        principal::Principal,
+        response::{
+            app_error::AppError, app_response::AppResponse, pagination_response::PaginatedResponse,
+        },
+    },
+    module::m_file::{
+        repository,
+        schema::{MFile, MFilePreviewRequest, MFilePreviewResponse},
+        share::controller::check_access,
+    },
+    state::AppState,
+    util::{
+        document_preview::{self, Preview},
+        file_cache,
+    },
+};
+
+const PREVIEW_CACHE_KEY: &str = "preview.json";
+
+pub async fn preview(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Path(id): Path<i64>,
+    Query(_preview_request): Query<MFilePreviewRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = _preview_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    let _existing_data = find_document(&_state, &_principal, id)?;
+    let _file_path_string = _existing_data.file_path.unwrap_or_default();
+    let _file_name = _existing_data.file_name.unwrap_or_default();
+
+    let preview_data = match file_cache::read(&_file_path_string, PREVIEW_CACHE_KEY)
+        .await
+        .and_then(|value| serde_json::from_slice::<Preview>(&value).ok())
+    {
+        Some(value) => value,
+        None => build_preview(&_file_path_string, &_file_name).await?,
+    };
+
+    let preview_response = match preview_data {
+        Preview::Html {
+            html,
+            charset,
+            truncated,
+        } => MFilePreviewResponse::Html {
+            html,
+            charset,
+            truncated,
+        },
+        Preview::Table { sheets, truncated } => {
+            let sheet_names: Vec<String> = sheets.iter().map(|sheet| sheet.name.clone()).collect();
+            let sheet = match &_preview_request.sheet {
+                Some(value) => sheets.into_iter().find(|sheet| sheet.name == *value),
+                None => sheets.into_iter().next(),
+            }
+            .ok_or(AppError::NotFound)?;
+
+            let _page = _preview_request.page.unwrap_or(0);
+            let _size = _preview_request.size.unwrap_or(50);
+            let total_of_elements = sheet.rows.len() as i64;
+            let content: Vec<Vec<String>> = sheet
+                .rows
+                .into_iter()
+                // pages past the last row are empty, even when page * size overflows
+                .skip(_page.saturating_mul(_size) as usize)
+                .take(_size as usize)
+                .collect();
+            MFilePreviewResponse::Table {
+                sheets: sheet_names,
+                sheet: sheet.name,
+                columns: sheet.columns,
+                rows: PaginatedResponse {
+                    content,
+                    total_of_elements,
+                    total_of_pages: (total_of_elements + _size - 1) / _size,
+                    next: None,
+                    prev: None,
+                    facets: None,
+                },
+                truncated,
+            }
+        }
+    };
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(preview_response),
+            error: None,
+        }),
+    ))
+}
+
+async fn build_preview(file_path: &str, file_name: &str) -> Result<Preview, AppError> {
+    let config = &CONFIG;
+    let source_path = std::path::PathBuf::from(file_path);
+    let source_name = file_name.to_string();
+    let (max_size, max_rows) = (config.preview_max_size, config.preview_max_rows);
+    let preview_data = tokio::task::spawn_blocking(move || {
+        document_preview::render(&source_path, &source_name, max_size, max_rows)
+    })
+    .await
+    .map_err(|error| AppError::Other(format!("create preview failed: {error}")))?
+    .map_err(|error| AppError::BadRequest(format!("unreadable document: {error}")))?;
+
+    let preview_bytes = serde_json::to_vec(&preview_data)
+        .map_err(|error| AppError::Other(format!("create preview failed: {error}")))?;
+    file_cache::write(file_path, PREVIEW_CACHE_KEY, &preview_bytes).await;
+    Ok(preview_data)
+}
+
+fn find_document(_state: &AppState, _principal: &Principal, id: i64) -> Result<MFile, AppError> {
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!(
+                "get connection failed {error}, id: {id}"
+            )));
+        }
+    };
+
+    let find_by_id_result = repository::find_by_id(&mut db_conn, id);
+    match find_by_id_result {
+        Ok(Some(value)) => {
+            let file_name = value.file_name.clone().unwrap_or_default();
+            if !document_preview::is_supported(&file_name) {
+                return Err(AppError::BadRequest(format!(
+                    "preview is not supported for {file_name}, id: {id}"
+                )));
+            }
+            check_access(&mut db_conn, _principal, &value, Permission::READ, FileAccess::VIEWER)?;
+            Ok(value)
+        }
+        Ok(None) => Err(AppError::NotFound),
+        Err(error) => Err(error),
+    }
+}
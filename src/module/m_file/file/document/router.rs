@@ -0,0 +1,13 @@
+use axum::{middleware::from_fn_with_state, routing::get, Router};
+
+use crate::{
+    dto::enumerator::permission::Permission, middleware::auth_middleware::authorize,
+    module::m_file::file::document::controller::preview,
+};
+
+pub fn new() -> Router {
+    Router::new().route(
+        "/{id}/preview",
+        get(preview).route_layer(from_fn_with_state(Permission::READ, authorize)),
+    )
+}
@@ -0,0 +1,293 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::{Extension, Path, Query},
+    response::IntoResponse,
+};
+use validator::Validate;
+
+use crate::{
+    config::environment::CONFIG,
+    dto::{
+        enumerator::{
+            file_access::FileAccess, file_type::FileType, image_fit::ImageFit,
+            permission::Permission,
+        },
+        principal::Principal,
+        response::app_error::AppError,
+    },
+    module::m_file::{
+        repository,
+        schema::{MFile, MFileThumbnailRequest, MFileTransformRequest},
+        share::controller::check_access,
+    },
+    state::AppState,
+    util::{
+        file_cache,
+        image_processing::{self, Transform},
+        watermark::Watermark,
+    },
+};
+
+// transformed variants cached per image, the oldest are evicted first
+const MAX_TRANSFORM_CACHE_ENTRIES: usize = 20;
+
+pub async fn thumbnail(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Path(id): Path<i64>,
+    Query(_thumbnail_request): Query<MFileThumbnailRequest>,
+) -> impl IntoResponse {
+    let config = &CONFIG;
+    let size = match _thumbnail_request.size {
+        Some(value) => value,
+        None => *config.thumbnail_sizes.first().unwrap_or(&256),
+    };
+    if !config.thumbnail_sizes.contains(&size) {
+        return Err(AppError::BadRequest(format!(
+            "size must be one of {:?}",
+            config.thumbnail_sizes
+        )));
+    }
+
+    let _existing_data = find_image(&_state, &_principal, id)?;
+    let watermark = find_watermark(&_state, &_existing_data);
+    let _file_path_string = _existing_data.file_path.unwrap_or(String::new());
+
+    // thumbnails of modules with a watermark policy are made from the watermarked image
+    let thumbnail_bytes = match watermark {
+        Some(value) => watermark_thumbnail(&_file_path_string, size, value).await?,
+        None => match file_cache::read(&_file_path_string, &format!("thumbnail_{}", size)).await {
+            Some(value) => value,
+            None => create_thumbnail(&_file_path_string, size).await?,
+        },
+    };
+    let content_type = image::guess_format(&thumbnail_bytes)
+        .map(|value| value.to_mime_type())
+        .unwrap_or("application/octet-stream");
+
+    let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+        .header("Content-Type", content_type)
+        .header("Cache-Control", "private, max-age=86400")
+        .body(thumbnail_bytes.into())
+        .unwrap();
+
+    Ok(response_builder)
+}
+
+pub async fn transform(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Path(id): Path<i64>,
+    Query(_transform_request): Query<MFileTransformRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = _transform_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    let config = &CONFIG;
+    for value in [_transform_request.width, _transform_request.height] {
+        if value.unwrap_or(0) > config.image_max_dimension {
+            return Err(AppError::BadRequest(format!(
+                "width and height must not exceed {}",
+                config.image_max_dimension
+            )));
+        }
+    }
+    let rotate = _transform_request.rotate.unwrap_or(0);
+    if ![0, 90, 180, 270].contains(&rotate) {
+        return Err(AppError::BadRequest(
+            "rotate must be one of 0, 90, 180, 270".to_string(),
+        ));
+    }
+    let mut crop: Option<(u32, u32, u32, u32)> = None;
+    if let Some(value) = _transform_request.crop {
+        let parts: Vec<u32> = value
+            .split(',')
+            .filter_map(|part| part.parse().ok())
+            .collect();
+        if parts.len() != 4 || parts[2] == 0 || parts[3] == 0 {
+            return Err(AppError::BadRequest(
+                "crop must be x,y,width,height".to_string(),
+            ));
+        }
+        crop = Some((parts[0], parts[1], parts[2], parts[3]));
+    }
+
+    let _existing_data = find_image(&_state, &_principal, id)?;
+    let watermark = find_watermark(&_state, &_existing_data);
+    let _file_path_string = _existing_data.file_path.unwrap_or(String::new());
+
+    let image_transform = Transform {
+        width: _transform_request.width,
+        height: _transform_request.height,
+        fit: _transform_request.fit.unwrap_or(ImageFit::CONTAIN),
+        crop,
+        rotate,
+        format: _transform_request.format,
+        quality: _transform_request.quality.unwrap_or(80),
+        watermark,
+    };
+
+    let cache_key = image_transform.cache_key();
+    let image_bytes = match file_cache::read(&_file_path_string, &cache_key).await {
+        Some(value) => value,
+        None => {
+            let source_bytes = tokio::fs::read(&_file_path_string)
+                .await
+                .map_err(|_| AppError::NotFound)?;
+            let encoded_image = tokio::task::spawn_blocking(move || {
+                image_processing::transform(
+                    &source_bytes,
+                    &image_transform,
+                    CONFIG.image_max_dimension,
+                )
+            })
+            .await
+            .map_err(|error| AppError::Other(format!("transform image failed: {error}")))?
+            .map_err(|error| AppError::BadRequest(format!("transform image failed: {error}")))?;
+            file_cache::evict(&_file_path_string, "transform_", MAX_TRANSFORM_CACHE_ENTRIES).await;
+            file_cache::write(&_file_path_string, &cache_key, &encoded_image.bytes).await;
+            encoded_image.bytes
+        }
+    };
+    let content_type = image::guess_format(&image_bytes)
+        .map(|value| value.to_mime_type())
+        .unwrap_or("application/octet-stream");
+
+    let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+        .header("Content-Type", content_type)
+        .header("Cache-Control", "private, max-age=86400")
+        .body(image_bytes.into())
+        .unwrap();
+
+    Ok(response_builder)
+}
+
+/// Watermark of the module of an image, `None` for other files and for modules without a policy.
+pub fn find_watermark(_state: &AppState, m_file: &MFile) -> Option<Arc<Watermark>> {
+    if m_file.file_type != Some(FileType::IMAGE.to_string()) {
+        return None;
+    }
+    _state.watermarks.get(&m_file.module_id?).cloned()
+}
+
+/// The stored image with the watermark drawn on it, cached per policy. The stored file is left
+/// untouched.
+pub async fn watermark_image(
+    file_path: &str,
+    watermark: Arc<Watermark>,
+) -> Result<Vec<u8>, AppError> {
+    let cache_key = format!("watermark_{}", watermark.fingerprint);
+    if let Some(value) = file_cache::read(file_path, &cache_key).await {
+        return Ok(value);
+    }
+
+    let source_bytes = tokio::fs::read(file_path)
+        .await
+        .map_err(|_| AppError::NotFound)?;
+    let encoded_image = tokio::task::spawn_blocking(move || {
+        image_processing::watermark(&source_bytes, &watermark, CONFIG.image_max_dimension)
+    })
+    .await
+    .map_err(|error| AppError::Other(format!("watermark image failed: {error}")))?
+    .map_err(|error| AppError::BadRequest(format!("watermark image failed: {error}")))?;
+    file_cache::write(file_path, &cache_key, &encoded_image.bytes).await;
+    Ok(encoded_image.bytes)
+}
+
+/// Path of the watermarked image in the cache, see `watermark_image`, for serving it as a file.
+pub async fn watermark_path(
+    file_path: &str,
+    watermark: Arc<Watermark>,
+) -> Result<PathBuf, AppError> {
+    let cache_key = format!("watermark_{}", watermark.fingerprint);
+    let cache_path = file_cache::cache_path(file_path, &cache_key);
+    watermark_image(file_path, watermark).await?;
+    match tokio::fs::try_exists(&cache_path).await {
+        Ok(true) => Ok(cache_path),
+        _ => Err(AppError::Other(format!("watermark image failed: {file_path} is not cached"))),
+    }
+}
+
+async fn watermark_thumbnail(
+    file_path: &str,
+    size: u32,
+    watermark: Arc<Watermark>,
+) -> Result<Vec<u8>, AppError> {
+    let cache_key = format!("thumbnail_{}_watermark_{}", size, watermark.fingerprint);
+    if let Some(value) = file_cache::read(file_path, &cache_key).await {
+        return Ok(value);
+    }
+
+    let source_bytes = watermark_image(file_path, watermark).await?;
+    let encoded_image = tokio::task::spawn_blocking(move || {
+        image_processing::thumbnail(&source_bytes, size, CONFIG.image_max_dimension)
+    })
+    .await
+    .map_err(|error| AppError::Other(format!("create thumbnail failed: {error}")))?
+    .map_err(|error| AppError::BadRequest(format!("unsupported image: {error}")))?;
+    file_cache::write(file_path, &cache_key, &encoded_image.bytes).await;
+    Ok(encoded_image.bytes)
+}
+
+/// Create every configured thumbnail size of an uploaded image.
+pub async fn create_thumbnails(file_path: String) {
+    for size in CONFIG.thumbnail_sizes.iter() {
+        if let Err(error) = create_thumbnail(&file_path, *size).await {
+            log::error!("create thumbnail failed: {:?}, file: {}", error, file_path);
+            return;
+        }
+    }
+}
+
+async fn create_thumbnail(file_path: &str, size: u32) -> Result<Vec<u8>, AppError> {
+    let source_bytes = tokio::fs::read(file_path)
+        .await
+        .map_err(|_| AppError::NotFound)?;
+    let encoded_image =
+        tokio::task::spawn_blocking(move || {
+            image_processing::thumbnail(&source_bytes, size, CONFIG.image_max_dimension)
+        })
+            .await
+            .map_err(|error| AppError::Other(format!("create thumbnail failed: {error}")))?
+            .map_err(|error| AppError::BadRequest(format!("unsupported image: {error}")))?;
+
+    file_cache::write(
+        file_path,
+        &format!("thumbnail_{}", size),
+        &encoded_image.bytes,
+    )
+    .await;
+    Ok(encoded_image.bytes)
+}
+
+fn find_image(_state: &AppState, _principal: &Principal, id: i64) -> Result<MFile, AppError> {
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!(
+                "get connection failed {error}, id: {id}"
+            )));
+        }
+    };
+
+    let find_by_id_result = repository::find_by_id(&mut db_conn, id);
+    match find_by_id_result {
+        Ok(Some(value)) => {
+            if value.file_type != Some(FileType::IMAGE.to_string()) {
+                return Err(AppError::BadRequest(format!("file is not an image, id: {id}")));
+            }
+            check_access(&mut db_conn, _principal, &value, Permission::READ, FileAccess::VIEWER)?;
+            Ok(value)
+        }
+        Ok(None) => Err(AppError::NotFound),
+        Err(error) => Err(error),
+    }
+}
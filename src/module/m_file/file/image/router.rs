@@ -0,0 +1,13 @@
+use axum::{middleware::from_fn_with_state, routing::get, Router};
+
+use crate::{
+    dto::enumerator::permission::Permission,
+    middleware::auth_middleware::authorize,
+    module::m_file::file::image::controller::{thumbnail, transform},
+};
+
+pub fn new() -> Router {
+    Router::new()
+        .route("/{id}/thumbnail", get(thumbnail).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .route("/{id}/transform", get(transform).route_layer(from_fn_with_state(Permission::READ, authorize)))
+}
@@ -1,2 +1,6 @@
+pub mod audio;
 pub mod controller;
-pub mod router;
\ No newline at end of file
+pub mod document;
+pub mod image;
+pub mod pdf;
+pub mod router;
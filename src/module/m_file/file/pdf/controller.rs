@@ -0,0 +1,461 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{
+    extract::{Extension, Path},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use diesel::MysqlConnection;
+use lopdf::Document;
+use serde_json::{json, Value as JsonValue};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    config::environment::CONFIG,
+    dto::{
+        enumerator::{file_access::FileAccess, file_type::FileType, permission::Permission},
+        principal::Principal,
+        response::{app_error::AppError, app_response::AppResponse},
+    },
+    module::m_file::{
+        file::controller::extract_document,
+        repository,
+        schema::{
+            MFile, MFilePdfMergeRequest, MFilePdfResponse, MFilePdfRotateRequest,
+            MFilePdfSplitRequest,
+        },
+        search::controller::index_file,
+        share::controller::check_access,
+    },
+    state::AppState,
+    util::{file_cache, pdf_processing},
+};
+
+// name, content and provenance of a produced file
+type PdfResult = (String, Vec<u8>, JsonValue);
+
+// sources of one operation are loaded whole into memory, their total size is bounded
+const MAX_SOURCE_SIZE: u64 = 256 * 1024 * 1024;
+
+pub async fn info(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Path(id): Path<i64>,
+) -> impl IntoResponse {
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!(
+                "get connection failed {error}, id: {id}"
+            )));
+        }
+    };
+
+    let _existing_data = find_pdfs(&mut db_conn, &_principal, &[id])?.remove(0);
+    let source_path = PathBuf::from(_existing_data.file_path.unwrap_or_default());
+    let page_count = run_blocking(move || {
+        let document = load_pdf(&source_path)?;
+        Ok(pdf_processing::page_count(&document))
+    })
+    .await?;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(MFilePdfResponse { page_count }),
+            error: None,
+        }),
+    ))
+}
+
+/// Append the pages of several PDFs into a new file.
+pub async fn merge(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Json(m_file_pdf_merge_request): Json<MFilePdfMergeRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = m_file_pdf_merge_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let ids = m_file_pdf_merge_request.ids.unwrap_or_default();
+    let sources = find_pdfs(&mut db_conn, &_principal, &ids)?;
+    let source_paths: Vec<PathBuf> = sources.iter().map(source_path).collect();
+    let file_bytes = run_blocking(move || {
+        let mut documents = Vec::new();
+        for path in source_paths.iter() {
+            documents.push(load_pdf(path)?);
+        }
+        let sources: Vec<(&Document, Vec<u32>)> = documents
+            .iter()
+            .map(|document| (document, (1..=pdf_processing::page_count(document)).collect()))
+            .collect();
+        let mut output = pdf_processing::assemble(&sources).map_err(pdf_error)?;
+        pdf_processing::to_bytes(&mut output).map_err(pdf_error)
+    })
+    .await?;
+
+    let mut file_name = m_file_pdf_merge_request
+        .file_name
+        .unwrap_or("merged.pdf".to_string())
+        .replace(['/', '\\'], "_");
+    if !file_name.to_lowercase().ends_with(".pdf") {
+        file_name = format!("{file_name}.pdf");
+    }
+    let provenance = json!({
+        "operation": "merge",
+        "sources": sources.iter().map(|value| provenance_source(value, None)).collect::<Vec<_>>(),
+    });
+    let module_id = m_file_pdf_merge_request
+        .module_id
+        .or(sources[0].module_id)
+        .unwrap_or(0);
+    _principal.check(Permission::UPLOAD, Some(module_id))?;
+    let user_id = _principal.user_id;
+    let mut m_files = store_pdfs(
+        &_state,
+        &mut db_conn,
+        module_id,
+        user_id,
+        vec![(file_name, file_bytes, provenance)],
+    )
+    .await?;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(m_files.remove(0)),
+            error: None,
+        }),
+    ))
+}
+
+/// Extract page ranges of a PDF, one new file per range.
+pub async fn split(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Json(m_file_pdf_split_request): Json<MFilePdfSplitRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = m_file_pdf_split_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let id = m_file_pdf_split_request.id.unwrap_or(0);
+    let _existing_data = find_pdfs(&mut db_conn, &_principal, &[id])?.remove(0);
+    let ranges = m_file_pdf_split_request.ranges.unwrap_or_default();
+    let source = source_path(&_existing_data);
+    let job_ranges = ranges.clone();
+    let outputs = run_blocking(move || {
+        let document = load_pdf(&source)?;
+        let page_count = pdf_processing::page_count(&document);
+        let mut outputs = Vec::new();
+        for range in job_ranges.iter() {
+            let page_numbers = pdf_processing::parse_page_ranges(range, page_count)
+                .map_err(AppError::BadRequest)?;
+            let mut output =
+                pdf_processing::assemble(&[(&document, page_numbers)]).map_err(pdf_error)?;
+            outputs.push(pdf_processing::to_bytes(&mut output).map_err(pdf_error)?);
+        }
+        Ok(outputs)
+    })
+    .await?;
+
+    let stem = file_stem(&_existing_data);
+    let results: Vec<PdfResult> = ranges
+        .iter()
+        .zip(outputs)
+        .map(|(range, file_bytes)| {
+            let range_name: String = range
+                .chars()
+                .filter(|value| !value.is_whitespace())
+                .map(|value| if value == ',' { '_' } else { value })
+                .collect();
+            let provenance = json!({
+                "operation": "split",
+                "sources": [provenance_source(&_existing_data, Some(range))],
+            });
+            (format!("{stem}-p{range_name}.pdf"), file_bytes, provenance)
+        })
+        .collect();
+    let module_id = _existing_data.module_id.unwrap_or(0);
+    _principal.check(Permission::UPLOAD, Some(module_id))?;
+    let user_id = _principal.user_id;
+    let m_files = store_pdfs(&_state, &mut db_conn, module_id, user_id, results).await?;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(m_files),
+            error: None,
+        }),
+    ))
+}
+
+/// Rotate pages of a PDF into a new file, the source is kept as is.
+pub async fn rotate(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Json(m_file_pdf_rotate_request): Json<MFilePdfRotateRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = m_file_pdf_rotate_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let id = m_file_pdf_rotate_request.id.unwrap_or(0);
+    let _existing_data = find_pdfs(&mut db_conn, &_principal, &[id])?.remove(0);
+    let pages = m_file_pdf_rotate_request
+        .pages
+        .filter(|value| !value.trim().is_empty());
+    let angle = m_file_pdf_rotate_request.angle.unwrap_or(0);
+    let source = source_path(&_existing_data);
+    let job_pages = pages.clone();
+    let file_bytes = run_blocking(move || {
+        let mut document = load_pdf(&source)?;
+        let page_count = pdf_processing::page_count(&document);
+        let page_numbers = match job_pages {
+            Some(value) => pdf_processing::parse_page_ranges(&value, page_count)
+                .map_err(AppError::BadRequest)?,
+            None => (1..=page_count).collect(),
+        };
+        pdf_processing::rotate(&mut document, &page_numbers, angle).map_err(pdf_error)?;
+        pdf_processing::to_bytes(&mut document).map_err(pdf_error)
+    })
+    .await?;
+
+    let provenance = json!({
+        "operation": "rotate",
+        "angle": angle,
+        "sources": [provenance_source(&_existing_data, pages.as_deref())],
+    });
+    let file_name = format!("{}-rotated.pdf", file_stem(&_existing_data));
+    let module_id = _existing_data.module_id.unwrap_or(0);
+    _principal.check(Permission::UPLOAD, Some(module_id))?;
+    let user_id = _principal.user_id;
+    let mut m_files = store_pdfs(
+        &_state,
+        &mut db_conn,
+        module_id,
+        user_id,
+        vec![(file_name, file_bytes, provenance)],
+    )
+    .await?;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(m_files.remove(0)),
+            error: None,
+        }),
+    ))
+}
+
+/// Find the files in the requested order, every one of them must be a PDF the principal may read
+/// and together they must fit in `MAX_SOURCE_SIZE`.
+fn find_pdfs(
+    db_conn: &mut MysqlConnection,
+    _principal: &Principal,
+    ids: &[i64],
+) -> Result<Vec<MFile>, AppError> {
+    let found = repository::find_by_ids(db_conn, ids.to_vec())?;
+    let mut m_files = Vec::new();
+    for id in ids {
+        let Some(value) = found.iter().find(|value| value.id == *id) else {
+            return Err(AppError::NotFound);
+        };
+        let file_name = value.file_name.clone().unwrap_or_default();
+        if !file_name.to_lowercase().ends_with(".pdf") {
+            return Err(AppError::BadRequest(format!("file is not a pdf, id: {id}")));
+        }
+        check_access(db_conn, _principal, value, Permission::READ, FileAccess::VIEWER)?;
+        m_files.push(value.clone());
+    }
+
+    let total_size: u64 = m_files
+        .iter()
+        .filter_map(|value| std::fs::metadata(source_path(value)).ok())
+        .map(|value| value.len())
+        .sum();
+    if total_size > MAX_SOURCE_SIZE {
+        return Err(AppError::BadRequest(format!(
+            "pdf sources are larger than {} bytes",
+            MAX_SOURCE_SIZE
+        )));
+    }
+    Ok(m_files)
+}
+
+fn load_pdf(path: &std::path::Path) -> Result<Document, AppError> {
+    let document = pdf_processing::load(path).map_err(pdf_error)?;
+    if document.is_encrypted() {
+        return Err(AppError::BadRequest(
+            "encrypted pdf is not supported".to_string(),
+        ));
+    }
+    Ok(document)
+}
+
+fn pdf_error(error: lopdf::Error) -> AppError {
+    AppError::BadRequest(format!("unreadable pdf: {error}"))
+}
+
+async fn run_blocking<T, F>(job: F) -> Result<T, AppError>
+where
+    F: FnOnce() -> Result<T, AppError> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(job)
+        .await
+        .map_err(|error| AppError::Other(format!("pdf operation failed: {error}")))?
+}
+
+fn source_path(m_file: &MFile) -> PathBuf {
+    PathBuf::from(m_file.file_path.clone().unwrap_or_default())
+}
+
+fn file_stem(m_file: &MFile) -> String {
+    let file_name = m_file.file_name.clone().unwrap_or_default();
+    std::path::Path::new(&file_name)
+        .file_stem()
+        .and_then(|value| value.to_str())
+        .unwrap_or("document")
+        .to_string()
+}
+
+fn provenance_source(m_file: &MFile, pages: Option<&str>) -> JsonValue {
+    json!({
+        "id": m_file.id,
+        "file_name": m_file.file_name,
+        "pages": pages,
+    })
+}
+
+/// Store produced PDFs as new files of the module and user. The metadata carries the document
+/// properties and the provenance linking each file to its sources.
+async fn store_pdfs(
+    _state: &AppState,
+    db_conn: &mut MysqlConnection,
+    module_id: i64,
+    user_id: i64,
+    results: Vec<PdfResult>,
+) -> Result<Vec<MFile>, AppError> {
+    let config = &CONFIG;
+    let dir_path = format!(
+        "{}/{}/{}/{}",
+        config.file_root_dir,
+        module_id,
+        user_id,
+        FileType::DOCUMENT
+    );
+    if let Err(error) = tokio::fs::create_dir_all(&dir_path).await {
+        return Err(AppError::Other(format!("create dir failed: {error}")));
+    }
+
+    // one row per result, ids are allocated on insert
+    let created_on = chrono::Utc::now().naive_utc();
+    let mut m_files: Vec<MFile> = Vec::new();
+    for (file_name, file_bytes, mut provenance) in results {
+        let file_path = format!("{}/{}", dir_path, Uuid::new_v4());
+        if let Err(error) = tokio::fs::write(&file_path, &file_bytes).await {
+            let _ = tokio::fs::remove_file(&file_path).await;
+            remove_stored(&m_files).await;
+            return Err(AppError::Other(format!("write file failed: {error}")));
+        }
+
+        provenance["created_on"] = json!(created_on.format("%Y-%m-%d %H:%M:%S").to_string());
+        let mut metadata: JsonValue = extract_document(file_path.clone(), file_name.clone())
+            .await
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_else(|| json!({}));
+        metadata["provenance"] = provenance;
+
+        let mut new_m_file = MFile::new(
+            file_name,
+            FileType::DOCUMENT.to_string(),
+            file_path,
+            file_bytes.len().to_string(),
+            module_id,
+            user_id,
+        );
+        new_m_file.metadata = Some(metadata.to_string());
+        m_files.push(new_m_file);
+    }
+
+    let m_files = match repository::insert_mfiles_with_new_ids(db_conn, m_files.clone()) {
+        Ok(value) => value,
+        Err(error) => {
+            remove_stored(&m_files).await;
+            return Err(error);
+        }
+    };
+    for m_file in m_files.iter() {
+        index_file(&_state.search_index_queue, m_file.clone());
+    }
+    Ok(m_files)
+}
+
+async fn remove_stored(m_files: &[MFile]) {
+    for m_file in m_files {
+        let file_path = m_file.file_path.clone().unwrap_or_default();
+        file_cache::remove(&file_path).await;
+        let _ = tokio::fs::remove_file(&file_path).await;
+    }
+}
@@ -0,0 +1,19 @@
+use axum::{
+    middleware::from_fn_with_state,
+    routing::{get, post},
+    Router,
+};
+
+use crate::{
+    dto::enumerator::permission::Permission,
+    middleware::auth_middleware::authorize,
+    module::m_file::file::pdf::controller::{info, merge, rotate, split},
+};
+
+pub fn new() -> Router {
+    Router::new()
+        .route("/{id}/pdf", get(info).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .route("/pdf/merge", post(merge).route_layer(from_fn_with_state(Permission::UPLOAD, authorize)))
+        .route("/pdf/split", post(split).route_layer(from_fn_with_state(Permission::UPLOAD, authorize)))
+        .route("/pdf/rotate", post(rotate).route_layer(from_fn_with_state(Permission::UPLOAD, authorize)))
+}
@@ -1,17 +1,35 @@
 use axum::{
+    middleware::from_fn_with_state,
     routing::{delete, get, post, put}, Router
 };
 
-use crate::module::m_file::file::controller::{copy, delete_file, download, move_file, rename, stream, update, upload};
+use crate::{
+    dto::enumerator::permission::Permission,
+    middleware::auth_middleware::authorize,
+    module::m_file::file::{
+        controller::{
+            archive, copy, delete_file, download, download_entry, list_entries, move_file, rename,
+            stream, update, upload,
+        },
+        audio, document, image, pdf,
+    },
+};
 
 pub fn new() -> Router {
     Router::new()
-        .route("/", post(upload))
-        .route("/", put(update))
-        .route("/{id}", get(download))
-        .route("/stream/{id}", get(stream))
-        .route("/{id}", delete(delete_file))
-        .route("/rename", put(rename))
-        .route("/copy", put(copy))
-        .route("/move", put(move_file))
+        .route("/", post(upload).route_layer(from_fn_with_state(Permission::UPLOAD, authorize)))
+        .route("/", put(update).route_layer(from_fn_with_state(Permission::UPDATE, authorize)))
+        .route("/{id}", get(download).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .route("/stream/{id}", get(stream).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .route("/{id}", delete(delete_file).route_layer(from_fn_with_state(Permission::DELETE, authorize)))
+        .route("/rename", put(rename).route_layer(from_fn_with_state(Permission::UPDATE, authorize)))
+        .route("/copy", put(copy).route_layer(from_fn_with_state(Permission::UPLOAD, authorize)))
+        .route("/move", put(move_file).route_layer(from_fn_with_state(Permission::UPDATE, authorize)))
+        .route("/archive", post(archive).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .route("/{id}/entries", get(list_entries).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .route("/{id}/entries/{*entry}", get(download_entry).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .merge(image::router::new())
+        .merge(audio::router::new())
+        .merge(document::router::new())
+        .merge(pdf::router::new())
 }
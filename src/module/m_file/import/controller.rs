@@ -0,0 +1,403 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path as FilePath, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::Body,
+    extract::{Extension, Json, Multipart, Path},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use diesel::MysqlConnection;
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    config::environment::CONFIG,
+    dto::{
+        enumerator::{file_type::FileType, import_mode::ImportMode, permission::Permission},
+        principal::Principal,
+        response::{app_error::AppError, app_response::AppResponse},
+    },
+    module::m_file::{
+        file::controller::create_derived_files,
+        repository,
+        schema::{MFile, MFileRequest},
+        search::controller::index_file,
+    },
+    state::AppState,
+    util::import_job::{self, ImportJob, ImportRowError, ManifestLine, ManifestRow},
+};
+
+const MAX_MANIFEST_ROWS: usize = 100_000;
+
+struct ImportOptions {
+    job_id: String,
+    dry_run: bool,
+    mode: ImportMode,
+    // owner of rows without a user_id, the importing user
+    user_id: i64,
+    import_root: PathBuf,
+}
+
+/// Start importing the files listed in a CSV manifest with the columns `source_path`,
+/// `file_name`, `module_id`, `user_id` and `tags`. Rows are checked like an `MFileRequest`, then
+/// their source is copied or linked into storage and an m_file row is inserted. A dry run only
+/// checks the rows. Returns the job, its progress is read with `find_job`.
+pub async fn import(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    mut multipart: Multipart,
+) -> Result<(StatusCode, Json<AppResponse<ImportJob>>), AppError> {
+    // rows may target any module
+    _principal.check(Permission::ADMIN, None)?;
+    let config = &CONFIG;
+    if config.import_root_dir.is_empty() {
+        return Err(AppError::BadRequest("manifest import is disabled".to_string()));
+    }
+
+    let mut manifest_bytes: Vec<u8> = Vec::new();
+    let mut dry_run: bool = false;
+    let mut mode = ImportMode::COPY;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|error| AppError::BadRequest(error.to_string()))?
+    {
+        let field_name = field.name().unwrap_or_default().to_string();
+        let payload_tmp = field
+            .bytes()
+            .await
+            .map_err(|error| AppError::BadRequest(error.to_string()))?;
+        match field_name.as_str() {
+            "manifest" => manifest_bytes = payload_tmp.to_vec(),
+            "dry_run" => {
+                dry_run = String::from_utf8_lossy(&payload_tmp)
+                    .trim()
+                    .parse()
+                    .unwrap_or(false)
+            }
+            "mode" => {
+                mode = match String::from_utf8_lossy(&payload_tmp).trim() {
+                    "copy" => ImportMode::COPY,
+                    "link" => ImportMode::LINK,
+                    value => {
+                        return Err(AppError::BadRequest(format!("unknown mode {}", value)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if manifest_bytes.is_empty() {
+        return Err(AppError::BadRequest("manifest is mandatory".to_string()));
+    }
+
+    let rows = import_job::read_manifest(&manifest_bytes, MAX_MANIFEST_ROWS)
+        .map_err(AppError::BadRequest)?;
+    if rows.is_empty() {
+        return Err(AppError::BadRequest("manifest has no rows".to_string()));
+    }
+    let import_root = fs::canonicalize(&config.import_root_dir)
+        .map_err(|error| AppError::Other(format!("import root dir failed: {error}")))?;
+
+    let options = ImportOptions {
+        job_id: Uuid::new_v4().to_string(),
+        dry_run,
+        mode,
+        user_id: _principal.user_id,
+        import_root,
+    };
+    let job = _state.import_jobs.add(ImportJob::new(
+        options.job_id.clone(),
+        options.dry_run,
+        options.mode.clone(),
+        rows.len() as u64,
+    ));
+    let job_snapshot = job.lock().unwrap().clone();
+    log::info!(
+        "import {} started, dry_run {}, mode {}, rows {}",
+        options.job_id,
+        options.dry_run,
+        options.mode,
+        rows.len()
+    );
+
+    let state = _state.clone();
+    tokio::task::spawn_blocking(move || run_import(state, job, rows, options));
+
+    let status_code = StatusCode::ACCEPTED;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(job_snapshot),
+            error: None,
+        }),
+    ))
+}
+
+/// Progress of an import job.
+pub async fn find_job(
+    Path(id): Path<String>,
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+) -> Result<(StatusCode, Json<AppResponse<ImportJob>>), AppError> {
+    _principal.check(Permission::ADMIN, None)?;
+    let Some(job) = _state.import_jobs.get(&id) else {
+        return Err(AppError::NotFound);
+    };
+    let job_snapshot = job.lock().unwrap().clone();
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(job_snapshot),
+            error: None,
+        }),
+    ))
+}
+
+/// Rows of an import job that failed so far, as CSV with the columns `row`, `source_path` and
+/// `message`.
+pub async fn find_errors(
+    Path(id): Path<String>,
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+) -> impl IntoResponse {
+    _principal.check(Permission::ADMIN, None)?;
+    let Some(job) = _state.import_jobs.get(&id) else {
+        return Err(AppError::NotFound);
+    };
+    let errors = job.lock().unwrap().errors.clone();
+
+    let mut csv_writer = csv::Writer::from_writer(Vec::new());
+    for error in errors.iter() {
+        csv_writer
+            .serialize(error)
+            .map_err(|error| AppError::Other(format!("write errors failed: {error}")))?;
+    }
+    let mut csv_bytes = csv_writer
+        .into_inner()
+        .map_err(|error| AppError::Other(format!("write errors failed: {error}")))?;
+    if errors.is_empty() {
+        csv_bytes = b"row,source_path,message\n".to_vec();
+    }
+
+    let response_builder: axum::http::Response<Body> = axum::http::Response::builder()
+        .header(
+            "Content-Disposition",
+            format!("attachment; filename=\"import_{}_errors.csv\"", id),
+        )
+        .header("Content-Type", "text/csv; charset=utf-8")
+        .body(Body::from(csv_bytes))
+        .unwrap();
+
+    Ok(response_builder)
+}
+
+fn run_import(
+    state: Arc<AppState>,
+    job: Arc<Mutex<ImportJob>>,
+    rows: Vec<ManifestLine>,
+    options: ImportOptions,
+) {
+    // get db connection
+    let mut db_conn = match state.diesel_pool_mysql.get() {
+        Ok(value) => value,
+        Err(error) => {
+            job.lock()
+                .unwrap()
+                .finish(Some(format!("get connection failed {error}")));
+            return;
+        }
+    };
+
+    let mut sources: HashSet<PathBuf> = HashSet::new();
+    for (line, row) in rows {
+        let source_path = row
+            .as_ref()
+            .map(|value| value.source_path.clone())
+            .unwrap_or_default();
+        let result =
+            row.and_then(|value| import_row(&mut db_conn, value, &options, &mut sources));
+
+        if let Ok(Some(m_file)) = &result {
+            index_file(&state.search_index_queue, m_file.clone());
+            create_derived_files(m_file);
+        }
+        let mut job = job.lock().unwrap();
+        job.processed_rows += 1;
+        match result {
+            Ok(_) => job.imported_rows += 1,
+            Err(message) => {
+                job.failed_rows += 1;
+                job.errors.push(ImportRowError {
+                    row: line,
+                    source_path,
+                    message,
+                });
+            }
+        }
+    }
+
+    let mut job = job.lock().unwrap();
+    job.finish(None);
+    log::info!(
+        "import {} done, imported {}, failed {}",
+        job.id,
+        job.imported_rows,
+        job.failed_rows
+    );
+}
+
+// the stored file, None on a dry run
+fn import_row(
+    db_conn: &mut MysqlConnection,
+    row: ManifestRow,
+    options: &ImportOptions,
+    sources: &mut HashSet<PathBuf>,
+) -> Result<Option<MFile>, String> {
+    let source = resolve_source(&options.import_root, &row.source_path)?;
+    if !sources.insert(source.clone()) {
+        return Err("source is listed more than once".to_string());
+    }
+    let file_size = fs::metadata(&source)
+        .map_err(|error| format!("read source failed: {error}"))?
+        .len();
+    let file_name = row
+        .file_name
+        .filter(|value| !value.is_empty())
+        .or_else(|| {
+            source
+                .file_name()
+                .map(|value| value.to_string_lossy().to_string())
+        })
+        .unwrap_or_default();
+    let file_type = FileType::from_file_name(&file_name);
+    let user_id = row.user_id.unwrap_or(options.user_id);
+
+    let m_file_request = MFileRequest::new(
+        None,
+        Some(file_name.clone()),
+        Some(file_type.to_string()),
+        Some(source.to_string_lossy().to_string()),
+        row.module_id,
+        Some(user_id),
+    );
+    check_request(&m_file_request)?;
+    if options.dry_run {
+        return Ok(None);
+    }
+    let module_id = row.module_id.unwrap_or_default();
+
+    let config = &CONFIG;
+    let dir_path = format!(
+        "{}/{}/{}/{}",
+        config.file_root_dir, module_id, user_id, file_type
+    );
+    fs::create_dir_all(&dir_path).map_err(|error| format!("create dir failed: {error}"))?;
+    let file_path = format!("{}/{}", dir_path, Uuid::new_v4());
+    match options.mode {
+        ImportMode::COPY => fs::copy(&source, &file_path).map(|_| ()),
+        ImportMode::LINK => fs::hard_link(&source, &file_path),
+    }
+    .map_err(|error| format!("{} failed: {error}", options.mode))?;
+
+    let mut tags: Vec<String> = Vec::new();
+    for tag in row.tags.unwrap_or_default().split(';').map(|value| value.trim()) {
+        if !tag.is_empty() && !tags.iter().any(|value| value == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    let metadata = json!({
+        "import": {
+            "job_id": options.job_id,
+            "source_path": source.to_string_lossy(),
+            "mode": options.mode,
+            "tags": tags,
+        }
+    });
+    let mut new_m_file = MFile::new(
+        file_name,
+        file_type.to_string(),
+        file_path.clone(),
+        file_size.to_string(),
+        module_id,
+        user_id,
+    );
+    new_m_file.metadata = Some(metadata.to_string());
+
+    match repository::insert_mfile_with_new_id(db_conn, new_m_file) {
+        Ok(value) => Ok(Some(value)),
+        Err(error) => {
+            let _ = fs::remove_file(&file_path);
+            Err(error_message(error))
+        }
+    }
+}
+
+// a regular file below the import root, relative paths start at the root
+fn resolve_source(import_root: &FilePath, source_path: &str) -> Result<PathBuf, String> {
+    if source_path.is_empty() {
+        return Err("source_path is empty".to_string());
+    }
+    let source = import_root
+        .join(source_path)
+        .canonicalize()
+        .map_err(|error| format!("source not found: {error}"))?;
+    if !source.starts_with(import_root) {
+        return Err("source is outside of the import root".to_string());
+    }
+    if !source.is_file() {
+        return Err("source is not a file".to_string());
+    }
+    Ok(source)
+}
+
+// the same rules as a create request, except the id which is assigned on insert
+fn check_request(m_file_request: &MFileRequest) -> Result<(), String> {
+    let Err(errors) = m_file_request.validate() else {
+        return Ok(());
+    };
+    let mut messages: Vec<String> = errors
+        .field_errors()
+        .into_iter()
+        .filter(|(field, _)| field != "id")
+        .map(|(field, field_errors)| {
+            let reasons: Vec<String> = field_errors
+                .iter()
+                .map(|error| {
+                    error
+                        .message
+                        .as_ref()
+                        .unwrap_or(&error.code)
+                        .to_string()
+                })
+                .collect();
+            format!("{}: {}", field, reasons.join(", "))
+        })
+        .collect();
+    if messages.is_empty() {
+        return Ok(());
+    }
+    messages.sort();
+    Err(messages.join("; "))
+}
+
+fn error_message(error: AppError) -> String {
+    match error {
+        AppError::BadRequest(message) | AppError::Other(message) => message,
+        _ => format!("{:?}", error),
+    }
+}
@@ -0,0 +1,16 @@
+use axum::{middleware::from_fn_with_state, routing::{get, post}, Router};
+
+use crate::{
+    dto::enumerator::permission::Permission,
+    middleware::auth_middleware::authorize,
+    module::m_file::import::controller::{find_errors, find_job, import},
+};
+
+// imports write files of any module
+pub fn new() -> Router {
+    Router::new()
+        .route("/", post(import))
+        .route("/{id}", get(find_job))
+        .route("/{id}/errors", get(find_errors))
+        .route_layer(from_fn_with_state(Permission::ADMIN, authorize))
+}
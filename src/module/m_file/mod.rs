@@ -1,5 +1,10 @@
 pub mod schema;
 pub mod router;
 pub mod controller;
+pub mod export;
 pub mod file;
-pub mod repository;
\ No newline at end of file
+pub mod import;
+pub mod repository;
+pub mod saved_search;
+pub mod search;
+pub mod share;
\ No newline at end of file
@@ -1,14 +1,147 @@
-use diesel::{dsl::insert_into, prelude::*, sql_query, update};
+use std::collections::BTreeMap;
+
+use diesel::{debug_query, dsl::insert_into, mysql::Mysql, prelude::*, sql_query, update};
 
 use crate::{
+    config::environment::CONFIG,
     diesel_schema::m_file::dsl::*,
     dto::{
-        database::CountResult, enumerator::filter_match_mode::FilterMatchMode, request::{filter_request::Filter, sort_request::Sort}, response::app_error::AppError
+        database::{CountResult, FacetCount, IdResult}, enumerator::{file_access::FileAccess, filter_data_type::FilterDataType, permission::Permission}, principal::Principal, request::{filter_request::{FilterGroup, FilterNode}, sort_request::Sort}, response::app_error::AppError
+    },
+    module::m_file::{schema::MFile, share::repository as share_repository},
+    util::{
+        query_builder::{bind_all, BindValue, Column, Cursors, Facet, KeyedRow, QueryBuilder},
+        id_generator, string_manipulation,
     },
-    module::m_file::schema::MFile,
-    util::string_manipulation,
 };
 
+// most values returned per facet
+const MAX_FACET_VALUES: i64 = 100;
+
+static PAGINATION_FACETS: [Facet; 4] = [
+    Facet { id: "file_type", expression: "file_type" },
+    Facet { id: "module_id", expression: "module_id" },
+    Facet { id: "uploader", expression: "created_by" },
+    Facet { id: "upload_month", expression: "DATE_FORMAT(created_on, '%Y-%m')" },
+];
+
+// columns of m_file clients may filter and sort on, values of the metadata JSON are filtered as
+// `metadata.<namespace>.<key>`, e.g. `metadata.media.duration` with data_type NUMBER
+static PAGINATION_COLUMNS: [Column; 12] = [
+    Column { id: "id", expression: "id", data_type: FilterDataType::NUMBER, sortable: true },
+    Column { id: "file_name", expression: "file_name", data_type: FilterDataType::TEXT, sortable: true },
+    Column { id: "file_path", expression: "file_path", data_type: FilterDataType::TEXT, sortable: true },
+    Column { id: "file_type", expression: "file_type", data_type: FilterDataType::TEXT, sortable: true },
+    // stored as text, compared and sorted as a number
+    Column { id: "file_size", expression: "CAST(file_size AS UNSIGNED)", data_type: FilterDataType::NUMBER, sortable: true },
+    Column { id: "module_id", expression: "module_id", data_type: FilterDataType::NUMBER, sortable: true },
+    Column { id: "created_by", expression: "created_by", data_type: FilterDataType::NUMBER, sortable: true },
+    Column { id: "created_on", expression: "created_on", data_type: FilterDataType::DATE, sortable: true },
+    Column { id: "modified_by", expression: "modified_by", data_type: FilterDataType::NUMBER, sortable: true },
+    Column { id: "modified_on", expression: "modified_on", data_type: FilterDataType::DATE, sortable: true },
+    Column { id: "is_delete", expression: "is_delete", data_type: FilterDataType::BOOLEAN, sortable: true },
+    Column { id: "deleted_on", expression: "deleted_on", data_type: FilterDataType::DATE, sortable: true },
+];
+
+/// Files a user may see in listings, or use a permission on.
+#[derive(Debug, Clone)]
+pub struct FileScope {
+    // modules the permission is held on, every module when `None`
+    pub module_ids: Option<Vec<i64>>,
+    // modules whose files are all in scope whoever owns them, every module when `None`
+    pub admin_module_ids: Option<Vec<i64>>,
+    // other files are in scope when the user owns them or they are shared with the user or role
+    pub user_id: i64,
+    pub role_id: Option<i64>,
+    // least access a share must grant
+    pub access: FileAccess,
+    // only files shared with the user, not their own nor the ones of administered modules
+    pub shared_only: bool,
+}
+
+impl FileScope {
+    pub fn readable_by(principal: &Principal) -> FileScope {
+        FileScope::accessible_by(principal, Permission::READ, FileAccess::VIEWER)
+    }
+
+    pub fn accessible_by(
+        principal: &Principal,
+        permission: Permission,
+        least_access: FileAccess,
+    ) -> FileScope {
+        FileScope {
+            module_ids: principal.grants.module_ids(permission),
+            admin_module_ids: principal.grants.module_ids(Permission::ADMIN),
+            user_id: principal.user_id,
+            role_id: principal.role_id,
+            access: least_access,
+            shared_only: false,
+        }
+    }
+
+    /// Readable files other users shared with the principal or their role.
+    pub fn shared_with(principal: &Principal) -> FileScope {
+        FileScope {
+            shared_only: true,
+            ..FileScope::readable_by(principal)
+        }
+    }
+
+    /// Every file, for checking queries without running them.
+    pub fn every_file() -> FileScope {
+        FileScope {
+            module_ids: None,
+            admin_module_ids: None,
+            user_id: 0,
+            role_id: None,
+            access: FileAccess::VIEWER,
+            shared_only: false,
+        }
+    }
+
+    // `None` when every file is in scope
+    fn condition(&self) -> Option<(String, Vec<BindValue>)> {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut binds: Vec<BindValue> = Vec::new();
+        if let Some(module_ids) = &self.module_ids {
+            if module_ids.is_empty() {
+                return Some(("FALSE".to_string(), Vec::new()));
+            }
+            conditions.push(module_condition(module_ids));
+            binds.extend(module_ids.iter().map(|value| BindValue::Integer(*value)));
+        }
+
+        let (shared, shared_binds) =
+            share_repository::shared_condition(self.user_id, self.role_id, self.access);
+        if self.shared_only {
+            conditions.push("created_by <> ?".to_string());
+            binds.push(BindValue::Integer(self.user_id));
+            conditions.push(shared);
+            binds.extend(shared_binds);
+        } else if let Some(admin_module_ids) = &self.admin_module_ids {
+            let mut alternatives: Vec<String> = Vec::new();
+            if !admin_module_ids.is_empty() {
+                alternatives.push(module_condition(admin_module_ids));
+                binds.extend(admin_module_ids.iter().map(|value| BindValue::Integer(*value)));
+            }
+            alternatives.push("created_by = ?".to_string());
+            binds.push(BindValue::Integer(self.user_id));
+            alternatives.push(shared);
+            binds.extend(shared_binds);
+            conditions.push(format!("({})", alternatives.join(" OR ")));
+        }
+
+        if conditions.is_empty() {
+            return None;
+        }
+        Some((conditions.join(" AND "), binds))
+    }
+}
+
+fn module_condition(module_ids: &[i64]) -> String {
+    format!("module_id IN ({})", vec!["?"; module_ids.len()].join(", "))
+}
+
 pub fn find_by_id(
     conn: &mut MysqlConnection,
     mfile_id: i64,
@@ -39,16 +172,154 @@ pub fn find_query_by_id(
     Ok(user)
 }
 
-pub fn find_all(conn: &mut MysqlConnection) -> Result<Vec<MFile>, AppError> {
-    let query = "SELECT * 
-            FROM m_file";
+pub fn find_all(conn: &mut MysqlConnection, scope: &FileScope) -> Result<Vec<MFile>, AppError> {
+    let (condition, binds) = scope.condition().unwrap_or(("TRUE".to_string(), Vec::new()));
 
-    let user: Vec<MFile> = sql_query(query)
-        .get_results::<MFile>(conn)
+    let user: Vec<MFile> = bind_all(format!("SELECT * FROM m_file WHERE {}", condition), binds)
+        .load::<MFile>(conn)
         .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
     Ok(user)
 }
 
+pub fn find_by_ids(conn: &mut MysqlConnection, mfile_ids: Vec<i64>) -> Result<Vec<MFile>, AppError> {
+    let data_vec = m_file
+        .filter(id.eq_any(mfile_ids))
+        .filter(is_delete.eq(false))
+        .order(id.asc())
+        .select(MFile::as_select())
+        .load::<MFile>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(data_vec)
+}
+
+/// The files of `find_by_ids` in scope.
+pub fn find_by_ids_in_scope(
+    conn: &mut MysqlConnection,
+    mfile_ids: Vec<i64>,
+    scope: &FileScope,
+) -> Result<Vec<MFile>, AppError> {
+    if mfile_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut sql = format!(
+        "SELECT * FROM m_file WHERE id IN ({}) AND is_delete = FALSE",
+        vec!["?"; mfile_ids.len()].join(", ")
+    );
+    let mut binds: Vec<BindValue> = mfile_ids.into_iter().map(BindValue::Integer).collect();
+    if let Some((condition, scope_binds)) = scope.condition() {
+        sql = format!("{} AND {}", sql, condition);
+        binds.extend(scope_binds);
+    }
+
+    let data_vec = bind_all(format!("{} ORDER BY id ASC", sql), binds)
+        .load::<MFile>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(data_vec)
+}
+
+/// The ids of `find_by_ids_in_scope`, in no particular order.
+pub fn find_ids_in_scope(
+    conn: &mut MysqlConnection,
+    mfile_ids: Vec<i64>,
+    scope: &FileScope,
+) -> Result<Vec<i64>, AppError> {
+    if mfile_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut sql = format!(
+        "SELECT id FROM m_file WHERE id IN ({}) AND is_delete = FALSE",
+        vec!["?"; mfile_ids.len()].join(", ")
+    );
+    let mut binds: Vec<BindValue> = mfile_ids.into_iter().map(BindValue::Integer).collect();
+    if let Some((condition, scope_binds)) = scope.condition() {
+        sql = format!("{} AND {}", sql, condition);
+        binds.extend(scope_binds);
+    }
+
+    let data_vec = bind_all(sql, binds)
+        .load::<IdResult>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(data_vec.into_iter().map(|value| value.id).collect())
+}
+
+/// Number of files under a folder, a path relative to the file root dir, in scope.
+pub fn count_by_folder(
+    conn: &mut MysqlConnection,
+    relative_folder: &str,
+    scope: &FileScope,
+) -> Result<i64, AppError> {
+    let mut sql =
+        "SELECT COUNT(*) AS count FROM m_file WHERE LOCATE(?, file_path) = 1 AND is_delete = FALSE"
+            .to_string();
+    let mut binds = vec![BindValue::Text(format!(
+        "{}/{}/",
+        CONFIG.file_root_dir, relative_folder
+    ))];
+    if let Some((condition, scope_binds)) = scope.condition() {
+        sql = format!("{} AND {}", sql, condition);
+        binds.extend(scope_binds);
+    }
+
+    let results = bind_all(sql, binds)
+        .load::<CountResult>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(results.first().map(|value| value.count).unwrap_or(0))
+}
+
+/// Files that are not deleted of a module, uploader and folder, each when given, in scope.
+pub fn find_by_selector(
+    conn: &mut MysqlConnection,
+    selector_module_id: Option<i64>,
+    selector_user_id: Option<i64>,
+    selector_path_prefix: Option<String>,
+    scope: &FileScope,
+) -> Result<Vec<MFile>, AppError> {
+    let mut sql = "SELECT * FROM m_file WHERE is_delete = FALSE".to_string();
+    let mut binds: Vec<BindValue> = Vec::new();
+    if let Some(value) = selector_module_id {
+        sql.push_str(" AND module_id = ?");
+        binds.push(BindValue::Integer(value));
+    }
+    if let Some(value) = selector_user_id {
+        sql.push_str(" AND created_by = ?");
+        binds.push(BindValue::Integer(value));
+    }
+    if let Some(value) = selector_path_prefix {
+        // backslash is the default LIKE escape character of MySQL
+        sql.push_str(" AND file_path LIKE ?");
+        binds.push(BindValue::Text(format!(
+            "{}/%",
+            string_manipulation::escape_like(&value)
+        )));
+    }
+    if let Some((condition, scope_binds)) = scope.condition() {
+        sql = format!("{} AND {}", sql, condition);
+        binds.extend(scope_binds);
+    }
+
+    let data_vec = bind_all(format!("{} ORDER BY id ASC", sql), binds)
+        .load::<MFile>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(data_vec)
+}
+
+/// Up to `size` files that are not deleted with an id above `after_id`, by id.
+pub fn find_after_id(
+    conn: &mut MysqlConnection,
+    after_id: i64,
+    size: i64,
+) -> Result<Vec<MFile>, AppError> {
+    let data_vec = m_file
+        .filter(is_delete.eq(false))
+        .filter(id.gt(after_id))
+        .order(id.asc())
+        .limit(size)
+        .select(MFile::as_select())
+        .load::<MFile>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(data_vec)
+}
+
 pub fn delete_by_id(conn: &mut MysqlConnection, mfile_id: i64) -> Result<Option<()>, AppError> {
     let rows_affected = diesel::delete(m_file.filter(id.eq(mfile_id)))
         .execute(conn)
@@ -74,6 +345,40 @@ pub fn insert_mfile(
     return Ok(None);
 }
 
+/// Insert the file under a new id and return it with its id.
+pub fn insert_mfile_with_new_id(
+    conn: &mut MysqlConnection,
+    mfile: MFile,
+) -> Result<MFile, AppError> {
+    let mut new_mfile = mfile;
+    id_generator::with_new_id(|new_id| {
+        new_mfile.id = new_id;
+        insert_into(m_file).values(&new_mfile).execute(conn)
+    })
+    .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(new_mfile)
+}
+
+/// Insert the files in one transaction, each under a new id, and return them with their ids.
+pub fn insert_mfiles_with_new_ids(
+    conn: &mut MysqlConnection,
+    mfiles: Vec<MFile>,
+) -> Result<Vec<MFile>, AppError> {
+    conn.transaction(|conn| {
+        let mut inserted: Vec<MFile> = Vec::new();
+        for mfile in mfiles {
+            let mut new_mfile = mfile;
+            id_generator::with_new_id(|new_id| {
+                new_mfile.id = new_id;
+                insert_into(m_file).values(&new_mfile).execute(conn)
+            })?;
+            inserted.push(new_mfile);
+        }
+        Ok(inserted)
+    })
+    .map_err(|error: diesel::result::Error| AppError::Other(format!("query failed: {}", error)))
+}
+
 pub fn update_mfile(
     conn: &mut MysqlConnection,
     mfile: MFile,
@@ -88,6 +393,7 @@ pub fn update_mfile(
             file_name.eq(mfile.file_name),
             file_type.eq(mfile.file_type),
             file_path.eq(mfile.file_path),
+            metadata.eq(mfile.metadata),
         ))
         .execute(conn)
         .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, mfile.id)))?;
@@ -97,107 +403,119 @@ pub fn update_mfile(
     return Ok(None);
 }
 
+/// A page of the rows selected by `query_builder`, see `pagination_query`, and their count.
 pub fn pagination(
     conn: &mut MysqlConnection,
     page: i64,
     size: i64,
-    filters: Vec<Filter>,
-    sorts: Vec<Sort>,
-    search: String,
-) -> Result<(Vec<MFile>, i64), AppError> {
-    // Build the query
-    let mut query = "SELECT *".to_string();
-    let mut query_count = "SELECT COUNT(*) AS count".to_string();
-    let query_table = "FROM m_file".to_string();
-
-    // Sort
-    let mut query_sort = String::new();
-    for sort in sorts {
-        query_sort = "ORDER BY".to_owned();
-        let sort_asc = if sort.desc {
-            "DESC".to_string()
-        } else {
-            "ASC".to_string()
-        };
-        let sort_id = string_manipulation::cleanse_string(&sort.id);
-        query_sort = format!("{} {} {}", query_sort, sort_id, sort_asc);
-        break;
-    }
-
-    // Search
-    let mut query_search = String::new();
-    if search != String::new() {
-        query_search = format!(
-            "WHERE fullname LIKE '%{}%'",
-            string_manipulation::cleanse_string(&search)
-        );
-    }
-
-    // Filter
-    let mut query_filter = "".to_string();
-    for filter in filters {
-        let filter_id = string_manipulation::cleanse_string(&filter.id);
-        let filter_value = string_manipulation::cleanse_string(&filter.value);
-        let mut filter_query_temp = "".to_string();
-        match filter.match_mode {
-            FilterMatchMode::CONTAINS => {
-                filter_query_temp = format!("{} LIKE '%{}%'", filter_id, filter_value);
-            }
-            FilterMatchMode::SW => {
-                filter_query_temp = format!("{} LIKE '{}%'", filter_id, filter_value);
-            }
-            FilterMatchMode::EW => {
-                filter_query_temp = format!("{} LIKE '%{}'", filter_id, filter_value);
-            }
-            FilterMatchMode::BETWEEN => {}
-            FilterMatchMode::EQUALS => {
-                filter_query_temp = format!("{} = '{}'", filter_id, filter_value);
-            }
-            FilterMatchMode::NOT => {
-                filter_query_temp = format!("{} <> '{}'", filter_id, filter_value);
-            }
-            FilterMatchMode::LT => {
-                filter_query_temp = format!("{} < '{}'", filter_id, filter_value);
-            }
-            FilterMatchMode::GT => {
-                filter_query_temp = format!("{} > '{}'", filter_id, filter_value);
-            }
-        };
-
-        if query_search == "".to_owned() {
-            query_search = " ".to_owned();
-            query_filter = format!("WHERE {}", filter_query_temp);
-        } else {
-            query_filter = format!("{} AND {}", query_filter, filter_query_temp);
-        }
+    cursor: Option<String>,
+    mut query_builder: QueryBuilder,
+) -> Result<(Vec<MFile>, i64, Cursors), AppError> {
+    if let Some(value) = cursor {
+        query_builder.cursor(&value)?;
     }
 
-    // Pagination
-    let query_pagination = format!("LIMIT {} OFFSET {}", size, size * (page));
-
-    // Final
-    query = format!(
-        "{} {} {} {} {} {}",
-        query, query_table, query_search, query_filter, query_sort, query_pagination
-    );
-    query_count = format!(
-        "{} {} {} {} {}",
-        query_count, query_table, query_search, query_filter, query_sort
-    );
+    let query = query_builder.select(page, size)?;
+    let query_count = query_builder.count();
     log::info!(
-        "repository > find_diesel_query_mfile_page > query: {:#?}",
-        query
+        "repository > pagination > query: {}",
+        debug_query::<Mysql, _>(&query)
     );
     log::info!(
-        "repository > find_diesel_query_mfile_page > query_count: {:#?}",
-        query_count
+        "repository > pagination > query_count: {}",
+        debug_query::<Mysql, _>(&query_count)
     );
 
-    let data_vec: Vec<MFile> = sql_query(query)
-        .get_results::<MFile>(conn)
+    let rows: Vec<KeyedRow<MFile>> = query
+        .load::<KeyedRow<MFile>>(conn)
         .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
-    let results = sql_query(query_count)
+    let results = query_count
         .load::<CountResult>(conn)
         .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
-    Ok((data_vec, results[0].count))
+    let (data_vec, cursors) = query_builder.page(page, size, rows);
+    Ok((data_vec, results[0].count, cursors))
+}
+
+/// The rows after `cursor` without counting them, for reading a whole result batch by batch.
+/// Returns the cursor of the next batch, `None` after the last one.
+pub fn batch(
+    conn: &mut MysqlConnection,
+    size: i64,
+    cursor: Option<String>,
+    filter_group: FilterGroup,
+    sorts: Vec<Sort>,
+    search: String,
+    scope: &FileScope,
+) -> Result<(Vec<MFile>, Option<String>), AppError> {
+    let mut query_builder = pagination_query(filter_group, sorts, search, scope)?;
+    if let Some(value) = cursor {
+        query_builder.cursor(&value)?;
+    }
+
+    let query = query_builder.select(0, size)?;
+    log::info!(
+        "repository > batch > query: {}",
+        debug_query::<Mysql, _>(&query)
+    );
+    let rows: Vec<KeyedRow<MFile>> = query
+        .load::<KeyedRow<MFile>>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    let (data_vec, cursors) = query_builder.page(0, size, rows);
+    Ok((data_vec, cursors.next))
+}
+
+/// Rows per value of each requested facet, for the same search and filters as `pagination`.
+pub fn facets(
+    conn: &mut MysqlConnection,
+    filter_group: FilterGroup,
+    search: String,
+    names: Vec<String>,
+    scope: &FileScope,
+) -> Result<BTreeMap<String, Vec<FacetCount>>, AppError> {
+    let mut facets = Vec::new();
+    for name in names.iter() {
+        let facet = PAGINATION_FACETS
+            .iter()
+            .find(|facet| facet.id == name)
+            .ok_or_else(|| AppError::BadRequest(format!("unknown facet {}", name)))?;
+        facets.push(facet);
+    }
+
+    let query_builder = pagination_query(filter_group, Vec::new(), search, scope)?;
+    let mut facet_counts = BTreeMap::new();
+    for facet in facets {
+        let query = query_builder.facet(facet, MAX_FACET_VALUES);
+        log::info!(
+            "repository > facets > query: {}",
+            debug_query::<Mysql, _>(&query)
+        );
+        let counts = query
+            .load::<FacetCount>(conn)
+            .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+        facet_counts.insert(facet.id.to_string(), counts);
+    }
+    Ok(facet_counts)
+}
+
+/// The pagination query without paging, also used to check a saved search before it is stored.
+/// Only files in `scope` are selected.
+pub fn pagination_query(
+    filter_group: FilterGroup,
+    sorts: Vec<Sort>,
+    search: String,
+    scope: &FileScope,
+) -> Result<QueryBuilder, AppError> {
+    let mut query_builder =
+        QueryBuilder::new("m_file", &PAGINATION_COLUMNS, "id").json_column("metadata");
+    if let Some((condition, binds)) = scope.condition() {
+        query_builder.custom_condition(condition, binds);
+    }
+    if !search.is_empty() {
+        query_builder.search("file_name", &search)?;
+    }
+    query_builder.filter(&FilterNode::Group(filter_group))?;
+    for sort in sorts.iter() {
+        query_builder.sort(sort)?;
+    }
+    Ok(query_builder)
 }
@@ -1,13 +1,24 @@
-use axum::{routing::{get, post}, Router};
+use axum::{middleware::from_fn_with_state, routing::{delete, get, post, put}, Router};
 
-use crate::module::m_file::{controller::{create, delete_by_id, find_all, find_by_id, find_page, update}, file};
+use crate::{
+    dto::enumerator::permission::Permission,
+    middleware::auth_middleware::authorize,
+    module::m_file::{controller::{create, delete_by_id, find_all, find_by_id, find_page, update}, export, file, import, saved_search, search, share},
+};
 
 
 pub fn new() -> Router {
     Router::new()
-    .route("/list", get(find_all))
-    .route("/pagination", get(find_page))
-    .route("/", post(create).put(update))
-    .route("/{id}", get(find_by_id).delete(delete_by_id))
+    .route("/list", get(find_all).route_layer(from_fn_with_state(Permission::READ, authorize)))
+    .route("/pagination", get(find_page).route_layer(from_fn_with_state(Permission::READ, authorize)))
+    .route("/", post(create).route_layer(from_fn_with_state(Permission::UPLOAD, authorize)))
+    .route("/", put(update).route_layer(from_fn_with_state(Permission::UPDATE, authorize)))
+    .route("/{id}", get(find_by_id).route_layer(from_fn_with_state(Permission::READ, authorize)))
+    .route("/{id}", delete(delete_by_id).route_layer(from_fn_with_state(Permission::DELETE, authorize)))
+    .nest("/export", export::router::new())
     .nest("/file", file::router::new())
-}
\ No newline at end of file
+    .nest("/import", import::router::new())
+    .nest("/search", search::router::new())
+    .nest("/saved-search", saved_search::router::new())
+    .nest("/share", share::router::new())
+}
@@ -0,0 +1,358 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Json, Path, Query},
+    http::StatusCode,
+};
+use diesel::MysqlConnection;
+use validator::Validate;
+
+use crate::{
+    dto::{
+        principal::Principal,
+        request::{facet_request::Facets, pagination_request::Pagination},
+        response::{
+            app_error::AppError, app_response::AppResponse, pagination_response::PaginatedResponse,
+        },
+    },
+    module::m_file::{
+        repository::{self as m_file_repository, FileScope},
+        saved_search::{
+            repository,
+            schema::{MSavedSearch, MSavedSearchRequest, SavedSearchQuery},
+        },
+        schema::MFile,
+    },
+    state::AppState,
+};
+
+pub async fn find_all(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+) -> Result<(StatusCode, Json<AppResponse<Vec<MSavedSearch>>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let result = repository::find_by_user(&mut db_conn, _principal.user_id);
+    match result {
+        Ok(value) => {
+            let status_code = StatusCode::OK;
+            Ok((
+                status_code,
+                Json(AppResponse {
+                    status: status_code.as_u16(),
+                    message: "success".to_owned(),
+                    timestamp: chrono::Utc::now().naive_utc(),
+                    data: Some(value),
+                    error: None,
+                }),
+            ))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+pub async fn find_by_id(
+    Path(id): Path<i64>,
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+) -> Result<(StatusCode, Json<AppResponse<MSavedSearch>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}, id: {id}")));
+        }
+    };
+
+    let _saved_search = find_visible(&mut db_conn, id, _principal.user_id)?;
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(_saved_search),
+            error: None,
+        }),
+    ))
+}
+
+pub async fn create(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Json(m_saved_search_request): Json<MSavedSearchRequest>,
+) -> Result<(StatusCode, Json<AppResponse<MSavedSearch>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = m_saved_search_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+    check_query(m_saved_search_request.query.as_ref().unwrap())?;
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let new_saved_search = MSavedSearch::from_create_request(m_saved_search_request, _principal.user_id);
+    let result = repository::insert_saved_search(&mut db_conn, new_saved_search);
+    match result {
+        Ok(value) => {
+            let status_code = StatusCode::OK;
+            Ok((
+                status_code,
+                Json(AppResponse {
+                    status: status_code.as_u16(),
+                    message: "success".to_owned(),
+                    timestamp: chrono::Utc::now().naive_utc(),
+                    data: Some(value),
+                    error: None,
+                }),
+            ))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+pub async fn update(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Json(m_saved_search_request): Json<MSavedSearchRequest>,
+) -> Result<(StatusCode, Json<AppResponse<MSavedSearch>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = m_saved_search_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+    let Some(id) = m_saved_search_request.id else {
+        return Err(AppError::BadRequest("id is mandatory".to_string()));
+    };
+    check_query(m_saved_search_request.query.as_ref().unwrap())?;
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}, id: {id}")));
+        }
+    };
+
+    let _existing_data = find_owned(&mut db_conn, id, _principal.user_id)?;
+    let _new_saved_search = MSavedSearch::from_update_request(m_saved_search_request, _existing_data, _principal.user_id);
+    let result = repository::update_saved_search(&mut db_conn, _new_saved_search.clone());
+    match result {
+        Ok(Some(_)) => {
+            let status_code = StatusCode::OK;
+            Ok((
+                status_code,
+                Json(AppResponse {
+                    status: status_code.as_u16(),
+                    message: "success".to_owned(),
+                    timestamp: chrono::Utc::now().naive_utc(),
+                    data: Some(_new_saved_search),
+                    error: None,
+                }),
+            ))
+        }
+        Ok(None) => Err(AppError::Other("save data failed".to_string())),
+        Err(err) => Err(err),
+    }
+}
+
+pub async fn delete_by_id(
+    Path(id): Path<i64>,
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}, id: {id}")));
+        }
+    };
+
+    find_owned(&mut db_conn, id, _principal.user_id)?;
+    let result = repository::delete_by_id(&mut db_conn, id);
+    match result {
+        Ok(Some(_)) => {
+            let status_code = StatusCode::OK;
+            Ok((
+                status_code,
+                Json(AppResponse {
+                    status: status_code.as_u16(),
+                    message: "success".to_owned(),
+                    timestamp: chrono::Utc::now().naive_utc(),
+                    data: None,
+                    error: None,
+                }),
+            ))
+        }
+        Ok(None) => Err(AppError::NotFound),
+        Err(err) => Err(err),
+    }
+}
+
+/// The files matching a saved search, paged like `/m-file/pagination`. Only paging and facets
+/// come from the request, filters, sorts and the global search are the saved ones.
+pub async fn find_files(
+    Path(id): Path<i64>,
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Query(_pagination): Query<Pagination>,
+    Query(_facet): Query<Facets>,
+) -> Result<(StatusCode, Json<AppResponse<PaginatedResponse<MFile>>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = _pagination.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+    if let Err(err) = _facet.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    let _page = _pagination.page.unwrap_or(0).max(0);
+    let _size = _pagination.size.unwrap_or(5).max(1);
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}, id: {id}")));
+        }
+    };
+
+    let _saved_search = find_visible(&mut db_conn, id, _principal.user_id)?;
+    let _query = _saved_search.to_query()?;
+    let _filter_group = _query.to_group();
+    let _q = _query._q.clone().unwrap_or_default();
+    log::info!(
+        "saved search {:?}, page {:?}, size {:?}, filters {:?}, sorts {:?}, global_search {:?}",
+        id,
+        _page,
+        _size,
+        _filter_group,
+        _query._sort,
+        _q
+    );
+
+    // a shared search lists only the files of its viewer
+    let _scope = FileScope::readable_by(&_principal);
+    let _facet_names = _facet.names();
+    let mut _facets = None;
+    if !_facet_names.is_empty() {
+        _facets = Some(m_file_repository::facets(
+            &mut db_conn,
+            _filter_group.clone(),
+            _q.clone(),
+            _facet_names,
+            &_scope,
+        )?);
+    }
+
+    let (content, total_of_elements, cursors) = m_file_repository::pagination(
+        &mut db_conn,
+        _page,
+        _size,
+        _pagination.cursor.clone(),
+        m_file_repository::pagination_query(_filter_group, _query._sort, _q, &_scope)?,
+    )?;
+    let total_of_pages = (total_of_elements + _size - 1) / _size;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(PaginatedResponse {
+                content,
+                total_of_elements,
+                total_of_pages,
+                next: cursors.next,
+                prev: cursors.prev,
+                facets: _facets,
+            }),
+            error: None,
+        }),
+    ))
+}
+
+// filters and sorts are checked against the m_file columns before they are saved, not only
+// when the search is run
+fn check_query(query: &SavedSearchQuery) -> Result<(), AppError> {
+    m_file_repository::pagination_query(
+        query.to_group(),
+        query._sort.clone(),
+        query._q.clone().unwrap_or_default(),
+        &FileScope::every_file(),
+    )?;
+    Ok(())
+}
+
+// searches shared with someone else are reported as missing, not as forbidden
+fn find_visible(
+    db_conn: &mut MysqlConnection,
+    id: i64,
+    user_id: i64,
+) -> Result<MSavedSearch, AppError> {
+    match repository::find_by_id(db_conn, id)? {
+        Some(value) if value.is_visible_to(user_id) => Ok(value),
+        _ => Err(AppError::NotFound),
+    }
+}
+
+fn find_owned(
+    db_conn: &mut MysqlConnection,
+    id: i64,
+    user_id: i64,
+) -> Result<MSavedSearch, AppError> {
+    let saved_search = find_visible(db_conn, id, user_id)?;
+    if !saved_search.is_owner(user_id) {
+        return Err(AppError::Forbidden(
+            "only the owner can change a saved search".to_string(),
+        ));
+    }
+    Ok(saved_search)
+}
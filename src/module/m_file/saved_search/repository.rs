@@ -0,0 +1,86 @@
+use diesel::{dsl::insert_into, prelude::*, sql_query, update};
+
+use crate::{
+    diesel_schema::m_saved_search::dsl::*,
+    dto::response::app_error::AppError,
+    module::m_file::saved_search::schema::MSavedSearch,
+    util::id_generator,
+};
+
+pub fn find_by_id(
+    conn: &mut MysqlConnection,
+    saved_search_id: i64,
+) -> Result<Option<MSavedSearch>, AppError> {
+    let saved_search = m_saved_search
+        .filter(id.eq(saved_search_id))
+        .select(MSavedSearch::as_select())
+        .first::<MSavedSearch>(conn)
+        .optional()
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, saved_search_id)))?;
+
+    Ok(saved_search)
+}
+
+/// Saved searches of the user and the ones shared with them.
+pub fn find_by_user(
+    conn: &mut MysqlConnection,
+    user_id: i64,
+) -> Result<Vec<MSavedSearch>, AppError> {
+    let statement = "SELECT *
+            FROM m_saved_search
+            WHERE created_by = ?
+            OR JSON_CONTAINS(shared_with, CAST(? AS JSON))
+            ORDER BY name ASC, id ASC";
+
+    let data_vec: Vec<MSavedSearch> = sql_query(statement)
+        .bind::<diesel::sql_types::BigInt, _>(user_id)
+        .bind::<diesel::sql_types::BigInt, _>(user_id)
+        .get_results::<MSavedSearch>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}, user_id: {}", error, user_id)))?;
+    Ok(data_vec)
+}
+
+pub fn delete_by_id(conn: &mut MysqlConnection, saved_search_id: i64) -> Result<Option<()>, AppError> {
+    let rows_affected = diesel::delete(m_saved_search.filter(id.eq(saved_search_id)))
+        .execute(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, saved_search_id)))?;
+
+    if rows_affected > 0 {
+        return Ok(Some(()));
+    }
+    Ok(None)
+}
+
+/// Insert the saved search under a new id and return it with its id.
+pub fn insert_saved_search(
+    conn: &mut MysqlConnection,
+    saved_search: MSavedSearch,
+) -> Result<MSavedSearch, AppError> {
+    let mut new_saved_search = saved_search;
+    id_generator::with_new_id(|new_id| {
+        new_saved_search.id = new_id;
+        insert_into(m_saved_search).values(&new_saved_search).execute(conn)
+    })
+    .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(new_saved_search)
+}
+
+pub fn update_saved_search(
+    conn: &mut MysqlConnection,
+    saved_search: MSavedSearch,
+) -> Result<Option<()>, AppError> {
+    let rows_affected = update(m_saved_search.filter(id.eq(saved_search.id)))
+        .set((
+            name.eq(saved_search.name),
+            query.eq(saved_search.query),
+            shared_with.eq(saved_search.shared_with),
+            modified_by.eq(saved_search.modified_by),
+            modified_on.eq(saved_search.modified_on),
+        ))
+        .execute(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, saved_search.id)))?;
+    if rows_affected > 0 {
+        return Ok(Some(()));
+    }
+    Ok(None)
+}
@@ -0,0 +1,17 @@
+use axum::{middleware::from_fn_with_state, routing::{get, post}, Router};
+
+use crate::{
+    dto::enumerator::permission::Permission,
+    middleware::auth_middleware::authorize,
+    module::m_file::saved_search::controller::{create, delete_by_id, find_all, find_by_id, find_files, update},
+};
+
+// saved searches belong to their user, reading files is enough to keep them
+pub fn new() -> Router {
+    Router::new()
+        .route("/list", get(find_all))
+        .route("/", post(create).put(update))
+        .route("/{id}", get(find_by_id).delete(delete_by_id))
+        .route("/{id}/files", get(find_files))
+        .route_layer(from_fn_with_state(Permission::READ, authorize))
+}
@@ -0,0 +1,163 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::{Insertable, Queryable, QueryableByName};
+use diesel::Selectable;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::diesel_schema::m_saved_search;
+use crate::dto::{
+    enumerator::filter_mode::FilterMode,
+    request::{
+        filter_request::{FilterGroup, FilterNode},
+        sort_request::Sort,
+    },
+    response::app_error::AppError,
+};
+use crate::util::serializer::{
+    date_serializer, json_serializer, option_date_serializer, option_json_serializer,
+};
+
+/// A named `_filter`, `_filter_mode`, `_sort` and `_q` of the m_file pagination. Other users it
+/// is shared with see it as a smart folder.
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    Queryable,
+    QueryableByName,
+    Insertable,
+    Selectable
+)]
+#[diesel(table_name = m_saved_search)]
+pub struct MSavedSearch {
+    pub id: i64,
+    pub created_by: i64,
+    #[serde(with = "date_serializer")]
+    pub created_on: NaiveDateTime,
+    pub name: String,
+    // SavedSearchQuery as JSON
+    #[serde(with = "json_serializer")]
+    pub query: String,
+    // JSON array of user ids, the owner excluded
+    #[serde(with = "option_json_serializer")]
+    pub shared_with: Option<String>,
+    pub modified_by: Option<i64>,
+    #[serde(with = "option_date_serializer")]
+    pub modified_on: Option<NaiveDateTime>,
+}
+
+impl MSavedSearch {
+    pub fn from_create_request(request: MSavedSearchRequest, user_id: i64) -> MSavedSearch {
+        let date_now = chrono::Utc::now().naive_utc();
+        MSavedSearch {
+            // allocated on insert
+            id: 0,
+            created_by: user_id,
+            created_on: date_now,
+            name: request.name.unwrap_or_default(),
+            query: serde_json::to_string(&request.query.unwrap_or_default()).unwrap_or_default(),
+            shared_with: shared_with_json(user_id, request.shared_with),
+            modified_by: None,
+            modified_on: None,
+        }
+    }
+
+    pub fn from_update_request(
+        request: MSavedSearchRequest,
+        existing: MSavedSearch,
+        user_id: i64,
+    ) -> MSavedSearch {
+        let date_now = chrono::Utc::now().naive_utc();
+        MSavedSearch {
+            id: existing.id,
+            created_by: existing.created_by,
+            created_on: existing.created_on,
+            name: request.name.unwrap_or_default(),
+            query: serde_json::to_string(&request.query.unwrap_or_default()).unwrap_or_default(),
+            shared_with: shared_with_json(existing.created_by, request.shared_with),
+            modified_by: Some(user_id),
+            modified_on: Some(date_now),
+        }
+    }
+
+    pub fn to_query(&self) -> Result<SavedSearchQuery, AppError> {
+        serde_json::from_str(&self.query).map_err(|error| {
+            AppError::Other(format!("invalid saved search query: {}, id: {}", error, self.id))
+        })
+    }
+
+    pub fn is_owner(&self, user_id: i64) -> bool {
+        self.created_by == user_id
+    }
+
+    pub fn is_visible_to(&self, user_id: i64) -> bool {
+        if self.is_owner(user_id) {
+            return true;
+        }
+        let shared_with: Vec<i64> = self
+            .shared_with
+            .as_deref()
+            .and_then(|value| serde_json::from_str(value).ok())
+            .unwrap_or_default();
+        shared_with.contains(&user_id)
+    }
+}
+
+// distinct user ids other than the owner, None when the search is not shared
+fn shared_with_json(owner_id: i64, user_ids: Option<Vec<i64>>) -> Option<String> {
+    let mut shared_with: Vec<i64> = user_ids
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|user_id| *user_id != owner_id)
+        .collect();
+    shared_with.sort();
+    shared_with.dedup();
+    if shared_with.is_empty() {
+        return None;
+    }
+    serde_json::to_string(&shared_with).ok()
+}
+
+/// The listing parameters of a saved search, the same as the query parameters of
+/// `/m-file/pagination` but with `_filter` and `_sort` as JSON values instead of strings.
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct SavedSearchQuery {
+    #[serde(default)]
+    pub _filter: Vec<FilterNode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub _filter_mode: Option<FilterMode>,
+    #[serde(default)]
+    pub _sort: Vec<Sort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub _q: Option<String>,
+}
+
+impl SavedSearchQuery {
+    pub fn to_group(&self) -> FilterGroup {
+        FilterGroup {
+            mode: self._filter_mode.clone().unwrap_or(FilterMode::AND),
+            filters: self._filter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MSavedSearchRequest {
+    // mandatory on update, ignored on create
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    #[validate(
+        length(min = 1, max = 100, message = "must be between 1-100 chars"),
+        required(message = "mandatory")
+    )]
+    pub name: Option<String>,
+    #[validate(required(message = "mandatory"))]
+    pub query: Option<SavedSearchQuery>,
+    // users who see the search as a smart folder
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 100, message = "must be at most 100 users"))]
+    pub shared_with: Option<Vec<i64>>,
+}
@@ -1,12 +1,22 @@
+use std::collections::BTreeMap;
+
 use chrono::NaiveDateTime;
 use diesel::prelude::{Insertable, Queryable, QueryableByName};
 use diesel::Selectable;
+use lazy_static::lazy_static;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
 use crate::diesel_schema::m_file;
-use crate::util::serializer::{date_serializer, option_date_serializer};
+use crate::dto::enumerator::{export_format::ExportFormat, image_fit::ImageFit, image_format::ImageFormat};
+use crate::dto::response::pagination_response::PaginatedResponse;
+use crate::util::serializer::{date_serializer, option_date_serializer, option_json_serializer};
+
+lazy_static! {
+    static ref CROP_REGEX: Regex = Regex::new(r"^\d+,\d+,\d+,\d+$").unwrap();
+}
 
 #[derive(
     Debug,
@@ -36,6 +46,8 @@ pub struct MFile {
     #[serde(with = "option_date_serializer")]
     pub deleted_on: Option<NaiveDateTime>,
     pub is_delete: bool,
+    #[serde(with = "option_json_serializer")]
+    pub metadata: Option<String>,
 }
 
 impl MFile {
@@ -55,6 +67,7 @@ impl MFile {
             deleted_by: None,
             deleted_on: None,
             is_delete: false,
+            metadata: None,
         }
     }
     pub fn from_create_request(request: MFileRequest) -> MFile {
@@ -80,6 +93,7 @@ impl MFile {
             deleted_by: deleted_by,
             deleted_on: deleted_on,
             is_delete: is_delete,
+            metadata: None,
         }
     }
     pub fn from_update_request(request: MFileRequest, existing: MFile) -> MFile {
@@ -105,6 +119,7 @@ impl MFile {
             deleted_by: deleted_by,
             deleted_on: deleted_on,
             is_delete: is_delete,
+            metadata: existing.metadata,
         }
     }
 }
@@ -133,6 +148,8 @@ pub struct MFileRequest {
     pub is_delete: Option<bool>,
     #[validate(required(message = "mandatory"))]
     pub module_id: Option<i64>,
+    // the acting user, taken from the bearer token and never from the body
+    #[serde(skip_deserializing)]
     #[validate(required(message = "mandatory"))]
     pub user_id: Option<i64>,
 }
@@ -165,8 +182,6 @@ pub struct MFileRenameRequest {
         required(message = "mandatory")
     )]
     pub file_name: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub user_id: Option<i64>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Validate)]
@@ -178,8 +193,36 @@ pub struct MFileCopyMoveRequest {
     pub id: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub file_path: Option<String>,
+}
+
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+#[validate(schema(function = "validate_archive_selector"))]
+pub struct MFileArchiveRequest {
+    #[validate(length(min = 1, max = 10000, message = "must be between 1-10000 items"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ids: Option<Vec<i64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_id: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    #[validate(length(min = 1, max = 100, message = "must be between 1-100 chars"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_name: Option<String>,
+}
+
+fn validate_archive_selector(request: &MFileArchiveRequest) -> Result<(), ValidationError> {
+    if request.ids.is_none()
+        && request.module_id.is_none()
+        && request.user_id.is_none()
+        && request.folder.is_none()
+    {
+        return Err(ValidationError::new("selector")
+            .with_message("ids, module_id, user_id or folder is mandatory".into()));
+    }
+    Ok(())
 }
 
 
@@ -205,4 +248,168 @@ pub struct MFileResponse {
     pub file_size: Option<String>,
     #[validate(required(message = "mandatory"))]
     pub module_id: Option<i64>,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MFileEntryResponse {
+    pub name: String,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_size: Option<u64>,
+    #[serde(with = "option_date_serializer")]
+    pub modified_on: Option<NaiveDateTime>,
+    pub is_dir: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFileThumbnailRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFileWaveformRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFileTransformRequest {
+    #[validate(range(min = 1, message = "must be greater than 0"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[validate(range(min = 1, message = "must be greater than 0"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fit: Option<ImageFit>,
+    // x,y,width,height in source pixels
+    #[validate(regex(path = *CROP_REGEX, message = "must be x,y,width,height"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotate: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ImageFormat>,
+    #[validate(range(min = 1, max = 100, message = "must be between 1-100"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFileSearchRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFileExportRequest {
+    // csv by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ExportFormat>,
+    // comma separated column names, every column by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 255))]
+    pub columns: Option<String>,
+    // IANA name like `Asia/Jakarta`, UTC by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 64))]
+    pub timezone: Option<String>,
+    // strftime pattern, RFC 3339 by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(length(max = 64))]
+    pub date_format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MFileSearchResponse {
+    pub score: f32,
+    pub highlights: BTreeMap<String, String>,
+    pub file: MFile,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFilePreviewRequest {
+    // page, size and sheet apply to table previews
+    #[validate(range(min = 0, message = "must be 0 or greater"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 500, message = "must be between 1-500"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sheet: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MFilePreviewResponse {
+    Html {
+        html: String,
+        charset: Option<String>,
+        truncated: bool,
+    },
+    Table {
+        sheets: Vec<String>,
+        sheet: String,
+        columns: Vec<String>,
+        rows: PaginatedResponse<Vec<String>>,
+        truncated: bool,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MFilePdfResponse {
+    pub page_count: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFilePdfMergeRequest {
+    // pages of the files are appended in this order
+    #[validate(
+        length(min = 2, max = 100, message = "must be between 2-100 items"),
+        required(message = "mandatory")
+    )]
+    pub ids: Option<Vec<i64>>,
+    #[validate(length(min = 3, max = 255, message = "must be between 3-255 chars"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+    // defaults to the module of the first file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFilePdfSplitRequest {
+    #[validate(required(message = "mandatory"))]
+    pub id: Option<i64>,
+    // one new file per item, each item is a page range list like 1-3,5,8-
+    #[validate(
+        length(min = 1, max = 100, message = "must be between 1-100 items"),
+        required(message = "mandatory")
+    )]
+    pub ranges: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFilePdfRotateRequest {
+    #[validate(required(message = "mandatory"))]
+    pub id: Option<i64>,
+    // page range list like 1-3,5, all pages when empty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages: Option<String>,
+    // clockwise, in degrees
+    #[validate(
+        custom(function = "validate_rotation"),
+        required(message = "mandatory")
+    )]
+    pub angle: Option<i64>,
+}
+
+fn validate_rotation(angle: i64) -> Result<(), ValidationError> {
+    if angle == 0 || angle % 90 != 0 {
+        return Err(ValidationError::new("angle")
+            .with_message("must be a multiple of 90".into()));
+    }
+    Ok(())
+}
@@ -0,0 +1,291 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{
+    extract::{Extension, Json, Query},
+    http::StatusCode,
+};
+use serde_json::Value as JsonValue;
+use validator::Validate;
+
+use crate::{
+    dto::{
+        enumerator::permission::Permission,
+        principal::Principal,
+        request::{pagination_request::Pagination, search_request::Search},
+        response::{
+            app_error::AppError, app_response::AppResponse, pagination_response::PaginatedResponse,
+        },
+    },
+    module::m_file::{
+        repository::{self, FileScope},
+        schema::{MFile, MFileSearchRequest, MFileSearchResponse},
+    },
+    state::AppState,
+    util::{
+        file_cache,
+        search_index::{IndexQueue, IndexUpdate, SearchDocument},
+        text_extraction,
+    },
+};
+
+// matches ranked per search, the deepest hit a search may page to and the largest total
+const MAX_SEARCH_HITS: i64 = 10_000;
+// files read from the database at a time while reindexing
+const REINDEX_BATCH_SIZE: i64 = 500;
+
+pub async fn search(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Query(_pagination): Query<Pagination>,
+    Query(_global_search): Query<Search>,
+    Query(_search_request): Query<MFileSearchRequest>,
+) -> Result<(StatusCode, Json<AppResponse<PaginatedResponse<MFileSearchResponse>>>), AppError> {
+    if let Err(err) = _pagination.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    let _page = _pagination.page.unwrap_or(0).max(0);
+    let _size = _pagination.size.unwrap_or(5).clamp(1, 100);
+    let _q = _global_search._q.clone().unwrap_or_default();
+    if _q.trim().is_empty() {
+        return Err(AppError::BadRequest("_q must not be empty".to_string()));
+    }
+
+    // every ranked match is kept in memory
+    let offset = match _page.checked_mul(_size) {
+        Some(value) if value + _size <= MAX_SEARCH_HITS => value,
+        _ => {
+            return Err(AppError::BadRequest(format!(
+                "page * size must not exceed {}",
+                MAX_SEARCH_HITS - _size
+            )));
+        }
+    };
+
+    let module_id = _search_request.module_id;
+    if module_id.is_some() {
+        _principal.check(Permission::READ, module_id)?;
+    }
+    let search_index = _state.search_index.clone();
+    let matches = tokio::task::spawn_blocking(move || {
+        search_index.search(&_q, module_id, MAX_SEARCH_HITS as usize)
+    })
+    .await
+    .map_err(|error| AppError::Other(format!("search failed: {error}")))?
+    .map_err(|error| AppError::Other(format!("search failed: {error}")))?;
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    // the index holds every file, only the readable matches are counted and paged, in the
+    // ranking of the index
+    let _scope = FileScope::readable_by(&_principal);
+    let match_ids = matches.ids();
+    let readable_ids: HashSet<i64> =
+        repository::find_ids_in_scope(&mut db_conn, match_ids.clone(), &_scope)?
+            .into_iter()
+            .collect();
+    let readable_ids: Vec<i64> = match_ids
+        .into_iter()
+        .filter(|value| readable_ids.contains(value))
+        .collect();
+    let total_of_elements = readable_ids.len() as i64;
+    let page_ids: Vec<i64> = readable_ids
+        .into_iter()
+        .skip(offset as usize)
+        .take(_size as usize)
+        .collect();
+
+    let search_index = _state.search_index.clone();
+    let hit_ids = page_ids.clone();
+    let hits = tokio::task::spawn_blocking(move || search_index.hits(&matches, &hit_ids))
+        .await
+        .map_err(|error| AppError::Other(format!("search failed: {error}")))?
+        .map_err(|error| AppError::Other(format!("search failed: {error}")))?;
+
+    // rows deleted meanwhile are skipped
+    let mut m_files: HashMap<i64, MFile> =
+        repository::find_by_ids_in_scope(&mut db_conn, page_ids, &_scope)?
+            .into_iter()
+            .map(|value| (value.id, value))
+            .collect();
+    let content: Vec<MFileSearchResponse> = hits
+        .into_iter()
+        .filter_map(|hit| {
+            m_files.remove(&hit.id).map(|file| MFileSearchResponse {
+                score: hit.score,
+                highlights: hit.highlights,
+                file,
+            })
+        })
+        .collect();
+
+    let paginated_response = PaginatedResponse {
+        content,
+        total_of_elements,
+        total_of_pages: (total_of_elements + _size - 1) / _size,
+        next: None,
+        prev: None,
+        facets: None,
+    };
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(paginated_response),
+            error: None,
+        }),
+    ))
+}
+
+/// Rebuild the index from every file that is not deleted.
+pub async fn reindex(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+) -> Result<(StatusCode, Json<AppResponse<usize>>), AppError> {
+    _principal.check(Permission::ADMIN, None)?;
+
+    // files are read batch by batch on the index worker, each batch on a connection of its own
+    let pool = _state.diesel_pool_mysql.clone();
+    let mut after_id = i64::MIN;
+    let mut batch = Vec::new().into_iter();
+    let mut is_done = false;
+    let documents = std::iter::from_fn(move || {
+        loop {
+            if let Some(m_file) = batch.next() {
+                return Some(Ok(search_document(&m_file)));
+            }
+            if is_done {
+                return None;
+            }
+            let find_result = pool
+                .get()
+                .map_err(|error| format!("get connection failed {error}"))
+                .and_then(|mut db_conn| {
+                    repository::find_after_id(&mut db_conn, after_id, REINDEX_BATCH_SIZE)
+                        .map_err(|error| format!("{:?}", error))
+                });
+            match find_result {
+                Ok(data_vec) => {
+                    is_done = (data_vec.len() as i64) < REINDEX_BATCH_SIZE;
+                    after_id = data_vec.last().map_or(after_id, |value| value.id);
+                    batch = data_vec.into_iter();
+                }
+                Err(error) => {
+                    is_done = true;
+                    return Some(Err(tantivy::TantivyError::SystemError(error)));
+                }
+            }
+        }
+    });
+
+    // queued behind earlier updates, and committed once
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    _state
+        .search_index_queue
+        .send(IndexUpdate::Rebuild(Box::new(documents), sender));
+    let indexed = receiver
+        .await
+        .map_err(|error| AppError::Other(format!("reindex failed: {error}")))?
+        .map_err(|error| AppError::Other(format!("reindex failed: {error}")))?;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(indexed),
+            error: None,
+        }),
+    ))
+}
+
+/// Add or refresh a file in the search index, in the background after earlier updates.
+pub fn index_file(search_index_queue: &IndexQueue, m_file: MFile) {
+    search_index_queue.send(IndexUpdate::Upsert(Box::new(move || search_document(&m_file))));
+}
+
+/// Remove a file from the search index, in the background after earlier updates.
+pub fn unindex_file(search_index_queue: &IndexQueue, id: i64) {
+    search_index_queue.send(IndexUpdate::Delete(id));
+}
+
+fn search_document(m_file: &MFile) -> SearchDocument {
+    let file_name = m_file.file_name.clone().unwrap_or_default();
+    let file_path = m_file.file_path.clone().unwrap_or_default();
+    let metadata: JsonValue = m_file
+        .metadata
+        .as_deref()
+        .and_then(|value| serde_json::from_str(value).ok())
+        .unwrap_or_default();
+
+    // audio tags and camera names are searched as tags, everything else as metadata
+    let mut tags: Vec<String> = Vec::new();
+    if let Some(value) = metadata.pointer("/media/tags").and_then(|value| value.as_object()) {
+        tags.extend(value.values().map(json_text));
+    }
+    for pointer in ["/image/camera_make", "/image/camera_model"] {
+        if let Some(value) = metadata.pointer(pointer).and_then(|value| value.as_str()) {
+            tags.push(value.to_string());
+        }
+    }
+    let mut metadata_text: Vec<String> = Vec::new();
+    collect_text(&metadata, &mut metadata_text);
+
+    SearchDocument {
+        id: m_file.id,
+        file_name: file_name.clone(),
+        file_type: m_file.file_type.clone().unwrap_or_default(),
+        module_id: m_file.module_id.unwrap_or(0),
+        tags,
+        metadata: metadata_text.join(" "),
+        content: document_text(&file_path, &file_name),
+    }
+}
+
+// text extracted at upload, files stored before extraction existed are read now
+fn document_text(file_path: &str, file_name: &str) -> String {
+    let cache_path = file_cache::cache_path(file_path, text_extraction::TEXT_CACHE_KEY);
+    if let Ok(value) = std::fs::read_to_string(cache_path) {
+        return value;
+    }
+    text_extraction::extract(std::path::Path::new(file_path), file_name)
+        .map(|value| value.text)
+        .unwrap_or_default()
+}
+
+fn json_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(value) => value.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn collect_text(value: &JsonValue, texts: &mut Vec<String>) {
+    match value {
+        JsonValue::Object(map) => map
+            .iter()
+            .filter(|(key, _)| key.as_str() != "blurhash")
+            .for_each(|(_, value)| collect_text(value, texts)),
+        JsonValue::Array(values) => values.iter().for_each(|value| collect_text(value, texts)),
+        JsonValue::String(value) => texts.push(value.clone()),
+        _ => {}
+    }
+}
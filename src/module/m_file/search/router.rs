@@ -0,0 +1,13 @@
+use axum::{middleware::from_fn_with_state, routing::{get, post}, Router};
+
+use crate::{
+    dto::enumerator::permission::Permission,
+    middleware::auth_middleware::authorize,
+    module::m_file::search::controller::{reindex, search},
+};
+
+pub fn new() -> Router {
+    Router::new()
+        .route("/", get(search).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .route("/reindex", post(reindex).route_layer(from_fn_with_state(Permission::ADMIN, authorize)))
+}
@@ -0,0 +1,361 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Extension, Json, Path, Query},
+    http::StatusCode,
+};
+use diesel::MysqlConnection;
+use validator::Validate;
+
+use crate::{
+    dto::{
+        enumerator::{file_access::FileAccess, permission::Permission},
+        principal::Principal,
+        request::{
+            filter_request::Filters, pagination_request::Pagination, search_request::Search,
+            sort_request::Sorts,
+        },
+        response::{
+            app_error::AppError, app_response::AppResponse, pagination_response::PaginatedResponse,
+        },
+    },
+    module::m_file::{
+        repository::{self as m_file_repository, FileScope},
+        schema::MFile,
+        share::{
+            repository,
+            schema::{MFileAcl, MFileAclRequest, MFileAclTargetRequest},
+        },
+    },
+    state::AppState,
+};
+
+/// Fail with 403 unless the principal may use the permission on the file. Their role must hold
+/// the permission on the module of the file, and unless it administers the module they must own
+/// the file or have been granted `least_access` on it or on one of its folders.
+pub fn check_access(
+    db_conn: &mut MysqlConnection,
+    _principal: &Principal,
+    m_file: &MFile,
+    permission: Permission,
+    least_access: FileAccess,
+) -> Result<(), AppError> {
+    _principal.check(permission, m_file.module_id)?;
+    if _principal.grants.allows(Permission::ADMIN, m_file.module_id)
+        || m_file.created_by == _principal.user_id
+    {
+        return Ok(());
+    }
+    if repository::is_granted(
+        db_conn,
+        m_file.id,
+        _principal.user_id,
+        _principal.role_id,
+        least_access,
+    )? {
+        return Ok(());
+    }
+    Err(AppError::Forbidden(format!(
+        "{} access to file {} required",
+        least_access, m_file.id
+    )))
+}
+
+pub async fn find_by_target(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Query(_target_request): Query<MFileAclTargetRequest>,
+) -> Result<(StatusCode, Json<AppResponse<Vec<MFileAcl>>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = _target_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let _folder = check_target(
+        &mut db_conn,
+        &_principal,
+        _target_request.file_id,
+        _target_request.folder,
+    )?;
+    let result = repository::find_by_target(&mut db_conn, _target_request.file_id, _folder);
+    match result {
+        Ok(value) => {
+            let status_code = StatusCode::OK;
+            Ok((
+                status_code,
+                Json(AppResponse {
+                    status: status_code.as_u16(),
+                    message: "success".to_owned(),
+                    timestamp: chrono::Utc::now().naive_utc(),
+                    data: Some(value),
+                    error: None,
+                }),
+            ))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Share a file or a folder with a user or a role. Sharing the same target with the same user or
+/// role again replaces the access granted before.
+pub async fn create(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Json(m_file_acl_request): Json<MFileAclRequest>,
+) -> Result<(StatusCode, Json<AppResponse<MFileAcl>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = m_file_acl_request.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+    if m_file_acl_request.user_id.is_some() == m_file_acl_request.role_id.is_some() {
+        return Err(AppError::BadRequest(
+            "either user_id or role_id is mandatory".to_string(),
+        ));
+    }
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let _folder = check_target(
+        &mut db_conn,
+        &_principal,
+        m_file_acl_request.file_id,
+        m_file_acl_request.folder.clone(),
+    )?;
+    let _existing_data = repository::find_by_target(
+        &mut db_conn,
+        m_file_acl_request.file_id,
+        _folder.clone(),
+    )?
+    .into_iter()
+    .find(|value| value.is_same_grantee(&m_file_acl_request));
+
+    let mut _new_acl = MFileAcl::from_request(m_file_acl_request, _folder, _principal.user_id);
+    let result = match _existing_data {
+        Some(value) => {
+            _new_acl.id = value.id;
+            _new_acl.created_by = value.created_by;
+            _new_acl.created_on = value.created_on;
+            repository::update_acl(&mut db_conn, _new_acl.clone())
+                .map(|updated| updated.map(|_| _new_acl))
+        }
+        None => repository::insert_acl(&mut db_conn, _new_acl).map(Some),
+    };
+    match result {
+        Ok(Some(value)) => {
+            let status_code = StatusCode::OK;
+            Ok((
+                status_code,
+                Json(AppResponse {
+                    status: status_code.as_u16(),
+                    message: "success".to_owned(),
+                    timestamp: chrono::Utc::now().naive_utc(),
+                    data: Some(value),
+                    error: None,
+                }),
+            ))
+        }
+        Ok(None) => Err(AppError::Other("save data failed".to_string())),
+        Err(err) => Err(err),
+    }
+}
+
+pub async fn delete_by_id(
+    Path(id): Path<i64>,
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+) -> Result<(StatusCode, Json<AppResponse<String>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}, id: {id}")));
+        }
+    };
+
+    let _existing_data = match repository::find_by_id(&mut db_conn, id)? {
+        Some(value) => value,
+        None => {
+            return Err(AppError::NotFound);
+        }
+    };
+    check_target(
+        &mut db_conn,
+        &_principal,
+        _existing_data.file_id,
+        _existing_data.folder,
+    )?;
+    let result = repository::delete_by_id(&mut db_conn, id);
+    match result {
+        Ok(Some(_)) => {
+            let status_code = StatusCode::OK;
+            Ok((
+                status_code,
+                Json(AppResponse {
+                    status: status_code.as_u16(),
+                    message: "success".to_owned(),
+                    timestamp: chrono::Utc::now().naive_utc(),
+                    data: None,
+                    error: None,
+                }),
+            ))
+        }
+        Ok(None) => Err(AppError::NotFound),
+        Err(err) => Err(err),
+    }
+}
+
+/// Files other users shared with the principal or their role, paged and filtered like
+/// `/m-file/pagination`.
+pub async fn find_shared(
+    Extension(_state): Extension<Arc<AppState>>,
+    _principal: Principal,
+    Query(_pagination): Query<Pagination>,
+    Query(_sort): Query<Sorts>,
+    Query(_filter): Query<Filters>,
+    Query(_global_search): Query<Search>,
+) -> Result<(StatusCode, Json<AppResponse<PaginatedResponse<MFile>>>), AppError> {
+    log::info!("status: {}", _state.status);
+
+    if let Err(err) = _pagination.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+    if let Err(err) = _filter.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+    if let Err(err) = _sort.validate() {
+        return Err(AppError::InvalidRequest(err));
+    };
+
+    let _page = _pagination.page.unwrap_or(0).max(0);
+    let _size = _pagination.size.unwrap_or(5).max(1);
+    let _filter_group = _filter.to_group();
+    let _sorts = _sort._sort.clone().unwrap_or_default();
+    let _q = _global_search._q.clone().unwrap_or_default();
+    log::info!(
+        "shared with {:?}, page {:?}, size {:?}, filters {:?}, sorts {:?}, global_search {:?}",
+        _principal.user_id,
+        _page,
+        _size,
+        _filter_group,
+        _sorts,
+        _q
+    );
+
+    // get db connection
+    let db_conn_result = _state.diesel_pool_mysql.get();
+    let mut db_conn;
+    match db_conn_result {
+        Ok(value) => {
+            db_conn = value;
+        }
+        Err(error) => {
+            return Err(AppError::Other(format!("get connection failed {error}")));
+        }
+    };
+
+    let _scope = FileScope::shared_with(&_principal);
+    let (content, total_of_elements, cursors) = m_file_repository::pagination(
+        &mut db_conn,
+        _page,
+        _size,
+        _pagination.cursor.clone(),
+        m_file_repository::pagination_query(_filter_group, _sorts, _q, &_scope)?,
+    )?;
+    let total_of_pages = (total_of_elements + _size - 1) / _size;
+
+    let status_code = StatusCode::OK;
+    Ok((
+        status_code,
+        Json(AppResponse {
+            status: status_code.as_u16(),
+            message: "success".to_owned(),
+            timestamp: chrono::Utc::now().naive_utc(),
+            data: Some(PaginatedResponse {
+                content,
+                total_of_elements,
+                total_of_pages,
+                next: cursors.next,
+                prev: cursors.prev,
+                facets: None,
+            }),
+            error: None,
+        }),
+    ))
+}
+
+// only owners share a file, and a folder only when they own every file under it. Returns the
+// folder without leading and trailing '/'.
+fn check_target(
+    db_conn: &mut MysqlConnection,
+    _principal: &Principal,
+    file_id: Option<i64>,
+    folder: Option<String>,
+) -> Result<Option<String>, AppError> {
+    match (file_id, folder) {
+        (Some(value), None) => {
+            let m_file = match m_file_repository::find_by_id(db_conn, value)? {
+                Some(m_file) => m_file,
+                None => {
+                    return Err(AppError::NotFound);
+                }
+            };
+            check_access(db_conn, _principal, &m_file, Permission::UPDATE, FileAccess::OWNER)?;
+            Ok(None)
+        }
+        (None, Some(value)) => {
+            let folder = value.trim_matches('/').to_string();
+            if folder.is_empty() || folder.split('/').any(|part| part == "..") {
+                return Err(AppError::BadRequest(format!("invalid folder: {folder}")));
+            }
+            let total =
+                m_file_repository::count_by_folder(db_conn, &folder, &FileScope::every_file())?;
+            if total == 0 {
+                return Err(AppError::NotFound);
+            }
+            let owned = m_file_repository::count_by_folder(
+                db_conn,
+                &folder,
+                &FileScope::accessible_by(_principal, Permission::UPDATE, FileAccess::OWNER),
+            )?;
+            if owned < total {
+                return Err(AppError::Forbidden(format!(
+                    "owner access to every file under {folder} required"
+                )));
+            }
+            Ok(Some(folder))
+        }
+        _ => Err(AppError::BadRequest(
+            "either file_id or folder is mandatory".to_string(),
+        )),
+    }
+}
@@ -0,0 +1,134 @@
+use diesel::{dsl::insert_into, prelude::*, update};
+
+use crate::{
+    config::environment::CONFIG,
+    diesel_schema::m_file_acl::dsl::*,
+    dto::{
+        database::CountResult, enumerator::file_access::FileAccess,
+        response::app_error::AppError,
+    },
+    module::m_file::share::schema::MFileAcl,
+    util::{
+        id_generator,
+        query_builder::{bind_all, BindValue},
+    },
+};
+
+/// Condition on m_file rows matching the ones shared with the user or their role with at least
+/// `least_access`, directly or through one of their folders. A folder share only covers the
+/// files of the user who shared it, not the ones others add to the folder later.
+pub fn shared_condition(
+    grantee_user_id: i64,
+    grantee_role_id: Option<i64>,
+    least_access: FileAccess,
+) -> (String, Vec<BindValue>) {
+    let mut binds = vec![BindValue::Integer(grantee_user_id)];
+    let mut grantee = "m_file_acl.user_id = ?".to_string();
+    if let Some(value) = grantee_role_id {
+        grantee = format!("({} OR m_file_acl.role_id = ?)", grantee);
+        binds.push(BindValue::Integer(value));
+    }
+    let levels = least_access.at_least();
+    let placeholders = vec!["?"; levels.len()].join(", ");
+    binds.extend(levels.iter().map(|value| BindValue::Text(value.to_string())));
+    binds.push(BindValue::Text(CONFIG.file_root_dir.clone()));
+    (
+        format!(
+            "EXISTS (SELECT 1 FROM m_file_acl WHERE {} AND m_file_acl.access IN ({}) AND (m_file_acl.file_id = m_file.id OR (m_file.created_by = m_file_acl.created_by AND LOCATE(CONCAT(?, '/', m_file_acl.folder, '/'), m_file.file_path) = 1)))",
+            grantee, placeholders
+        ),
+        binds,
+    )
+}
+
+/// Whether the file is shared with the user or their role with at least `least_access`.
+pub fn is_granted(
+    conn: &mut MysqlConnection,
+    mfile_id: i64,
+    grantee_user_id: i64,
+    grantee_role_id: Option<i64>,
+    least_access: FileAccess,
+) -> Result<bool, AppError> {
+    let (condition, mut binds) = shared_condition(grantee_user_id, grantee_role_id, least_access);
+    binds.insert(0, BindValue::Integer(mfile_id));
+    let results = bind_all(
+        format!("SELECT COUNT(*) AS count FROM m_file WHERE id = ? AND {}", condition),
+        binds,
+    )
+    .load::<CountResult>(conn)
+    .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, mfile_id)))?;
+    Ok(results.first().is_some_and(|value| value.count > 0))
+}
+
+pub fn find_by_id(conn: &mut MysqlConnection, acl_id: i64) -> Result<Option<MFileAcl>, AppError> {
+    let acl = m_file_acl
+        .filter(id.eq(acl_id))
+        .select(MFileAcl::as_select())
+        .first::<MFileAcl>(conn)
+        .optional()
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, acl_id)))?;
+
+    Ok(acl)
+}
+
+/// Entries on exactly the file or the folder, not the ones inherited from parent folders.
+pub fn find_by_target(
+    conn: &mut MysqlConnection,
+    target_file_id: Option<i64>,
+    target_folder: Option<String>,
+) -> Result<Vec<MFileAcl>, AppError> {
+    let mut query = m_file_acl.select(MFileAcl::as_select()).into_boxed();
+    match (target_file_id, target_folder) {
+        (Some(value), _) => query = query.filter(file_id.eq(value)),
+        (None, Some(value)) => query = query.filter(folder.eq(value)),
+        (None, None) => return Ok(Vec::new()),
+    }
+
+    let data_vec = query
+        .order(id.asc())
+        .load::<MFileAcl>(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(data_vec)
+}
+
+/// Insert the entry under a new id and return it with its id.
+pub fn insert_acl(conn: &mut MysqlConnection, acl: MFileAcl) -> Result<MFileAcl, AppError> {
+    let mut new_acl = acl;
+    id_generator::with_new_id(|new_id| {
+        new_acl.id = new_id;
+        insert_into(m_file_acl).values(&new_acl).execute(conn)
+    })
+    .map_err(|error| AppError::Other(format!("query failed: {}", error)))?;
+    Ok(new_acl)
+}
+
+/// Change the access of an entry, its creator stays the same so a folder share keeps covering
+/// the same files.
+pub fn update_acl(conn: &mut MysqlConnection, acl: MFileAcl) -> Result<Option<()>, AppError> {
+    let rows_affected = update(m_file_acl.filter(id.eq(acl.id)))
+        .set(access.eq(acl.access))
+        .execute(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, acl.id)))?;
+    if rows_affected > 0 {
+        return Ok(Some(()));
+    }
+    Ok(None)
+}
+
+pub fn delete_by_id(conn: &mut MysqlConnection, acl_id: i64) -> Result<Option<()>, AppError> {
+    let rows_affected = diesel::delete(m_file_acl.filter(id.eq(acl_id)))
+        .execute(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, acl_id)))?;
+
+    if rows_affected > 0 {
+        return Ok(Some(()));
+    }
+    Ok(None)
+}
+
+/// Remove the entries of a deleted file.
+pub fn delete_by_file_id(conn: &mut MysqlConnection, mfile_id: i64) -> Result<usize, AppError> {
+    diesel::delete(m_file_acl.filter(file_id.eq(mfile_id)))
+        .execute(conn)
+        .map_err(|error| AppError::Other(format!("query failed: {}, id: {}", error, mfile_id)))
+}
@@ -0,0 +1,16 @@
+use axum::{middleware::from_fn_with_state, routing::{delete, get, post}, Router};
+
+use crate::{
+    dto::enumerator::permission::Permission,
+    middleware::auth_middleware::authorize,
+    module::m_file::share::controller::{create, delete_by_id, find_by_target, find_shared},
+};
+
+// changing who a file is shared with is updating the file
+pub fn new() -> Router {
+    Router::new()
+        .route("/shared-with-me", get(find_shared).route_layer(from_fn_with_state(Permission::READ, authorize)))
+        .route("/list", get(find_by_target).route_layer(from_fn_with_state(Permission::UPDATE, authorize)))
+        .route("/", post(create).route_layer(from_fn_with_state(Permission::UPDATE, authorize)))
+        .route("/{id}", delete(delete_by_id).route_layer(from_fn_with_state(Permission::UPDATE, authorize)))
+}
@@ -0,0 +1,88 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::{Insertable, Queryable, QueryableByName};
+use diesel::Selectable;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::diesel_schema::m_file_acl;
+use crate::dto::enumerator::file_access::FileAccess;
+use crate::util::serializer::date_serializer;
+
+/// Access granted to a user or to every user of a role, on a file or on the files under a folder
+/// owned by the user who shared it.
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    Queryable,
+    QueryableByName,
+    Insertable,
+    Selectable
+)]
+#[diesel(table_name = m_file_acl)]
+pub struct MFileAcl {
+    pub id: i64,
+    pub file_id: Option<i64>,
+    // path relative to the file root dir, without leading or trailing '/'
+    pub folder: Option<String>,
+    pub user_id: Option<i64>,
+    pub role_id: Option<i64>,
+    // FileAccess
+    pub access: String,
+    pub created_by: i64,
+    #[serde(with = "date_serializer")]
+    pub created_on: NaiveDateTime,
+}
+
+impl MFileAcl {
+    pub fn from_request(
+        request: MFileAclRequest,
+        folder: Option<String>,
+        user_id: i64,
+    ) -> MFileAcl {
+        let date_now = chrono::Utc::now().naive_utc();
+        MFileAcl {
+            // allocated on insert
+            id: 0,
+            file_id: request.file_id,
+            folder,
+            user_id: request.user_id,
+            role_id: request.role_id,
+            access: request.access.unwrap_or(FileAccess::VIEWER).to_string(),
+            created_by: user_id,
+            created_on: date_now,
+        }
+    }
+
+    pub fn is_same_grantee(&self, request: &MFileAclRequest) -> bool {
+        self.user_id == request.user_id && self.role_id == request.role_id
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFileAclRequest {
+    // either file_id or folder
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(length(min = 1, max = 255, message = "must be between 1-255 chars"))]
+    pub folder: Option<String>,
+    // either user_id or role_id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role_id: Option<i64>,
+    #[validate(required(message = "mandatory"))]
+    pub access: Option<FileAccess>,
+}
+
+/// The file or folder whose entries are listed, either `file_id` or `folder`.
+#[derive(Debug, Deserialize, Serialize, Validate)]
+pub struct MFileAclTargetRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[validate(length(min = 1, max = 255, message = "must be between 1-255 chars"))]
+    pub folder: Option<String>,
+}
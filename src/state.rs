@@ -1,6 +1,12 @@
 use std::sync::Arc;
 
 use diesel::{r2d2, MysqlConnection};
+
+use crate::util::{
+    import_job::ImportJobs, jwt::Jwt, permission::RolePermissions,
+    search_index::{IndexQueue, SearchIndex},
+    watermark::WatermarkPolicies,
+};
 // use diesel_async::{pooled_connection::AsyncDieselConnectionManager, AsyncPgConnection};
 
 
@@ -9,5 +15,11 @@ use diesel::{r2d2, MysqlConnection};
 pub struct AppState {
     // pub diesel_pool_postgres_async: bb8::Pool<AsyncDieselConnectionManager<AsyncPgConnection>>,
     pub diesel_pool_mysql: Arc<r2d2::Pool<r2d2::ConnectionManager<MysqlConnection>>>,
-    pub status: String
+    pub status: String,
+    pub search_index: Arc<SearchIndex>,
+    pub search_index_queue: Arc<IndexQueue>,
+    pub watermarks: Arc<WatermarkPolicies>,
+    pub import_jobs: Arc<ImportJobs>,
+    pub jwt: Arc<Jwt>,
+    pub role_permissions: Arc<RolePermissions>,
 }
@@ -0,0 +1,581 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Cursor, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use axum::body::Bytes;
+use chrono::NaiveDateTime;
+use flate2::read::GzDecoder;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::ReceiverStream;
+use uuid::Uuid;
+use zip::{
+    result::ZipError, write::SimpleFileOptions, CompressionMethod, DateTime, ZipArchive, ZipWriter,
+};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+// small archives may be extracted regardless of their compression ratio
+const RATIO_FREE_SIZE: u64 = 1024 * 1024;
+
+pub struct ArchiveEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified_on: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+pub struct ExtractLimit {
+    pub max_entries: usize,
+    pub max_size: u64,
+    pub max_ratio: u64,
+}
+
+pub struct ExtractedEntry {
+    pub folder: String,
+    pub file_name: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+pub struct ArchiveListEntry {
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: Option<u64>,
+    pub modified_on: Option<NaiveDateTime>,
+    pub is_dir: bool,
+}
+
+/// Detect a supported archive from its magic bytes.
+pub fn detect_format(bytes: &[u8]) -> Option<ArchiveFormat> {
+    if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        return Some(ArchiveFormat::Zip);
+    }
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        return Some(ArchiveFormat::TarGz);
+    }
+    if bytes.len() > 262 && &bytes[257..262] == b"ustar" {
+        return Some(ArchiveFormat::Tar);
+    }
+    None
+}
+
+/// Detect the archive format of a stored file by reading its header.
+pub fn detect_file_format(path: &Path) -> io::Result<Option<ArchiveFormat>> {
+    let mut header: Vec<u8> = Vec::with_capacity(512);
+    File::open(path)?.take(512).read_to_end(&mut header)?;
+    Ok(detect_format(&header))
+}
+
+/// List the entries of a stored archive.
+///
+/// Zip files are listed from the central directory and plain tar files by seeking from header
+/// to header, so the entry data is never read. Gzipped tar files have to be decompressed.
+pub fn list_entries(path: &Path, format: ArchiveFormat) -> io::Result<Vec<ArchiveListEntry>> {
+    let mut entries: Vec<ArchiveListEntry> = Vec::new();
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip_archive = ZipArchive::new(File::open(path)?).map_err(zip_error)?;
+            for index in 0..zip_archive.len() {
+                let zip_file = zip_archive.by_index_raw(index).map_err(zip_error)?;
+                entries.push(ArchiveListEntry {
+                    name: zip_file.name().to_string(),
+                    size: zip_file.size(),
+                    compressed_size: Some(zip_file.compressed_size()),
+                    modified_on: zip_file
+                        .last_modified()
+                        .and_then(|value| NaiveDateTime::try_from(value).ok()),
+                    is_dir: zip_file.is_dir(),
+                });
+            }
+        }
+        ArchiveFormat::Tar => {
+            let mut tar_archive = tar::Archive::new(File::open(path)?);
+            for entry in tar_archive.entries_with_seek().map_err(decode_error)? {
+                entries.push(tar_list_entry(&entry.map_err(decode_error)?)?);
+            }
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar_archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+            for entry in tar_archive.entries().map_err(decode_error)? {
+                entries.push(tar_list_entry(&entry.map_err(decode_error)?)?);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn tar_list_entry<R: Read>(entry: &tar::Entry<R>) -> io::Result<ArchiveListEntry> {
+    let header = entry.header();
+    Ok(ArchiveListEntry {
+        name: String::from_utf8_lossy(&entry.path_bytes()).to_string(),
+        size: header.size().map_err(decode_error)?,
+        compressed_size: None,
+        modified_on: header
+            .mtime()
+            .ok()
+            .and_then(|value| chrono::DateTime::from_timestamp(value as i64, 0))
+            .map(|value| value.naive_utc()),
+        is_dir: header.entry_type().is_dir(),
+    })
+}
+
+/// Stream a single entry out of a stored archive.
+///
+/// Resolves to `None` when the archive has no regular file named `entry_name`, otherwise to
+/// the entry size and its content stream.
+pub async fn entry_stream(
+    path: PathBuf,
+    format: ArchiveFormat,
+    entry_name: String,
+) -> io::Result<Option<(u64, ReceiverStream<io::Result<Bytes>>)>> {
+    let (sender, receiver) = mpsc::channel::<io::Result<Bytes>>(16);
+    let (found_sender, found_receiver) = oneshot::channel::<io::Result<Option<u64>>>();
+
+    tokio::task::spawn_blocking(move || {
+        let error_sender = sender.clone();
+        let mut found_sender = Some(found_sender);
+        let mut on_found = |size: u64| {
+            if let Some(value) = found_sender.take() {
+                let _ = value.send(Ok(Some(size)));
+            }
+        };
+        let result = write_entry(
+            &path,
+            format,
+            &entry_name,
+            &mut on_found,
+            ChannelWriter::new(sender),
+        );
+        match result {
+            Ok(true) => {}
+            Ok(false) => {
+                if let Some(value) = found_sender.take() {
+                    let _ = value.send(Ok(None));
+                }
+            }
+            Err(error) => {
+                if let Some(value) = found_sender.take() {
+                    let _ = value.send(Err(error));
+                } else {
+                    log::error!("write archive entry failed: {}", error);
+                    let _ = error_sender.blocking_send(Err(error));
+                }
+            }
+        }
+    });
+
+    let found = found_receiver
+        .await
+        .map_err(|error| io::Error::other(error.to_string()))??;
+    Ok(found.map(|size| (size, ReceiverStream::new(receiver))))
+}
+
+fn write_entry(
+    path: &Path,
+    format: ArchiveFormat,
+    entry_name: &str,
+    on_found: &mut dyn FnMut(u64),
+    mut writer: ChannelWriter,
+) -> io::Result<bool> {
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip_archive = ZipArchive::new(File::open(path)?).map_err(zip_error)?;
+            let Some(index) = zip_archive.index_for_name(entry_name) else {
+                return Ok(false);
+            };
+            let mut zip_file = zip_archive.by_index(index).map_err(zip_error)?;
+            if zip_file.is_dir() {
+                return Ok(false);
+            }
+            on_found(zip_file.size());
+            io::copy(&mut zip_file, &mut writer)?;
+        }
+        ArchiveFormat::Tar => {
+            let mut tar_archive = tar::Archive::new(File::open(path)?);
+            let entries = tar_archive.entries_with_seek().map_err(decode_error)?;
+            if !write_tar_entry(entries, entry_name, on_found, &mut writer)? {
+                return Ok(false);
+            }
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar_archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+            let entries = tar_archive.entries().map_err(decode_error)?;
+            if !write_tar_entry(entries, entry_name, on_found, &mut writer)? {
+                return Ok(false);
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(true)
+}
+
+fn write_tar_entry<R: Read>(
+    entries: tar::Entries<'_, R>,
+    entry_name: &str,
+    on_found: &mut dyn FnMut(u64),
+    writer: &mut ChannelWriter,
+) -> io::Result<bool> {
+    for entry in entries {
+        let mut entry = entry.map_err(decode_error)?;
+        if !entry.header().entry_type().is_file() || *entry.path_bytes() != *entry_name.as_bytes() {
+            continue;
+        }
+        on_found(entry.header().size().map_err(decode_error)?);
+        io::copy(&mut entry, writer)?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Split an archive entry name into safe path components.
+///
+/// Returns `None` for names that try to leave the extraction directory.
+pub fn sanitize_entry_path(entry_name: &str) -> Option<Vec<String>> {
+    let mut components: Vec<String> = Vec::new();
+    for component in entry_name.replace('\\', "/").split('/') {
+        match component {
+            "" | "." => {}
+            ".." => return None,
+            value => {
+                if value.contains(':') || value.chars().any(|c| c.is_control()) {
+                    return None;
+                }
+                components.push(value.to_string());
+            }
+        }
+    }
+    if components.is_empty() {
+        return None;
+    }
+    Some(components)
+}
+
+/// Extract every regular file of the archive below `target_dir`.
+///
+/// Each entry is stored under its sanitized folder with a generated name. Extraction stops
+/// with `InvalidData` once the entry count, the total extracted size or the ratio between
+/// extracted and archive size goes over `limit`, and on archives that cannot be decoded.
+pub fn extract_archive(
+    bytes: &[u8],
+    format: ArchiveFormat,
+    target_dir: &Path,
+    limit: &ExtractLimit,
+) -> io::Result<Vec<ExtractedEntry>> {
+    let mut extractor = Extractor {
+        target_dir: target_dir.to_path_buf(),
+        max_entries: limit.max_entries,
+        max_size: limit.max_size.min(
+            (bytes.len() as u64)
+                .saturating_mul(limit.max_ratio)
+                .max(RATIO_FREE_SIZE),
+        ),
+        total_size: 0,
+        entries: Vec::new(),
+    };
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip_archive = ZipArchive::new(Cursor::new(bytes)).map_err(zip_error)?;
+            if zip_archive.len() > limit.max_entries {
+                return Err(limit_error("too many entries"));
+            }
+            for index in 0..zip_archive.len() {
+                let mut zip_file = zip_archive.by_index(index).map_err(zip_error)?;
+                if zip_file.is_dir() {
+                    continue;
+                }
+                let entry_name = zip_file.name().to_string();
+                extractor.extract_entry(&entry_name, &mut zip_file)?;
+            }
+        }
+        ArchiveFormat::Tar => {
+            extractor.extract_tar(tar::Archive::new(Cursor::new(bytes)))?;
+        }
+        ArchiveFormat::TarGz => {
+            extractor.extract_tar(tar::Archive::new(GzDecoder::new(Cursor::new(bytes))))?;
+        }
+    }
+
+    Ok(extractor.entries)
+}
+
+struct Extractor {
+    target_dir: PathBuf,
+    max_entries: usize,
+    max_size: u64,
+    total_size: u64,
+    entries: Vec<ExtractedEntry>,
+}
+
+impl Extractor {
+    fn extract_tar<R: Read>(&mut self, mut tar_archive: tar::Archive<R>) -> io::Result<()> {
+        for entry in tar_archive.entries().map_err(decode_error)? {
+            let mut entry = entry.map_err(decode_error)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let entry_name = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+            self.extract_entry(&entry_name, &mut entry)?;
+        }
+        Ok(())
+    }
+
+    fn extract_entry<R: Read>(&mut self, entry_name: &str, reader: &mut R) -> io::Result<()> {
+        let Some(mut components) = sanitize_entry_path(entry_name) else {
+            log::info!("skip unsafe archive entry: {}", entry_name);
+            return Ok(());
+        };
+        if self.entries.len() >= self.max_entries {
+            return Err(limit_error("too many entries"));
+        }
+
+        let file_name = components.pop().unwrap_or_default();
+        let folder = components.join("/");
+        let dir_path = self.target_dir.join(&folder);
+        fs::create_dir_all(&dir_path)?;
+        let path = dir_path.join(Uuid::new_v4().to_string());
+
+        let mut file = File::create(&path)?;
+        let remaining = self.max_size - self.total_size;
+        let size = io::copy(&mut DecodeReader(reader).take(remaining + 1), &mut file)?;
+        if size > remaining {
+            return Err(limit_error("extracted size exceeds limit"));
+        }
+        self.total_size += size;
+
+        self.entries.push(ExtractedEntry {
+            folder,
+            file_name,
+            path,
+            size,
+        });
+        Ok(())
+    }
+}
+
+// reads of archive data, failures are errors of the archive, not of the disk
+struct DecodeReader<'a, R>(&'a mut R);
+
+impl<R: Read> Read for DecodeReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf).map_err(decode_error)
+    }
+}
+
+// a corrupt, truncated or unsupported archive, reported as `InvalidData`; failures of the file
+// underneath keep their kind
+fn decode_error(error: io::Error) -> io::Error {
+    match error.kind() {
+        io::ErrorKind::Other
+        | io::ErrorKind::InvalidInput
+        | io::ErrorKind::UnexpectedEof
+        | io::ErrorKind::Unsupported => invalid_archive(error),
+        _ => error,
+    }
+}
+
+fn zip_error(error: ZipError) -> io::Error {
+    match error {
+        ZipError::Io(error) => decode_error(error),
+        error => invalid_archive(error),
+    }
+}
+
+fn invalid_archive(error: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("invalid archive: {}", error))
+}
+
+fn limit_error(message: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("archive rejected: {}", message),
+    )
+}
+
+/// Return a zip entry name built from `file_name` that is not used yet.
+///
+/// Duplicates get a ` (n)` suffix before the extension, e.g. `report (1).pdf`.
+pub fn unique_entry_name(used_names: &mut HashSet<String>, file_name: &str) -> String {
+    let mut base_name = file_name
+        .replace(['/', '\\'], "_")
+        .trim_start_matches('.')
+        .to_string();
+    if base_name.is_empty() {
+        base_name = "file".to_string();
+    }
+
+    let (stem, extension) = match base_name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem.to_string(), format!(".{}", extension)),
+        _ => (base_name.clone(), String::new()),
+    };
+
+    let mut entry_name = base_name;
+    let mut counter = 1;
+    while used_names.contains(&entry_name.to_lowercase()) {
+        entry_name = format!("{} ({}){}", stem, counter, extension);
+        counter += 1;
+    }
+    used_names.insert(entry_name.to_lowercase());
+    entry_name
+}
+
+/// Write every entry into a zip archive on a blocking thread and return the archive as a
+/// byte stream, so nothing is buffered on disk.
+pub fn zip_stream(entries: Vec<ArchiveEntry>) -> ReceiverStream<io::Result<Bytes>> {
+    let (sender, receiver) = mpsc::channel::<io::Result<Bytes>>(16);
+
+    tokio::task::spawn_blocking(move || {
+        let error_sender = sender.clone();
+        if let Err(error) = write_zip(entries, ChannelWriter::new(sender)) {
+            log::error!("write zip archive failed: {}", error);
+            let _ = error_sender.blocking_send(Err(error));
+        }
+    });
+
+    ReceiverStream::new(receiver)
+}
+
+fn write_zip(entries: Vec<ArchiveEntry>, writer: ChannelWriter) -> io::Result<()> {
+    let mut zip_writer = ZipWriter::new_stream(writer);
+
+    for entry in entries {
+        let mut options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(entry.size >= u32::MAX as u64);
+        if let Some(Ok(value)) = entry.modified_on.map(DateTime::try_from) {
+            options = options.last_modified_time(value);
+        }
+
+        let mut file = File::open(&entry.path)?;
+        zip_writer.start_file(entry.name, options)?;
+        io::copy(&mut file, &mut zip_writer)?;
+    }
+
+    let mut writer = zip_writer.finish()?.into_inner();
+    writer.flush()
+}
+
+/// `Write` adapter that forwards fixed size chunks to the response body channel.
+pub struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+    buffer: Vec<u8>,
+}
+
+impl ChannelWriter {
+    pub fn new(sender: mpsc::Sender<io::Result<Bytes>>) -> ChannelWriter {
+        ChannelWriter {
+            sender,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    fn send_buffer(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::replace(
+            &mut self.buffer,
+            Vec::with_capacity(CHUNK_SIZE),
+        ));
+        self.sender
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "client disconnected"))
+    }
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.send_buffer()?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buffer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_entry_path_splits_folders() {
+        assert_eq!(
+            sanitize_entry_path("docs/2024/report.pdf").unwrap(),
+            vec!["docs", "2024", "report.pdf"]
+        );
+        assert_eq!(
+            sanitize_entry_path("./docs//report.pdf").unwrap(),
+            vec!["docs", "report.pdf"]
+        );
+        assert_eq!(
+            sanitize_entry_path("docs\\report.pdf").unwrap(),
+            vec!["docs", "report.pdf"]
+        );
+        // absolute paths are taken as relative to the target folder
+        assert_eq!(sanitize_entry_path("/etc/passwd").unwrap(), vec!["etc", "passwd"]);
+    }
+
+    #[test]
+    fn sanitize_entry_path_rejects_unsafe_names() {
+        assert!(sanitize_entry_path("../secret").is_none());
+        assert!(sanitize_entry_path("docs/../../secret").is_none());
+        assert!(sanitize_entry_path("..\\secret").is_none());
+        assert!(sanitize_entry_path("C:/windows/system.ini").is_none());
+        assert!(sanitize_entry_path("docs/report\u{0}.pdf").is_none());
+        assert!(sanitize_entry_path("").is_none());
+        assert!(sanitize_entry_path("./").is_none());
+    }
+
+    fn extract_error(bytes: &[u8], format: ArchiveFormat, target_dir: &Path) -> io::ErrorKind {
+        let limit = ExtractLimit {
+            max_entries: 10,
+            max_size: 1024 * 1024,
+            max_ratio: 100,
+        };
+        match extract_archive(bytes, format, target_dir, &limit) {
+            Ok(_) => panic!("corrupt archive extracted"),
+            Err(error) => error.kind(),
+        }
+    }
+
+    #[test]
+    fn extract_archive_reports_corrupt_archives_as_invalid_data() {
+        let target_dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+
+        // zip magic bytes without a central directory
+        let zip = b"PK\x03\x04corrupt";
+        assert_eq!(
+            extract_error(zip, ArchiveFormat::Zip, &target_dir),
+            io::ErrorKind::InvalidData
+        );
+
+        // gzip magic bytes followed by garbage
+        let tar_gz = b"\x1f\x8b\x08\x00\x00\x00\x00\x00\x00\x03corrupt";
+        assert_eq!(
+            extract_error(tar_gz, ArchiveFormat::TarGz, &target_dir),
+            io::ErrorKind::InvalidData
+        );
+
+        // a tar header with a broken checksum
+        let mut tar = vec![b'x'; 512];
+        tar.extend_from_slice(&[0; 1024]);
+        assert_eq!(
+            extract_error(&tar, ArchiveFormat::Tar, &target_dir),
+            io::ErrorKind::InvalidData
+        );
+        assert!(!target_dir.exists());
+    }
+}
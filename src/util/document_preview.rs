@@ -0,0 +1,238 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use calamine::{open_workbook, Reader as WorkbookReader, Xlsx};
+use chardetng::{EncodingDetector, Iso2022JpDetection, Utf8Detection};
+use encoding_rs::Encoding;
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, Options, Parser};
+use serde::{Deserialize, Serialize};
+use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
+
+const CODE_THEME: &str = "InspiredGitHub";
+
+const MARKDOWN_EXTENSIONS: [&str; 2] = ["md", "markdown"];
+const PLAIN_TEXT_EXTENSIONS: [&str; 3] = ["txt", "log", "text"];
+const CODE_EXTENSIONS: [&str; 24] = [
+    "rs", "js", "ts", "py", "java", "kt", "go", "c", "h", "cpp", "hpp", "cs", "php", "rb", "sh",
+    "sql", "json", "xml", "html", "htm", "css", "yml", "yaml", "toml",
+];
+
+lazy_static! {
+    static ref SYNTAX_SET: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEME_SET: ThemeSet = ThemeSet::load_defaults();
+}
+
+/// Rendered preview of a document, cached as a whole and paged when served.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Preview {
+    Html {
+        html: String,
+        // detected encoding of the source text
+        charset: Option<String>,
+        // the source was longer than the size limit
+        truncated: bool,
+    },
+    Table {
+        sheets: Vec<PreviewSheet>,
+        // the source had more rows than the row limit
+        truncated: bool,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PreviewSheet {
+    pub name: String,
+    // first row of the sheet
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn extension(file_name: &str) -> String {
+    Path::new(file_name)
+        .extension()
+        .and_then(|value| value.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+pub fn is_supported(file_name: &str) -> bool {
+    let extension = extension(file_name);
+    MARKDOWN_EXTENSIONS.contains(&extension.as_str())
+        || PLAIN_TEXT_EXTENSIONS.contains(&extension.as_str())
+        || CODE_EXTENSIONS.contains(&extension.as_str())
+        || ["csv", "tsv", "xlsx"].contains(&extension.as_str())
+}
+
+/// Render the preview of a stored file.
+///
+/// At most `max_size` bytes of text sources are read, larger XLSX files are refused. Tables
+/// keep at most `max_rows` rows over all sheets.
+pub fn render(path: &Path, file_name: &str, max_size: u64, max_rows: usize) -> io::Result<Preview> {
+    let extension = extension(file_name);
+    if extension == "xlsx" {
+        if std::fs::metadata(path)?.len() > max_size {
+            return Err(io::Error::other(format!(
+                "file is larger than {max_size} bytes"
+            )));
+        }
+        return render_xlsx(path, max_rows);
+    }
+
+    let (bytes, truncated) = read_limited(path, max_size)?;
+    let (text, charset) = decode(&bytes);
+    let preview = match extension.as_str() {
+        "csv" | "tsv" => {
+            let delimiter = if extension == "tsv" { b'\t' } else { b',' };
+            render_csv(&text, delimiter, truncated, max_rows)?
+        }
+        _ => {
+            let html = if MARKDOWN_EXTENSIONS.contains(&extension.as_str()) {
+                render_markdown(&text)
+            } else if CODE_EXTENSIONS.contains(&extension.as_str()) {
+                render_code(&text, &extension)?
+            } else {
+                format!("<pre>{}</pre>", escape_html(&text))
+            };
+            Preview::Html {
+                html,
+                charset: Some(charset),
+                truncated,
+            }
+        }
+    };
+    Ok(preview)
+}
+
+fn read_limited(path: &Path, max_size: u64) -> io::Result<(Vec<u8>, bool)> {
+    let file = File::open(path)?;
+    let mut bytes = Vec::new();
+    file.take(max_size + 1).read_to_end(&mut bytes)?;
+    let truncated = bytes.len() as u64 > max_size;
+    bytes.truncate(max_size as usize);
+    Ok((bytes, truncated))
+}
+
+/// Decode text using its byte order mark, or the encoding guessed from its content.
+fn decode(bytes: &[u8]) -> (String, String) {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return (text.into_owned(), encoding.name().to_string());
+    }
+    let mut detector = EncodingDetector::new(Iso2022JpDetection::Deny);
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, Utf8Detection::Allow);
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    (text.into_owned(), encoding.name().to_string())
+}
+
+fn render_markdown(text: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(text, options));
+    // raw HTML in markdown may carry scripts, event handlers or javascript: links
+    ammonia::clean(&unsafe_html)
+}
+
+fn render_code(text: &str, extension: &str) -> io::Result<String> {
+    let syntax = SYNTAX_SET
+        .find_syntax_by_extension(extension)
+        .unwrap_or_else(|| SYNTAX_SET.find_syntax_plain_text());
+    let theme = &THEME_SET.themes[CODE_THEME];
+    // the highlighter escapes the source, the output holds only styled spans
+    syntect::html::highlighted_html_for_string(text, &SYNTAX_SET, syntax, theme)
+        .map_err(io::Error::other)
+}
+
+fn render_csv(
+    text: &str,
+    delimiter: u8,
+    source_truncated: bool,
+    max_rows: usize,
+) -> io::Result<Preview> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(text.as_bytes());
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut truncated = source_truncated;
+    for record in reader.records() {
+        let record = record.map_err(io::Error::other)?;
+        if rows.len() > max_rows {
+            truncated = true;
+            break;
+        }
+        rows.push(record.iter().map(|value| value.to_string()).collect());
+    }
+    // the last row of a cut source is likely incomplete
+    if source_truncated && !rows.is_empty() {
+        rows.pop();
+    }
+
+    let columns = if rows.is_empty() {
+        Vec::new()
+    } else {
+        rows.remove(0)
+    };
+    Ok(Preview::Table {
+        sheets: vec![PreviewSheet {
+            name: String::new(),
+            columns,
+            rows,
+        }],
+        truncated,
+    })
+}
+
+fn render_xlsx(path: &Path, max_rows: usize) -> io::Result<Preview> {
+    let mut workbook: Xlsx<_> = open_workbook(path).map_err(io::Error::other)?;
+    let mut sheets = Vec::new();
+    let mut remaining_rows = max_rows;
+    let mut truncated = false;
+    for name in workbook.sheet_names() {
+        let range = workbook.worksheet_range(&name).map_err(io::Error::other)?;
+        let mut rows: Vec<Vec<String>> = range
+            .rows()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .take(remaining_rows + 1)
+            .collect();
+        let columns = if rows.is_empty() {
+            Vec::new()
+        } else {
+            rows.remove(0)
+        };
+        if range.height() > rows.len() + 1 {
+            truncated = true;
+        }
+        remaining_rows -= rows.len();
+        sheets.push(PreviewSheet {
+            name,
+            columns,
+            rows,
+        });
+    }
+    Ok(Preview::Table { sheets, truncated })
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,271 @@
+use std::io::{self, Write};
+
+use axum::body::Bytes;
+use quick_xml::escape::escape;
+use serde_json::Value as JsonValue;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use zip::{
+    write::{SimpleFileOptions, StreamWriter},
+    CompressionMethod, ZipWriter,
+};
+
+use crate::{dto::enumerator::export_format::ExportFormat, util::archive::ChannelWriter};
+
+// rows of a worksheet, the header included
+const MAX_XLSX_ROWS: u32 = 1_048_576;
+// characters of a worksheet cell
+const MAX_XLSX_TEXT: usize = 32_767;
+
+const XLSX_CONTENT_TYPES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/></Types>"#;
+const XLSX_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+const XLSX_WORKBOOK: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets><sheet name="export" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+const XLSX_WORKBOOK_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+const XLSX_SHEET_START: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#;
+const XLSX_SHEET_END: &str = "</sheetData></worksheet>";
+
+pub enum ExportValue {
+    Null,
+    Text(String),
+    Integer(i64),
+    Boolean(bool),
+    Json(JsonValue),
+}
+
+pub fn content_type(format: &ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::CSV => "text/csv; charset=utf-8",
+        ExportFormat::NDJSON => "application/x-ndjson",
+        ExportFormat::XLSX => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    }
+}
+
+/// Write the rows returned by `next_rows` on a blocking thread until it returns `None`, and
+/// return the export as a byte stream. Only one batch of rows is held at a time. Failures after
+/// the first bytes are sent end the stream with an error, the client gets a truncated file.
+pub fn export_stream<F>(
+    format: ExportFormat,
+    columns: Vec<String>,
+    mut next_rows: F,
+) -> ReceiverStream<io::Result<Bytes>>
+where
+    F: FnMut() -> Result<Option<Vec<Vec<ExportValue>>>, String> + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<io::Result<Bytes>>(16);
+
+    tokio::task::spawn_blocking(move || {
+        let error_sender = sender.clone();
+        let result = (|| -> io::Result<()> {
+            let mut writer = RowWriter::new(format, columns, ChannelWriter::new(sender))?;
+            while let Some(rows) = next_rows().map_err(io::Error::other)? {
+                for row in rows.iter() {
+                    writer.write_row(row)?;
+                }
+            }
+            writer.finish()
+        })();
+        if let Err(error) = result {
+            log::error!("write export failed: {}", error);
+            let _ = error_sender.blocking_send(Err(error));
+        }
+    });
+
+    ReceiverStream::new(receiver)
+}
+
+enum RowWriter<W: Write> {
+    Csv(csv::Writer<W>),
+    Ndjson {
+        writer: W,
+        columns: Vec<String>,
+    },
+    Xlsx {
+        zip_writer: ZipWriter<StreamWriter<W>>,
+        row_number: u32,
+    },
+}
+
+impl<W: Write> RowWriter<W> {
+    // the header row is written right away
+    fn new(format: ExportFormat, columns: Vec<String>, writer: W) -> io::Result<RowWriter<W>> {
+        match format {
+            ExportFormat::CSV => {
+                let mut csv_writer = csv::Writer::from_writer(writer);
+                csv_writer.write_record(&columns)?;
+                Ok(RowWriter::Csv(csv_writer))
+            }
+            ExportFormat::NDJSON => Ok(RowWriter::Ndjson { writer, columns }),
+            ExportFormat::XLSX => {
+                let mut zip_writer = ZipWriter::new_stream(writer);
+                let options =
+                    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+                for (name, contents) in [
+                    ("[Content_Types].xml", XLSX_CONTENT_TYPES),
+                    ("_rels/.rels", XLSX_RELS),
+                    ("xl/workbook.xml", XLSX_WORKBOOK),
+                    ("xl/_rels/workbook.xml.rels", XLSX_WORKBOOK_RELS),
+                ] {
+                    zip_writer.start_file(name, options)?;
+                    zip_writer.write_all(contents.as_bytes())?;
+                }
+                // the worksheet is the last entry, rows are appended to it until finish
+                zip_writer.start_file("xl/worksheets/sheet1.xml", options.large_file(true))?;
+                zip_writer.write_all(XLSX_SHEET_START.as_bytes())?;
+                let mut row_writer = RowWriter::Xlsx {
+                    zip_writer,
+                    row_number: 0,
+                };
+                let header: Vec<ExportValue> = columns.into_iter().map(ExportValue::Text).collect();
+                row_writer.write_row(&header)?;
+                Ok(row_writer)
+            }
+        }
+    }
+
+    fn write_row(&mut self, row: &[ExportValue]) -> io::Result<()> {
+        match self {
+            RowWriter::Csv(csv_writer) => {
+                let record: Vec<String> = row.iter().map(csv_field).collect();
+                csv_writer.write_record(&record)?;
+            }
+            RowWriter::Ndjson { writer, columns } => {
+                // keys in the order of the selected columns
+                writer.write_all(b"{")?;
+                for (index, (column, value)) in columns.iter().zip(row.iter()).enumerate() {
+                    if index > 0 {
+                        writer.write_all(b",")?;
+                    }
+                    serde_json::to_writer(&mut *writer, column)?;
+                    writer.write_all(b":")?;
+                    serde_json::to_writer(&mut *writer, &json_value(value))?;
+                }
+                writer.write_all(b"}\n")?;
+            }
+            RowWriter::Xlsx {
+                zip_writer,
+                row_number,
+            } => {
+                if *row_number >= MAX_XLSX_ROWS {
+                    return Err(io::Error::other(format!(
+                        "xlsx holds at most {} rows",
+                        MAX_XLSX_ROWS - 1
+                    )));
+                }
+                *row_number += 1;
+                let mut xml = format!(r#"<row r="{}">"#, row_number);
+                for (index, value) in row.iter().enumerate() {
+                    // empty cells are left out, cells carry their reference so the others stay
+                    // in their column
+                    let reference = format!("{}{}", column_name(index), row_number);
+                    match value {
+                        ExportValue::Null => continue,
+                        ExportValue::Integer(value) => {
+                            xml.push_str(&format!(r#"<c r="{}"><v>{}</v></c>"#, reference, value))
+                        }
+                        ExportValue::Boolean(value) => xml.push_str(&format!(
+                            r#"<c r="{}" t="b"><v>{}</v></c>"#,
+                            reference,
+                            *value as u8
+                        )),
+                        ExportValue::Text(_) | ExportValue::Json(_) => xml.push_str(&format!(
+                            r#"<c r="{}" t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                            reference,
+                            escape(xlsx_text(&text_value(value)))
+                        )),
+                    }
+                }
+                xml.push_str("</row>");
+                zip_writer.write_all(xml.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> io::Result<()> {
+        let mut writer = match self {
+            RowWriter::Csv(csv_writer) => csv_writer
+                .into_inner()
+                .map_err(|error| io::Error::other(error.to_string()))?,
+            RowWriter::Ndjson { writer, .. } => writer,
+            RowWriter::Xlsx { mut zip_writer, .. } => {
+                zip_writer.write_all(XLSX_SHEET_END.as_bytes())?;
+                zip_writer.finish()?.into_inner()
+            }
+        };
+        writer.flush()
+    }
+}
+
+fn text_value(value: &ExportValue) -> String {
+    match value {
+        ExportValue::Null => String::new(),
+        ExportValue::Text(value) => value.clone(),
+        ExportValue::Integer(value) => value.to_string(),
+        ExportValue::Boolean(value) => value.to_string(),
+        ExportValue::Json(value) => value.to_string(),
+    }
+}
+
+fn json_value(value: &ExportValue) -> JsonValue {
+    match value {
+        ExportValue::Null => JsonValue::Null,
+        ExportValue::Text(value) => JsonValue::from(value.as_str()),
+        ExportValue::Integer(value) => JsonValue::from(*value),
+        ExportValue::Boolean(value) => JsonValue::from(*value),
+        ExportValue::Json(value) => value.clone(),
+    }
+}
+
+// text starting like a formula is quoted so spreadsheets opening the CSV do not evaluate it
+fn csv_field(value: &ExportValue) -> String {
+    let text = text_value(value);
+    if let ExportValue::Text(_) = value
+        && text.starts_with(['=', '+', '-', '@', '\t', '\r'])
+    {
+        return format!("'{}", text);
+    }
+    text
+}
+
+// without the control characters XML 1.0 forbids, cut to the cell limit
+fn xlsx_text(value: &str) -> String {
+    value
+        .chars()
+        .filter(|character| !character.is_control() || ['\t', '\n', '\r'].contains(character))
+        .take(MAX_XLSX_TEXT)
+        .collect()
+}
+
+// A, B, ..., Z, AA, AB, ...
+fn column_name(index: usize) -> String {
+    let mut name = Vec::new();
+    let mut number = index + 1;
+    while number > 0 {
+        number -= 1;
+        name.push(b'A' + (number % 26) as u8);
+        number /= 26;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_name_counts_in_letters() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(51), "AZ");
+        assert_eq!(column_name(52), "BA");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+
+/// Directory holding derived files (thumbnails, previews, ...) of a stored file.
+///
+/// It sits next to the stored file, so replacing the file under a new name never serves stale
+/// cache entries.
+pub fn cache_dir(file_path: &str) -> PathBuf {
+    PathBuf::from(format!("{}.cache", file_path))
+}
+
+pub fn cache_path(file_path: &str, key: &str) -> PathBuf {
+    cache_dir(file_path).join(key)
+}
+
+pub async fn read(file_path: &str, key: &str) -> Option<Vec<u8>> {
+    tokio::fs::read(cache_path(file_path, key)).await.ok()
+}
+
+pub async fn write(file_path: &str, key: &str, contents: &[u8]) {
+    let dir_path = cache_dir(file_path);
+    if let Err(error) = tokio::fs::create_dir_all(&dir_path).await {
+        log::error!("create cache dir failed: {}", error);
+        return;
+    }
+
+    // write to a temporary name first, concurrent readers never see a partial file
+    let tmp_path = dir_path.join(format!("{}.{}.tmp", key, uuid::Uuid::new_v4()));
+    if let Err(error) = tokio::fs::write(&tmp_path, contents).await {
+        log::error!("write cache failed: {}", error);
+        return;
+    }
+    if let Err(error) = tokio::fs::rename(&tmp_path, cache_path(file_path, key)).await {
+        log::error!("write cache failed: {}", error);
+        let _ = tokio::fs::remove_file(tmp_path).await;
+    }
+}
+
+/// Remove the oldest entries whose key starts with `prefix` until there is room for one more
+/// within `max_entries`. Keeps derived files requested with arbitrary parameters bounded.
+pub async fn evict(file_path: &str, prefix: &str, max_entries: usize) {
+    let Ok(mut dir_entries) = tokio::fs::read_dir(cache_dir(file_path)).await else {
+        return;
+    };
+    let mut entries: Vec<(std::time::SystemTime, PathBuf)> = Vec::new();
+    while let Ok(Some(dir_entry)) = dir_entries.next_entry().await {
+        let name = dir_entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(prefix) || name.ends_with(".tmp") {
+            continue;
+        }
+        if let Ok(modified) = dir_entry.metadata().await.and_then(|value| value.modified()) {
+            entries.push((modified, dir_entry.path()));
+        }
+    }
+    if entries.len() < max_entries {
+        return;
+    }
+
+    entries.sort();
+    for (_, path) in entries.iter().take(entries.len() + 1 - max_entries) {
+        if let Err(error) = tokio::fs::remove_file(path).await {
+            log::error!("evict cache failed: {}", error);
+        }
+    }
+}
+
+pub async fn remove(file_path: &str) {
+    let dir_path = cache_dir(file_path);
+    if !tokio::fs::try_exists(&dir_path).await.unwrap_or(false) {
+        return;
+    }
+    if let Err(error) = tokio::fs::remove_dir_all(dir_path).await {
+        log::error!("remove cache failed: {}", error);
+    }
+}
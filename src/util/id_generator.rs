@@ -0,0 +1,34 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use diesel::result::{DatabaseErrorKind, Error};
+
+// inserts retried with a fresh id before giving up
+const MAX_ATTEMPTS: usize = 5;
+
+static LAST_ID: AtomicI64 = AtomicI64::new(0);
+
+/// Next id of a new row, the current time in milliseconds or one above the last id handed out
+/// by this process, whichever is larger. Ids never repeat within the process, rows inserted by
+/// other processes or with client supplied ids may still take one, see `with_new_id`.
+pub fn next_id() -> i64 {
+    let now = chrono::Utc::now().timestamp_millis();
+    let last = LAST_ID
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| Some(now.max(last + 1)))
+        .unwrap_or_else(|last| last);
+    now.max(last + 1)
+}
+
+/// Run `insert` with a new id, and again with a fresh one while the id is already taken.
+pub fn with_new_id<T>(mut insert: impl FnMut(i64) -> Result<T, Error>) -> Result<T, Error> {
+    let mut attempt = 1;
+    loop {
+        match insert(next_id()) {
+            Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _))
+                if attempt < MAX_ATTEMPTS =>
+            {
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
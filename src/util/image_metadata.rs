@@ -0,0 +1,307 @@
+use std::io::Cursor;
+
+use axum::body::Bytes;
+use chrono::NaiveDateTime;
+use exif::{Context, Exif, In, Tag, Value};
+use image::{metadata::Orientation, DynamicImage, ImageFormat, ImageReader, ImageResult};
+use img_parts::{
+    jpeg::{markers, Jpeg, JpegSegment},
+    png::Png,
+    webp::{WebP, CHUNK_EXIF, CHUNK_XMP},
+    ImageEXIF,
+};
+use regex::Regex;
+use serde_json::{json, Value as JsonValue};
+
+use crate::util::image_processing;
+
+const XMP_JPEG_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const XMP_PNG_KEYWORD: &[u8] = b"XML:com.adobe.xmp\0";
+const EXIF_JPEG_HEADER: &[u8] = b"Exif\0\0";
+
+pub struct ProcessedImage {
+    // rewritten image, `None` when the upload is stored as is
+    pub bytes: Option<Vec<u8>>,
+    pub metadata: JsonValue,
+}
+
+/// Read EXIF/XMP of an uploaded image and prepare the bytes to store.
+///
+/// JPEG files with an EXIF orientation are rotated upright. With `strip` every EXIF and XMP
+/// block is dropped from the stored file, which removes GPS position, serial numbers and
+/// owner names; the useful fields are kept in the returned metadata instead. The metadata also
+/// carries a BlurHash and the dominant colour for placeholders. Images are only decoded within
+/// the limits of `image_processing::load` for `max_dimension`, larger ones are stored without
+/// rotation and placeholders.
+pub fn process(bytes: &[u8], strip: bool, max_dimension: u32) -> ImageResult<ProcessedImage> {
+    let reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let format = reader.format();
+    let (mut width, mut height) = reader.into_dimensions()?;
+
+    let exif_raw = read_exif(bytes, format);
+    let exif = exif_raw
+        .as_ref()
+        .and_then(|value| exif::Reader::new().read_raw(value.to_vec()).ok());
+    let xmp = read_xmp(bytes, format);
+
+    let orientation = exif
+        .as_ref()
+        .and_then(|value| value.get_field(Tag::Orientation, In::PRIMARY))
+        .and_then(|field| field.value.get_uint(0))
+        .unwrap_or(1);
+    if (5..=8).contains(&orientation) {
+        std::mem::swap(&mut width, &mut height);
+    }
+
+    let mut captured_on = exif.as_ref().and_then(|value| {
+        exif_date(value, Tag::DateTimeOriginal).or_else(|| exif_date(value, Tag::DateTime))
+    });
+    if captured_on.is_none() {
+        captured_on = xmp.as_ref().and_then(|value| xmp_date(value));
+    }
+    let has_location = exif
+        .as_ref()
+        .map(|value| value.fields().any(|field| field.tag.context() == Context::Gps))
+        .unwrap_or(false)
+        || xmp
+            .as_ref()
+            .map(|value| value.contains("GPSLatitude"))
+            .unwrap_or(false);
+
+    let mut metadata = json!({
+        "width": width,
+        "height": height,
+        "orientation": orientation,
+        "camera_make": exif.as_ref().and_then(|value| exif_text(value, Tag::Make)),
+        "camera_model": exif.as_ref().and_then(|value| exif_text(value, Tag::Model)),
+        "captured_on": captured_on.map(|value| value.format("%Y-%m-%d %H:%M:%S").to_string()),
+        "has_location": has_location && !strip,
+        "stripped": strip,
+    });
+
+    let auto_orient = format == Some(ImageFormat::Jpeg) && orientation != 1;
+    let mut decoded: Option<DynamicImage> = None;
+    let mut stored_bytes: Option<Vec<u8>> = None;
+    let oriented = if auto_orient {
+        image_processing::load(bytes, max_dimension).ok()
+    } else {
+        None
+    };
+    if let Some(mut image) = oriented {
+        if let Some(value) = Orientation::from_exif(orientation as u8) {
+            image.apply_orientation(value);
+        }
+        let encoded = image_processing::encode_jpeg(&image, 90)?.bytes;
+        stored_bytes = Some(if strip {
+            encoded
+        } else {
+            restore_jpeg_metadata(encoded, bytes, exif_raw)
+        });
+        decoded = Some(image);
+    } else if strip {
+        stored_bytes = Some(strip_metadata(bytes, format));
+    }
+
+    // placeholders are computed from the upright image, a decode failure only skips them
+    let decoded = decoded.or_else(|| image_processing::load(bytes, max_dimension).ok());
+    if let Some(image) = decoded {
+        metadata["blurhash"] = json!(image_processing::blurhash(&image));
+        metadata["dominant_color"] = json!(image_processing::dominant_color(&image));
+    }
+
+    Ok(ProcessedImage {
+        bytes: stored_bytes,
+        metadata,
+    })
+}
+
+fn read_exif(bytes: &[u8], format: Option<ImageFormat>) -> Option<Bytes> {
+    let bytes = Bytes::copy_from_slice(bytes);
+    match format {
+        Some(ImageFormat::Jpeg) => Jpeg::from_bytes(bytes).ok()?.exif(),
+        Some(ImageFormat::Png) => Png::from_bytes(bytes).ok()?.exif(),
+        Some(ImageFormat::WebP) => WebP::from_bytes(bytes).ok()?.exif(),
+        _ => None,
+    }
+}
+
+fn read_xmp(bytes: &[u8], format: Option<ImageFormat>) -> Option<String> {
+    let bytes = Bytes::copy_from_slice(bytes);
+    let xmp: Bytes = match format {
+        Some(ImageFormat::Jpeg) => Jpeg::from_bytes(bytes)
+            .ok()?
+            .segments()
+            .iter()
+            .find(|segment| is_jpeg_xmp(segment))?
+            .contents()
+            .slice(XMP_JPEG_HEADER.len()..),
+        Some(ImageFormat::Png) => Png::from_bytes(bytes)
+            .ok()?
+            .chunks()
+            .iter()
+            .find(|chunk| chunk.kind() == *b"iTXt" && chunk.contents().starts_with(XMP_PNG_KEYWORD))?
+            .contents()
+            .clone(),
+        Some(ImageFormat::WebP) => WebP::from_bytes(bytes)
+            .ok()?
+            .chunk_by_id(CHUNK_XMP)?
+            .content()
+            .data()?
+            .clone(),
+        _ => return None,
+    };
+    Some(String::from_utf8_lossy(&xmp).to_string())
+}
+
+fn is_jpeg_xmp(segment: &JpegSegment) -> bool {
+    segment.marker() == markers::APP1 && segment.contents().starts_with(XMP_JPEG_HEADER)
+}
+
+fn strip_metadata(bytes: &[u8], format: Option<ImageFormat>) -> Vec<u8> {
+    let bytes = Bytes::copy_from_slice(bytes);
+    match format {
+        Some(ImageFormat::Jpeg) => match Jpeg::from_bytes(bytes.clone()) {
+            Ok(mut jpeg) => {
+                jpeg.set_exif(None);
+                jpeg.segments_mut().retain(|segment| !is_jpeg_xmp(segment));
+                jpeg.encoder().bytes().to_vec()
+            }
+            Err(_) => bytes.to_vec(),
+        },
+        Some(ImageFormat::Png) => match Png::from_bytes(bytes.clone()) {
+            Ok(mut png) => {
+                png.set_exif(None);
+                png.chunks_mut().retain(|chunk| {
+                    !(chunk.kind() == *b"iTXt" && chunk.contents().starts_with(XMP_PNG_KEYWORD))
+                });
+                png.encoder().bytes().to_vec()
+            }
+            Err(_) => bytes.to_vec(),
+        },
+        Some(ImageFormat::WebP) => match WebP::from_bytes(bytes.clone()) {
+            Ok(mut webp) => {
+                webp.remove_chunks_by_id(CHUNK_EXIF);
+                webp.remove_chunks_by_id(CHUNK_XMP);
+                webp.encoder().bytes().to_vec()
+            }
+            Err(_) => bytes.to_vec(),
+        },
+        _ => bytes.to_vec(),
+    }
+}
+
+// put the original EXIF (now with orientation 1) and XMP back into a re-encoded JPEG
+fn restore_jpeg_metadata(encoded: Vec<u8>, original: &[u8], exif_raw: Option<Bytes>) -> Vec<u8> {
+    let Ok(mut jpeg) = Jpeg::from_bytes(Bytes::from(encoded.clone())) else {
+        return encoded;
+    };
+    let xmp_segments: Vec<JpegSegment> = match Jpeg::from_bytes(Bytes::copy_from_slice(original)) {
+        Ok(value) => value
+            .segments()
+            .iter()
+            .filter(|segment| is_jpeg_xmp(segment))
+            .cloned()
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    // keep APP0 (JFIF) first
+    let mut index = match jpeg.segments().first() {
+        Some(segment) if segment.marker() == markers::APP0 => 1,
+        _ => 0,
+    };
+    if let Some(value) = exif_raw {
+        let mut contents = EXIF_JPEG_HEADER.to_vec();
+        contents.extend_from_slice(&reset_orientation(&value));
+        jpeg.segments_mut().insert(
+            index,
+            JpegSegment::new_with_contents(markers::APP1, Bytes::from(contents)),
+        );
+        index += 1;
+    }
+    for segment in xmp_segments {
+        jpeg.segments_mut().insert(index, segment);
+        index += 1;
+    }
+    jpeg.encoder().bytes().to_vec()
+}
+
+/// Set the orientation tag of the first IFD in a raw TIFF/EXIF block to 1 (upright).
+fn reset_orientation(tiff: &[u8]) -> Vec<u8> {
+    let mut tiff = tiff.to_vec();
+    if tiff.len() < 8 {
+        return tiff;
+    }
+    let little_endian = &tiff[0..2] == b"II";
+    let read_u16 = |data: &[u8], offset: usize| -> u16 {
+        let value = [data[offset], data[offset + 1]];
+        if little_endian {
+            u16::from_le_bytes(value)
+        } else {
+            u16::from_be_bytes(value)
+        }
+    };
+    let ifd_bytes = [tiff[4], tiff[5], tiff[6], tiff[7]];
+    let ifd_offset = if little_endian {
+        u32::from_le_bytes(ifd_bytes)
+    } else {
+        u32::from_be_bytes(ifd_bytes)
+    } as usize;
+    if ifd_offset + 2 > tiff.len() {
+        return tiff;
+    }
+
+    let entry_count = read_u16(&tiff, ifd_offset) as usize;
+    for index in 0..entry_count {
+        let entry_offset = ifd_offset + 2 + index * 12;
+        if entry_offset + 12 > tiff.len() {
+            break;
+        }
+        // orientation is a single SHORT stored inline in the value field
+        if read_u16(&tiff, entry_offset) == 0x0112 {
+            let value = if little_endian {
+                1u16.to_le_bytes()
+            } else {
+                1u16.to_be_bytes()
+            };
+            tiff[entry_offset + 8] = value[0];
+            tiff[entry_offset + 9] = value[1];
+            break;
+        }
+    }
+    tiff
+}
+
+fn exif_text(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => {
+            let text = String::from_utf8_lossy(values.first()?).trim().to_string();
+            if text.is_empty() {
+                return None;
+            }
+            Some(text)
+        }
+        _ => None,
+    }
+}
+
+fn exif_date(exif: &Exif, tag: Tag) -> Option<NaiveDateTime> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => {
+            let text = String::from_utf8_lossy(values.first()?).to_string();
+            NaiveDateTime::parse_from_str(text.trim(), "%Y:%m:%d %H:%M:%S").ok()
+        }
+        _ => None,
+    }
+}
+
+fn xmp_date(xmp: &str) -> Option<NaiveDateTime> {
+    let re = Regex::new(
+        r#"(?:xmp:CreateDate|photoshop:DateCreated|exif:DateTimeOriginal)(?:="|>)(\d{4}-\d{2}-\d{2}T\d{2}:\d{2}(?::\d{2})?)"#,
+    )
+    .unwrap();
+    let value = re.captures(xmp)?.get(1)?.as_str().to_string();
+    NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(&value, "%Y-%m-%dT%H:%M"))
+        .ok()
+}
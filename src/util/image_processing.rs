@@ -0,0 +1,248 @@
+use std::{collections::HashMap, io::Cursor, sync::Arc};
+
+use image::{
+    codecs::jpeg::JpegEncoder,
+    error::{LimitError, LimitErrorKind},
+    imageops::FilterType,
+    DynamicImage, ImageError, ImageFormat, ImageReader, ImageResult, Limits,
+};
+
+use crate::{
+    dto::enumerator::{image_fit::ImageFit, image_format::ImageFormat as OutputFormat},
+    util::watermark::Watermark,
+};
+
+pub struct Transform {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: ImageFit,
+    pub crop: Option<(u32, u32, u32, u32)>,
+    pub rotate: u16,
+    pub format: Option<OutputFormat>,
+    pub quality: u8,
+    // drawn after resizing
+    pub watermark: Option<Arc<Watermark>>,
+}
+
+impl Transform {
+    /// Stable name of the transformation, used as cache key.
+    pub fn cache_key(&self) -> String {
+        let crop = match self.crop {
+            Some((x, y, width, height)) => format!("{}-{}-{}-{}", x, y, width, height),
+            None => "none".to_string(),
+        };
+        let format = match &self.format {
+            Some(value) => value.to_string(),
+            None => "auto".to_string(),
+        };
+        let watermark = match &self.watermark {
+            Some(value) => format!("_wm{}", value.fingerprint),
+            None => String::new(),
+        };
+        format!(
+            "transform_w{}_h{}_{:?}_c{}_r{}_q{}{}.{}",
+            self.width.unwrap_or(0),
+            self.height.unwrap_or(0),
+            self.fit,
+            crop,
+            self.rotate,
+            self.quality,
+            watermark,
+            format
+        )
+        .to_lowercase()
+    }
+}
+
+pub struct EncodedImage {
+    pub bytes: Vec<u8>,
+    pub content_type: &'static str,
+}
+
+/// Decode an image, refusing sources larger than `max_dimension` times 4 on either side.
+pub fn load(bytes: &[u8], max_dimension: u32) -> ImageResult<DynamicImage> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(max_dimension.saturating_mul(4));
+    limits.max_image_height = Some(max_dimension.saturating_mul(4));
+
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    reader.limits(limits);
+    reader.decode()
+}
+
+/// Apply crop, rotation, resize, watermark and format conversion, in that order.
+///
+/// The output never exceeds `max_dimension` on either side. Quality only applies to JPEG,
+/// PNG and WebP are encoded lossless.
+pub fn transform(bytes: &[u8], transform: &Transform, max_dimension: u32) -> ImageResult<EncodedImage> {
+    let mut image = load(bytes, max_dimension)?;
+
+    if let Some((x, y, width, height)) = transform.crop {
+        if x >= image.width() || y >= image.height() {
+            return Err(ImageError::Limits(LimitError::from_kind(
+                LimitErrorKind::DimensionError,
+            )));
+        }
+        let width = width.min(image.width() - x);
+        let height = height.min(image.height() - y);
+        image = image.crop_imm(x, y, width, height);
+    }
+
+    image = match transform.rotate {
+        90 => image.rotate90(),
+        180 => image.rotate180(),
+        270 => image.rotate270(),
+        _ => image,
+    };
+
+    let (width, height) = target_size(&image, transform.width, transform.height, max_dimension);
+    if width != image.width() || height != image.height() {
+        image = match transform.fit {
+            ImageFit::CONTAIN => image.resize(width, height, FilterType::Lanczos3),
+            ImageFit::COVER => image.resize_to_fill(width, height, FilterType::Lanczos3),
+            ImageFit::FILL => image.resize_exact(width, height, FilterType::Lanczos3),
+        };
+    }
+
+    if let Some(watermark) = &transform.watermark {
+        image = watermark.apply(&image);
+    }
+
+    match transform.format {
+        Some(OutputFormat::JPEG) => encode_jpeg(&image, transform.quality),
+        Some(OutputFormat::PNG) => encode(&image, ImageFormat::Png),
+        Some(OutputFormat::WEBP) => {
+            if image.color().has_alpha() {
+                return encode(&DynamicImage::ImageRgba8(image.to_rgba8()), ImageFormat::WebP);
+            }
+            encode(&DynamicImage::ImageRgb8(image.to_rgb8()), ImageFormat::WebP)
+        }
+        None => {
+            if image.color().has_alpha() {
+                return encode(&image, ImageFormat::Png);
+            }
+            encode_jpeg(&image, transform.quality)
+        }
+    }
+}
+
+/// Draw the watermark on an image and encode it in its own format, GIF animations keep only
+/// their first frame.
+pub fn watermark(bytes: &[u8], watermark: &Watermark, max_dimension: u32) -> ImageResult<EncodedImage> {
+    let image = watermark.apply(&load(bytes, max_dimension)?);
+    match image::guess_format(bytes) {
+        Ok(ImageFormat::Jpeg) => encode_jpeg(&image, 90),
+        Ok(format @ (ImageFormat::Png | ImageFormat::WebP | ImageFormat::Gif)) => {
+            encode(&image, format)
+        }
+        _ => encode_preview(&image),
+    }
+}
+
+// a missing side follows the aspect ratio, both sides are capped at max_dimension
+fn target_size(
+    image: &DynamicImage,
+    width: Option<u32>,
+    height: Option<u32>,
+    max_dimension: u32,
+) -> (u32, u32) {
+    let source_width = image.width().max(1) as u64;
+    let source_height = image.height().max(1) as u64;
+    let (width, height) = match (width, height) {
+        (Some(width), Some(height)) => (width as u64, height as u64),
+        (Some(width), None) => (width as u64, (width as u64 * source_height / source_width).max(1)),
+        (None, Some(height)) => ((height as u64 * source_width / source_height).max(1), height as u64),
+        (None, None) => (source_width, source_height),
+    };
+
+    let max_dimension = max_dimension as u64;
+    if width <= max_dimension && height <= max_dimension {
+        return (width as u32, height as u32);
+    }
+    let scale_width = width * max_dimension / width.max(height);
+    let scale_height = height * max_dimension / width.max(height);
+    (scale_width.max(1) as u32, scale_height.max(1) as u32)
+}
+
+/// Scale the image down to fit in a `size` x `size` box, keeping its aspect ratio.
+pub fn thumbnail(bytes: &[u8], size: u32, max_dimension: u32) -> ImageResult<EncodedImage> {
+    let source = load(bytes, max_dimension)?;
+    let thumbnail = if source.width() <= size && source.height() <= size {
+        source
+    } else {
+        source.thumbnail(size, size)
+    };
+    encode_preview(&thumbnail)
+}
+
+/// Encode as PNG when the image has transparency, as JPEG otherwise.
+pub fn encode_preview(image: &DynamicImage) -> ImageResult<EncodedImage> {
+    if image.color().has_alpha() {
+        return encode(image, ImageFormat::Png);
+    }
+    encode(&DynamicImage::ImageRgb8(image.to_rgb8()), ImageFormat::Jpeg)
+}
+
+pub fn encode_jpeg(image: &DynamicImage, quality: u8) -> ImageResult<EncodedImage> {
+    let mut bytes: Vec<u8> = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, quality).encode_image(&image.to_rgb8())?;
+    Ok(EncodedImage {
+        bytes,
+        content_type: ImageFormat::Jpeg.to_mime_type(),
+    })
+}
+
+pub fn encode(image: &DynamicImage, format: ImageFormat) -> ImageResult<EncodedImage> {
+    let mut bytes: Vec<u8> = Vec::new();
+    image.write_to(&mut Cursor::new(&mut bytes), format)?;
+    Ok(EncodedImage {
+        bytes,
+        content_type: format.to_mime_type(),
+    })
+}
+
+/// BlurHash placeholder of the image, 4 x 3 components (3 x 4 for portrait images).
+pub fn blurhash(image: &DynamicImage) -> Option<String> {
+    let small = image.thumbnail(32, 32).to_rgba8();
+    let (components_x, components_y) = if small.width() >= small.height() {
+        (4, 3)
+    } else {
+        (3, 4)
+    };
+    blurhash::encode(
+        components_x,
+        components_y,
+        small.width(),
+        small.height(),
+        small.as_raw(),
+    )
+    .ok()
+}
+
+/// Most frequent colour of the image as `#rrggbb`, transparent pixels are ignored.
+///
+/// Pixels are grouped in 4 bit per channel buckets, the colour is the average of the biggest
+/// bucket so near identical shades count as one colour.
+pub fn dominant_color(image: &DynamicImage) -> Option<String> {
+    let small = image.thumbnail(64, 64).to_rgba8();
+    // bucket -> [pixel count, red sum, green sum, blue sum]
+    let mut buckets: HashMap<(u8, u8, u8), [u64; 4]> = HashMap::new();
+    for pixel in small.pixels() {
+        let [red, green, blue, alpha] = pixel.0;
+        if alpha < 128 {
+            continue;
+        }
+        let bucket = buckets.entry((red >> 4, green >> 4, blue >> 4)).or_default();
+        bucket[0] += 1;
+        bucket[1] += red as u64;
+        bucket[2] += green as u64;
+        bucket[3] += blue as u64;
+    }
+    let [count, red, green, blue] = buckets.into_values().max_by_key(|bucket| bucket[0])?;
+    Some(format!(
+        "#{:02x}{:02x}{:02x}",
+        red / count,
+        green / count,
+        blue / count
+    ))
+}
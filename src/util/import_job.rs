@@ -0,0 +1,160 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dto::enumerator::{import_mode::ImportMode, job_status::JobStatus},
+    util::serializer::{date_serializer, option_date_serializer},
+};
+
+// finished jobs kept for their report, the oldest are dropped first
+const MAX_FINISHED_JOBS: usize = 50;
+
+// line number of a manifest row and the row, or why it could not be read
+pub type ManifestLine = (u64, Result<ManifestRow, String>);
+
+/// A line of a manifest, columns are matched by their header name.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ManifestRow {
+    pub source_path: String,
+    // name of the source by default
+    pub file_name: Option<String>,
+    pub module_id: Option<i64>,
+    // owner of the file, the importing user by default
+    pub user_id: Option<i64>,
+    // separated by `;`
+    pub tags: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportRowError {
+    // line of the manifest, the header is line 1
+    pub row: u64,
+    pub source_path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ImportJob {
+    pub id: String,
+    pub dry_run: bool,
+    pub mode: ImportMode,
+    pub status: JobStatus,
+    pub total_rows: u64,
+    pub processed_rows: u64,
+    // rows a dry run would import
+    pub imported_rows: u64,
+    pub failed_rows: u64,
+    // why the whole job failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip)]
+    pub errors: Vec<ImportRowError>,
+    #[serde(with = "date_serializer")]
+    pub started_on: NaiveDateTime,
+    #[serde(with = "option_date_serializer")]
+    pub finished_on: Option<NaiveDateTime>,
+}
+
+impl ImportJob {
+    pub fn new(id: String, dry_run: bool, mode: ImportMode, total_rows: u64) -> ImportJob {
+        ImportJob {
+            id,
+            dry_run,
+            mode,
+            status: JobStatus::RUNNING,
+            total_rows,
+            processed_rows: 0,
+            imported_rows: 0,
+            failed_rows: 0,
+            message: None,
+            errors: Vec::new(),
+            started_on: chrono::Utc::now().naive_utc(),
+            finished_on: None,
+        }
+    }
+
+    pub fn finish(&mut self, message: Option<String>) {
+        self.status = match message {
+            Some(_) => JobStatus::FAILED,
+            None => JobStatus::DONE,
+        };
+        self.message = message;
+        self.finished_on = Some(chrono::Utc::now().naive_utc());
+    }
+}
+
+/// Import jobs of this instance, kept in memory.
+#[derive(Default)]
+pub struct ImportJobs {
+    jobs: Mutex<Vec<Arc<Mutex<ImportJob>>>>,
+}
+
+impl ImportJobs {
+    pub fn add(&self, job: ImportJob) -> Arc<Mutex<ImportJob>> {
+        let job = Arc::new(Mutex::new(job));
+        let mut jobs = self.jobs.lock().unwrap();
+        let is_finished =
+            |job: &Arc<Mutex<ImportJob>>| job.lock().unwrap().status != JobStatus::RUNNING;
+        while jobs.iter().filter(|job| is_finished(job)).count() >= MAX_FINISHED_JOBS {
+            let Some(index) = jobs.iter().position(is_finished) else {
+                break;
+            };
+            jobs.remove(index);
+        }
+        jobs.push(job.clone());
+        job
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<Mutex<ImportJob>>> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|job| job.lock().unwrap().id == id)
+            .cloned()
+    }
+}
+
+/// Read the rows of a CSV manifest with a header line. A row that does not fit the columns is
+/// returned as its error message, with its line number.
+pub fn read_manifest(
+    bytes: &[u8],
+    max_rows: usize,
+) -> Result<Vec<ManifestLine>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(bytes);
+    let headers = reader
+        .headers()
+        .map_err(|error| format!("invalid manifest header: {}", error))?
+        .clone();
+    if !headers.iter().any(|header| header == "source_path") {
+        return Err("manifest has no source_path column".to_string());
+    }
+
+    let mut rows = Vec::new();
+    let mut record = csv::StringRecord::new();
+    loop {
+        let (line, row) = match reader.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => (
+                record.position().map(|position| position.line()),
+                record
+                    .deserialize::<ManifestRow>(Some(&headers))
+                    .map_err(|error| error.to_string()),
+            ),
+            // e.g. a row with more or less fields than the header
+            Err(error) => (
+                error.position().map(|position| position.line()),
+                Err(error.to_string()),
+            ),
+        };
+        if rows.len() >= max_rows {
+            return Err(format!("manifest has more than {} rows", max_rows));
+        }
+        rows.push((line.unwrap_or(0), row));
+    }
+    Ok(rows)
+}
@@ -0,0 +1,162 @@
+use std::fs;
+
+use jsonwebtoken::{
+    decode, encode, errors::ErrorKind, Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use serde::{Deserialize, Serialize};
+
+/// Claims of a bearer token, the subject is the id of the user in m_user.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: i64,
+    #[serde(default)]
+    pub iat: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub aud: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub role_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+pub struct JwtSettings {
+    // HS256 or RS256
+    pub algorithm: String,
+    // HS256 secret
+    pub key: String,
+    // PEM public key, RS256 only
+    pub public_key_file: String,
+    // PEM private key, RS256 only, tokens are not issued without it
+    pub private_key_file: String,
+    // not checked when empty
+    pub issuer: String,
+    pub audience: String,
+    // accepted clock difference with the issuer, in seconds
+    pub leeway: u64,
+    // lifetime of issued tokens, in milliseconds
+    pub expiration: i64,
+}
+
+/// Issues and verifies bearer tokens.
+pub struct Jwt {
+    algorithm: Algorithm,
+    encoding_key: Option<EncodingKey>,
+    decoding_key: DecodingKey,
+    validation: Validation,
+    issuer: Option<String>,
+    audience: Option<String>,
+    expiration: i64,
+}
+
+impl Jwt {
+    pub fn load(settings: &JwtSettings) -> Result<Self, String> {
+        let (algorithm, encoding_key, decoding_key) = match settings.algorithm.as_str() {
+            "" | "HS256" => {
+                if settings.key.is_empty() {
+                    return Err("HS256 needs a key".to_string());
+                }
+                (
+                    Algorithm::HS256,
+                    Some(EncodingKey::from_secret(settings.key.as_bytes())),
+                    DecodingKey::from_secret(settings.key.as_bytes()),
+                )
+            }
+            "RS256" => {
+                let public_key = fs::read(&settings.public_key_file).map_err(|error| {
+                    format!("read {} failed: {error}", settings.public_key_file)
+                })?;
+                let decoding_key = DecodingKey::from_rsa_pem(&public_key).map_err(|error| {
+                    format!("invalid public key {}: {error}", settings.public_key_file)
+                })?;
+                // a service that only verifies tokens has no private key
+                let mut encoding_key = None;
+                if !settings.private_key_file.is_empty() {
+                    let private_key = fs::read(&settings.private_key_file).map_err(|error| {
+                        format!("read {} failed: {error}", settings.private_key_file)
+                    })?;
+                    encoding_key = Some(EncodingKey::from_rsa_pem(&private_key).map_err(|error| {
+                        format!("invalid private key {}: {error}", settings.private_key_file)
+                    })?);
+                }
+                (Algorithm::RS256, encoding_key, decoding_key)
+            }
+            value => return Err(format!("unsupported algorithm {value}")),
+        };
+
+        // tokens signed with another algorithm than the configured one are rejected
+        let mut validation = Validation::new(algorithm);
+        validation.leeway = settings.leeway;
+        validation.validate_nbf = true;
+        let mut required_claims = vec!["exp", "sub"];
+        if !settings.issuer.is_empty() {
+            validation.set_issuer(&[&settings.issuer]);
+            required_claims.push("iss");
+        }
+        if settings.audience.is_empty() {
+            validation.validate_aud = false;
+        } else {
+            validation.set_audience(&[&settings.audience]);
+            required_claims.push("aud");
+        }
+        validation.set_required_spec_claims(&required_claims);
+
+        Ok(Self {
+            algorithm,
+            encoding_key,
+            decoding_key,
+            validation,
+            issuer: Some(settings.issuer.clone()).filter(|value| !value.is_empty()),
+            audience: Some(settings.audience.clone()).filter(|value| !value.is_empty()),
+            expiration: settings.expiration,
+        })
+    }
+
+    /// Sign a token for the user, with the configured issuer, audience and lifetime. Returns the
+    /// token and its lifetime in seconds.
+    pub fn issue(
+        &self,
+        user_id: i64,
+        role_id: Option<i64>,
+        email: Option<String>,
+    ) -> Result<(String, i64), String> {
+        let Some(encoding_key) = &self.encoding_key else {
+            return Err("no private key to sign tokens with".to_string());
+        };
+        let now = chrono::Utc::now().timestamp();
+        let expires_in = (self.expiration / 1000).max(1);
+        let claims = Claims {
+            sub: user_id.to_string(),
+            exp: now + expires_in,
+            iat: now,
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            role_id,
+            email,
+        };
+        let token = encode(&Header::new(self.algorithm), &claims, encoding_key)
+            .map_err(|error| format!("sign token failed: {error}"))?;
+        Ok((token, expires_in))
+    }
+
+    /// Claims of a token with a valid signature, issuer, audience and lifetime. The error is
+    /// safe to show to the client.
+    pub fn verify(&self, token: &str) -> Result<Claims, String> {
+        decode::<Claims>(token, &self.decoding_key, &self.validation)
+            .map(|value| value.claims)
+            .map_err(|error| match error.kind() {
+                ErrorKind::ExpiredSignature => "token expired".to_string(),
+                ErrorKind::ImmatureSignature => "token not valid yet".to_string(),
+                ErrorKind::InvalidIssuer => "invalid token issuer".to_string(),
+                ErrorKind::InvalidAudience => "invalid token audience".to_string(),
+                ErrorKind::MissingRequiredClaim(claim) => format!("token has no {claim}"),
+                _ => "invalid token".to_string(),
+            })
+    }
+}
@@ -0,0 +1,169 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use mp4::{Mp4Reader, TrackType};
+use serde_json::{json, Map, Value as JsonValue};
+use symphonia::core::{
+    codecs::CODEC_TYPE_NULL,
+    formats::FormatOptions,
+    io::{MediaSourceStream, MediaSourceStreamOptions},
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey, Value as TagValue},
+    probe::Hint,
+};
+
+/// Read technical metadata of an audio or video file: duration (in second), bitrate (in bit per
+/// second), codecs, sample rate, channels, resolution and ID3/Vorbis/MP4 tags.
+///
+/// Audio streams and tags are read by symphonia (MP3, AAC, ALAC, FLAC, Vorbis, WAV in MP4, MKV,
+/// OGG and RIFF containers), video streams are only read from MP4 containers. Returns `None`
+/// when the file can not be parsed by either. `file_name` is only used as a format hint, stored
+/// files have no extension.
+pub fn read(path: &Path, file_name: &str) -> Option<JsonValue> {
+    let file_size = std::fs::metadata(path).ok()?.len();
+    let mut metadata = Map::new();
+
+    if let Some(value) = read_mp4(path, file_size) {
+        metadata.extend(value);
+    }
+    if let Some(value) = read_audio(path, file_name) {
+        for (key, value) in value {
+            // the MP4 header is more precise for duration, keep it when both are present
+            metadata.entry(key).or_insert(value);
+        }
+    }
+    if metadata.is_empty() {
+        return None;
+    }
+
+    let duration = metadata
+        .get("duration")
+        .and_then(|value| value.as_f64())
+        .unwrap_or(0.0);
+    if duration > 0.0 && !metadata.contains_key("bitrate") {
+        let bitrate = (file_size as f64 * 8.0 / duration).round() as u64;
+        metadata.insert("bitrate".to_string(), json!(bitrate));
+    }
+    Some(JsonValue::Object(metadata))
+}
+
+fn read_mp4(path: &Path, file_size: u64) -> Option<Map<String, JsonValue>> {
+    let file = File::open(path).ok()?;
+    let mut header = [0u8; 8];
+    std::io::Read::read_exact(&mut (&file), &mut header).ok()?;
+    if &header[4..8] != b"ftyp" {
+        return None;
+    }
+
+    let file = File::open(path).ok()?;
+    let reader = Mp4Reader::read_header(BufReader::new(file), file_size).ok()?;
+    let mut metadata = Map::new();
+    let duration = reader.duration().as_secs_f64();
+    if duration > 0.0 {
+        metadata.insert("duration".to_string(), json!(round_duration(duration)));
+    }
+
+    let mut tracks: Vec<_> = reader.tracks().values().collect();
+    tracks.sort_by_key(|track| track.track_id());
+    let video_track = tracks
+        .into_iter()
+        .find(|track| matches!(track.track_type(), Ok(TrackType::Video)))?;
+    let video_codec = match video_track.media_type() {
+        Ok(value) => value.to_string(),
+        Err(_) => video_track
+            .box_type()
+            .map(|value| value.to_string())
+            .unwrap_or_default(),
+    };
+    metadata.insert("video_codec".to_string(), json!(video_codec));
+    metadata.insert("width".to_string(), json!(video_track.width()));
+    metadata.insert("height".to_string(), json!(video_track.height()));
+    metadata.insert("frame_rate".to_string(), json!(video_track.frame_rate()));
+    Some(metadata)
+}
+
+fn read_audio(path: &Path, file_name: &str) -> Option<Map<String, JsonValue>> {
+    let file = File::open(path).ok()?;
+    let source = MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default());
+    let mut hint = Hint::new();
+    if let Some(value) = Path::new(file_name).extension().and_then(|value| value.to_str()) {
+        hint.with_extension(value);
+    }
+
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    let mut metadata = Map::new();
+
+    let track = probed
+        .format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)?;
+    let params = &track.codec_params;
+    if let (Some(time_base), Some(n_frames)) = (params.time_base, params.n_frames) {
+        let time = time_base.calc_time(n_frames);
+        let duration = time.seconds as f64 + time.frac;
+        metadata.insert("duration".to_string(), json!(round_duration(duration)));
+    }
+    if let Some(value) = symphonia::default::get_codecs().get_codec(params.codec) {
+        metadata.insert("audio_codec".to_string(), json!(value.short_name));
+    }
+    if let Some(value) = params.sample_rate {
+        metadata.insert("sample_rate".to_string(), json!(value));
+    }
+    if let Some(value) = params.channels {
+        metadata.insert("channels".to_string(), json!(value.count()));
+    }
+    if let Some(value) = params.bits_per_sample {
+        metadata.insert("bits_per_sample".to_string(), json!(value));
+    }
+
+    // tags found outside the container (ID3) come first, container tags win on conflict
+    let mut tags = Map::new();
+    if let Some(revision) = probed.metadata.get().as_ref().and_then(|value| value.current().cloned()) {
+        read_tags(&revision, &mut tags);
+    }
+    if let Some(revision) = probed.format.metadata().current() {
+        read_tags(revision, &mut tags);
+    }
+    if !tags.is_empty() {
+        metadata.insert("tags".to_string(), JsonValue::Object(tags));
+    }
+    Some(metadata)
+}
+
+fn read_tags(revision: &MetadataRevision, tags: &mut Map<String, JsonValue>) {
+    for tag in revision.tags() {
+        let key = match tag.std_key {
+            Some(StandardTagKey::TrackTitle) => "title",
+            Some(StandardTagKey::Artist) => "artist",
+            Some(StandardTagKey::Album) => "album",
+            Some(StandardTagKey::AlbumArtist) => "album_artist",
+            Some(StandardTagKey::Composer) => "composer",
+            Some(StandardTagKey::Genre) => "genre",
+            Some(StandardTagKey::Date) => "date",
+            Some(StandardTagKey::TrackNumber) => "track_number",
+            Some(StandardTagKey::DiscNumber) => "disc_number",
+            Some(StandardTagKey::Comment) => "comment",
+            Some(StandardTagKey::Copyright) => "copyright",
+            _ => continue,
+        };
+        let value = match &tag.value {
+            TagValue::String(value) => json!(value.trim_matches(|c: char| c.is_whitespace() || c == '\0')),
+            TagValue::UnsignedInt(value) => json!(value),
+            TagValue::SignedInt(value) => json!(value),
+            TagValue::Float(value) => json!(value),
+            TagValue::Boolean(value) => json!(value),
+            TagValue::Binary(_) | TagValue::Flag => continue,
+        };
+        tags.insert(key.to_string(), value);
+    }
+}
+
+fn round_duration(duration: f64) -> f64 {
+    (duration * 1000.0).round() / 1000.0
+}
@@ -1,2 +1,20 @@
+pub mod archive;
+pub mod document_preview;
+pub mod export;
+pub mod file_cache;
+pub mod id_generator;
+pub mod image_metadata;
+pub mod image_processing;
+pub mod import_job;
+pub mod jwt;
+pub mod media_metadata;
+pub mod password;
+pub mod pdf_processing;
+pub mod permission;
+pub mod query_builder;
+pub mod search_index;
 pub mod serializer;
-pub mod string_manipulation;
\ No newline at end of file
+pub mod string_manipulation;
+pub mod text_extraction;
+pub mod watermark;
+pub mod waveform;
\ No newline at end of file
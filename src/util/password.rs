@@ -0,0 +1,12 @@
+use argon2::Config;
+
+/// Argon2 hash of a password in the encoded `$argon2id$...` form kept in `m_user.password`.
+pub fn hash(password: &str, salt: &str) -> Result<String, String> {
+    argon2::hash_encoded(password.as_bytes(), salt.as_bytes(), &Config::default())
+        .map_err(|error| format!("hash password failed: {error}"))
+}
+
+/// Whether the password matches the encoded hash, a malformed hash never matches.
+pub fn verify(password: &str, encoded: &str) -> bool {
+    argon2::verify_encoded(encoded, password.as_bytes()).unwrap_or(false)
+}
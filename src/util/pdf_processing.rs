@@ -0,0 +1,223 @@
+use std::{collections::BTreeMap, path::Path};
+
+use lopdf::{dictionary, Document, Error, Object, ObjectId, Result};
+
+// page attributes a page may inherit from its parent page tree nodes
+const INHERITABLE_KEYS: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+pub fn load(path: &Path) -> Result<Document> {
+    Document::load(path)
+}
+
+pub fn page_count(document: &Document) -> u32 {
+    document.get_pages().len() as u32
+}
+
+/// Parse page ranges like `1-3,5,8-` into page numbers, in the given order. An open end runs to
+/// the last page.
+pub fn parse_page_ranges(ranges: &str, page_count: u32) -> std::result::Result<Vec<u32>, String> {
+    let mut page_numbers = Vec::new();
+    for range in ranges.split(',').map(|value| value.trim()) {
+        let (start, end) = match range.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (range, range),
+        };
+        let start: u32 = start
+            .parse()
+            .map_err(|_| format!("invalid page range: {range}"))?;
+        let end: u32 = if end.is_empty() {
+            page_count
+        } else {
+            end.parse()
+                .map_err(|_| format!("invalid page range: {range}"))?
+        };
+        if start < 1 || start > end || end > page_count {
+            return Err(format!(
+                "page range {range} is outside of 1-{page_count}"
+            ));
+        }
+        page_numbers.extend(start..=end);
+    }
+    Ok(page_numbers)
+}
+
+/// Build a new document from pages of other documents. Pages are taken in the given order and
+/// may repeat; objects they use are copied along, everything else of the sources is left out.
+/// The sources are only read, one loaded document can feed several outputs.
+pub fn assemble(sources: &[(&Document, Vec<u32>)]) -> Result<Document> {
+    let mut output = Document::with_version("1.7");
+    let pages_id = output.new_object_id();
+    let mut kids: Vec<Object> = Vec::new();
+
+    for (document, page_numbers) in sources {
+        let pages = document.get_pages();
+
+        // the page tree and catalog of the source are rebuilt, references to them are pointed
+        // to the new pages or dropped, other objects are copied when first referenced
+        let mut copies: BTreeMap<ObjectId, Object> = document
+            .objects
+            .iter()
+            .filter(|(_, object)| is_structural(object))
+            .map(|(id, _)| (*id, Object::Null))
+            .collect();
+        let mut new_pages = Vec::new();
+        for page_number in page_numbers {
+            let page_id = *pages
+                .get(page_number)
+                .ok_or(Error::PageNumberNotFound(*page_number))?;
+            let mut page = document.get_dictionary(page_id)?.clone();
+            for key in INHERITABLE_KEYS {
+                if !page.has(key)
+                    && let Some(value) = inherited(document, page_id, key)
+                {
+                    page.set(key, value);
+                }
+            }
+            let new_page_id = output.new_object_id();
+            if let Some(value) = copies.get_mut(&page_id)
+                && matches!(value, Object::Null)
+            {
+                *value = Object::Reference(new_page_id);
+            }
+            new_pages.push((new_page_id, page));
+        }
+
+        let mut pending: Vec<(ObjectId, ObjectId)> = Vec::new();
+        for (new_page_id, page) in new_pages {
+            let mut page = Object::Dictionary(page);
+            copy_references(&mut page, &mut copies, &mut output, &mut pending);
+            if let Object::Dictionary(value) = &mut page {
+                value.set("Parent", pages_id);
+            }
+            output.objects.insert(new_page_id, page);
+            kids.push(new_page_id.into());
+        }
+        while let Some((source_id, new_id)) = pending.pop() {
+            let mut object = document
+                .get_object(source_id)
+                .cloned()
+                .unwrap_or(Object::Null);
+            copy_references(&mut object, &mut copies, &mut output, &mut pending);
+            output.objects.insert(new_id, object);
+        }
+    }
+
+    let count = kids.len() as i64;
+    output.objects.insert(
+        pages_id,
+        dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+        }
+        .into(),
+    );
+    let catalog_id = output.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    output.trailer.set("Root", catalog_id);
+
+    output.compress();
+    Ok(output)
+}
+
+/// Rotate pages clockwise by `angle`, a multiple of 90 degrees, on top of their rotation.
+pub fn rotate(document: &mut Document, page_numbers: &[u32], angle: i64) -> Result<()> {
+    let pages = document.get_pages();
+    for page_number in page_numbers {
+        let page_id = *pages
+            .get(page_number)
+            .ok_or(Error::PageNumberNotFound(*page_number))?;
+        let current = inherited(document, page_id, b"Rotate")
+            .and_then(|value| value.as_i64().ok())
+            .unwrap_or(0);
+        document
+            .get_dictionary_mut(page_id)?
+            .set("Rotate", (current + angle).rem_euclid(360));
+    }
+    Ok(())
+}
+
+pub fn to_bytes(document: &mut Document) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    document.save_to(&mut bytes)?;
+    Ok(bytes)
+}
+
+// value of `key` on the node or the closest of its ancestors
+fn inherited(document: &Document, node_id: ObjectId, key: &[u8]) -> Option<Object> {
+    let mut node = document.get_dictionary(node_id).ok()?;
+    // page trees are shallow, the bound only protects against cycles
+    for _ in 0..32 {
+        if let Ok(value) = node.get(key) {
+            return Some(value.clone());
+        }
+        let parent_id = node.get(b"Parent").ok()?.as_reference().ok()?;
+        node = document.get_dictionary(parent_id).ok()?;
+    }
+    None
+}
+
+fn is_structural(object: &Object) -> bool {
+    let type_name = object.type_name().unwrap_or_default();
+    [b"Catalog".as_slice(), b"Pages", b"Page"].contains(&type_name)
+}
+
+// points references at the copies of their objects in `output`, objects not copied yet get a new
+// id and are queued in `pending` to be copied
+fn copy_references(
+    object: &mut Object,
+    copies: &mut BTreeMap<ObjectId, Object>,
+    output: &mut Document,
+    pending: &mut Vec<(ObjectId, ObjectId)>,
+) {
+    match object {
+        Object::Reference(id) => {
+            let source_id = *id;
+            let copy = copies.entry(source_id).or_insert_with(|| {
+                let new_id = output.new_object_id();
+                pending.push((source_id, new_id));
+                Object::Reference(new_id)
+            });
+            *object = copy.clone();
+        }
+        Object::Array(values) => values
+            .iter_mut()
+            .for_each(|value| copy_references(value, copies, output, pending)),
+        Object::Dictionary(dictionary) => dictionary
+            .iter_mut()
+            .for_each(|(_, value)| copy_references(value, copies, output, pending)),
+        Object::Stream(stream) => stream
+            .dict
+            .iter_mut()
+            .for_each(|(_, value)| copy_references(value, copies, output, pending)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_page_ranges_expands_ranges_in_order() {
+        assert_eq!(parse_page_ranges("1-3,5", 5).unwrap(), vec![1, 2, 3, 5]);
+        assert_eq!(parse_page_ranges(" 4 , 2 - 3 ", 5).unwrap(), vec![4, 2, 3]);
+        assert_eq!(parse_page_ranges("2,2", 5).unwrap(), vec![2, 2]);
+        // an open range runs to the last page
+        assert_eq!(parse_page_ranges("3-", 5).unwrap(), vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn parse_page_ranges_rejects_invalid_ranges() {
+        assert!(parse_page_ranges("0", 5).is_err());
+        assert!(parse_page_ranges("6", 5).is_err());
+        assert!(parse_page_ranges("4-2", 5).is_err());
+        assert!(parse_page_ranges("1-9", 5).is_err());
+        assert!(parse_page_ranges("a-b", 5).is_err());
+        assert!(parse_page_ranges("", 5).is_err());
+        assert!(parse_page_ranges("1,,2", 5).is_err());
+        assert!(parse_page_ranges("-3", 5).is_err());
+    }
+}
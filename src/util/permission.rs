@@ -0,0 +1,86 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    sync::Arc,
+};
+
+use crate::dto::enumerator::permission::Permission;
+
+// key of the permissions a role holds on every module
+const EVERY_MODULE: &str = "*";
+
+// role code -> grants of the role
+pub type RolePermissions = HashMap<String, Arc<Grants>>;
+
+/// Permissions of a role, per module.
+#[derive(Debug, Default, Clone)]
+pub struct Grants {
+    every_module: HashSet<Permission>,
+    modules: HashMap<i64, HashSet<Permission>>,
+}
+
+impl Grants {
+    /// Whether the permission is held on the module. Files without a module are only covered by
+    /// permissions on every module. `admin` holds every other permission.
+    pub fn allows(&self, permission: Permission, module_id: Option<i64>) -> bool {
+        let holds = |permissions: &HashSet<Permission>| {
+            permissions.contains(&permission) || permissions.contains(&Permission::ADMIN)
+        };
+        holds(&self.every_module)
+            || module_id
+                .and_then(|value| self.modules.get(&value))
+                .is_some_and(holds)
+    }
+
+    /// Whether the permission is held on at least one module.
+    pub fn allows_any(&self, permission: Permission) -> bool {
+        self.allows(permission, None)
+            || self
+                .modules
+                .keys()
+                .any(|module_id| self.allows(permission, Some(*module_id)))
+    }
+
+    /// Modules the permission is held on, `None` when it is held on every module.
+    pub fn module_ids(&self, permission: Permission) -> Option<Vec<i64>> {
+        if self.allows(permission, None) {
+            return None;
+        }
+        let mut module_ids: Vec<i64> = self
+            .modules
+            .keys()
+            .filter(|module_id| self.allows(permission, Some(**module_id)))
+            .copied()
+            .collect();
+        module_ids.sort();
+        Some(module_ids)
+    }
+}
+
+/// Read the permissions of a JSON file shaped
+/// `{"<role code>": {"<module id>" or "*": ["read", "upload", ...]}, ...}`.
+pub fn load_role_permissions(path: &Path) -> Result<RolePermissions, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| format!("read {} failed: {error}", path.display()))?;
+    let roles: HashMap<String, HashMap<String, HashSet<Permission>>> =
+        serde_json::from_str(&contents)
+            .map_err(|error| format!("parse {} failed: {error}", path.display()))?;
+    roles
+        .into_iter()
+        .map(|(role_code, modules)| {
+            let mut grants = Grants::default();
+            for (module, permissions) in modules {
+                if module == EVERY_MODULE {
+                    grants.every_module = permissions;
+                    continue;
+                }
+                let module_id: i64 = module
+                    .parse()
+                    .map_err(|_| format!("role {role_code}: invalid module id {module}"))?;
+                grants.modules.insert(module_id, permissions);
+            }
+            Ok((role_code, Arc::new(grants)))
+        })
+        .collect()
+}
@@ -0,0 +1,903 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use diesel::{
+    mysql::Mysql,
+    query_builder::{BoxedSqlQuery, SqlQuery},
+    row::NamedRow,
+    sql_query, sql_types, QueryableByName,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::{
+    dto::{
+        enumerator::{
+            filter_data_type::FilterDataType, filter_match_mode::FilterMatchMode,
+            filter_mode::FilterMode,
+        },
+        request::{
+            filter_request::{Filter, FilterNode},
+            sort_request::Sort,
+        },
+        response::app_error::AppError,
+    },
+    util::string_manipulation,
+};
+
+pub type BoxedQuery = BoxedSqlQuery<'static, Mysql, SqlQuery>;
+
+const DATE_TIME_FORMATS: [&str; 3] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"];
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const END_OF_DAY: NaiveTime = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
+
+// longest list of an IN or NOTIN filter
+const MAX_IN_VALUES: usize = 1000;
+// DATETIME values inside a JSON array
+const JSON_DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f";
+
+/// Column clients may filter and sort on. `id` is the name used in requests, `expression` the
+/// SQL it stands for, so request values never reach the SQL text.
+pub struct Column {
+    pub id: &'static str,
+    pub expression: &'static str,
+    pub data_type: FilterDataType,
+    pub sortable: bool,
+}
+
+/// Column or expression whose rows are counted per value.
+pub struct Facet {
+    pub id: &'static str,
+    pub expression: &'static str,
+}
+
+/// Value bound to a `?` placeholder.
+#[derive(Debug, Clone)]
+pub enum BindValue {
+    Text(String),
+    Integer(i64),
+    Number(f64),
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    Boolean(bool),
+}
+
+// SQL fragment with the values of its placeholders, in order
+struct Fragment {
+    sql: String,
+    binds: Vec<BindValue>,
+}
+
+#[derive(Clone)]
+struct Order {
+    id: &'static str,
+    expression: &'static str,
+    data_type: FilterDataType,
+    desc: bool,
+}
+
+/// Row of a `select`, with the values of its sort columns as a JSON array.
+pub struct KeyedRow<T> {
+    pub row: T,
+    pub sort_key: String,
+}
+
+impl<T: QueryableByName<Mysql>> QueryableByName<Mysql> for KeyedRow<T> {
+    fn build<'a>(row: &impl NamedRow<'a, Mysql>) -> diesel::deserialize::Result<Self> {
+        Ok(KeyedRow {
+            row: T::build(row)?,
+            sort_key: NamedRow::get::<sql_types::Text, String>(row, "sort_key")?,
+        })
+    }
+}
+
+/// Opaque cursors of the pages after and before the current one.
+#[derive(Debug, Clone, Default)]
+pub struct Cursors {
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum CursorDirection {
+    Next,
+    Prev,
+}
+
+// position of a row in a sorted listing, given to clients base64 encoded
+#[derive(Debug, Deserialize, Serialize)]
+struct Cursor {
+    direction: CursorDirection,
+    // sort the cursor was made for, e.g. `created_on:desc,id:asc`
+    sort: String,
+    // sort column values of the row
+    values: Vec<JsonValue>,
+}
+
+/// Paged `SELECT` of one table with whitelisted filters and sorts, every value is bound as a
+/// parameter.
+///
+/// Pages are addressed by number (`LIMIT/OFFSET`) or by a cursor (keyset), rows are always
+/// sorted last by `key_column` so the order is stable.
+pub struct QueryBuilder {
+    table: &'static str,
+    columns: &'static [Column],
+    // unique column, the last sort of every query
+    key_column: &'static str,
+    // JSON column whose values are filtered as `<column>.<key>.<key>`
+    json_column: Option<&'static str>,
+    conditions: Vec<Fragment>,
+    orders: Vec<Order>,
+    cursor: Option<Cursor>,
+}
+
+impl QueryBuilder {
+    pub fn new(table: &'static str, columns: &'static [Column], key_column: &'static str) -> Self {
+        QueryBuilder {
+            table,
+            columns,
+            key_column,
+            json_column: None,
+            conditions: Vec::new(),
+            orders: Vec::new(),
+            cursor: None,
+        }
+    }
+
+    pub fn json_column(mut self, name: &'static str) -> Self {
+        self.json_column = Some(name);
+        self
+    }
+
+    /// Match rows whose column contains `value`, `%` and `_` in it are matched literally.
+    pub fn search(&mut self, column_id: &str, value: &str) -> Result<(), AppError> {
+        let column = self.column(column_id)?;
+        self.conditions.push(Fragment {
+            sql: format!("{} LIKE ?", text_expression(&column)),
+            binds: [
+                column.binds,
+                vec![BindValue::Text(format!(
+                    "%{}%",
+                    string_manipulation::escape_like(value)
+                ))],
+            ]
+            .concat(),
+        });
+        Ok(())
+    }
+
+    /// Add a condition written by the caller, combined with the other conditions by AND. Its
+    /// values are bound to the placeholders in `sql`, never written into it.
+    pub fn custom_condition(&mut self, sql: String, binds: Vec<BindValue>) {
+        self.conditions.push(Fragment { sql, binds });
+    }
+
+    /// Add a filter or a group of filters, combined with the other conditions by AND.
+    pub fn filter(&mut self, node: &FilterNode) -> Result<(), AppError> {
+        if let Some(condition) = self.node_condition(node)? {
+            self.conditions.push(condition);
+        }
+        Ok(())
+    }
+
+    // `None` for empty groups, they match every row
+    fn node_condition(&self, node: &FilterNode) -> Result<Option<Fragment>, AppError> {
+        let group = match node {
+            FilterNode::Filter(filter) => return self.condition(filter).map(Some),
+            FilterNode::Group(group) => group,
+        };
+        let mut conditions = Vec::new();
+        for child in group.filters.iter() {
+            if let Some(condition) = self.node_condition(child)? {
+                conditions.push(condition);
+            }
+        }
+        if conditions.is_empty() {
+            return Ok(None);
+        }
+        let separator = match group.mode {
+            FilterMode::AND => " AND ",
+            FilterMode::OR => " OR ",
+        };
+        Ok(Some(join(&conditions, separator)))
+    }
+
+    fn condition(&self, filter: &Filter) -> Result<Fragment, AppError> {
+        let column = self.column(&filter.id)?;
+        let ignore_case = filter.ignore_case && filter.data_type == FilterDataType::TEXT;
+        let condition = match filter.match_mode {
+            FilterMatchMode::CONTAINS | FilterMatchMode::SW | FilterMatchMode::EW => {
+                // backslash is the default LIKE escape character of MySQL
+                let value = string_manipulation::escape_like(&filter.value);
+                let pattern = match filter.match_mode {
+                    FilterMatchMode::CONTAINS => format!("%{}%", value),
+                    FilterMatchMode::SW => format!("{}%", value),
+                    _ => format!("%{}", value),
+                };
+                let sql = if filter.ignore_case {
+                    format!("LOWER({}) LIKE LOWER(?)", text_expression(&column))
+                } else {
+                    format!("{} LIKE ?", text_expression(&column))
+                };
+                Fragment {
+                    sql,
+                    binds: [column.binds, vec![BindValue::Text(pattern)]].concat(),
+                }
+            }
+            FilterMatchMode::EQUALS
+            | FilterMatchMode::NOT
+            | FilterMatchMode::LT
+            | FilterMatchMode::GT => {
+                let operator = match filter.match_mode {
+                    FilterMatchMode::EQUALS => "=",
+                    FilterMatchMode::NOT => "<>",
+                    FilterMatchMode::LT => "<",
+                    _ => ">",
+                };
+                let value = parse_filter_value(filter, &filter.value, &column.data_type)?;
+                let expression = typed_expression(&column, &filter.data_type, &value);
+                let sql = if ignore_case {
+                    format!("LOWER({}) {} LOWER(?)", expression, operator)
+                } else {
+                    format!("{} {} ?", expression, operator)
+                };
+                Fragment {
+                    sql,
+                    binds: [column.binds, vec![value]].concat(),
+                }
+            }
+            FilterMatchMode::BETWEEN => {
+                let bounds = filter.values.as_deref().unwrap_or_default();
+                let [lower, upper] = bounds else {
+                    return Err(AppError::BadRequest(format!(
+                        "BETWEEN needs two values, id: {}",
+                        filter.id
+                    )));
+                };
+                let mut lower = parse_filter_value(filter, lower, &column.data_type)?;
+                let mut upper = parse_filter_value(filter, upper, &column.data_type)?;
+                // a day next to a time stands for the whole day
+                match (&lower, &upper) {
+                    (BindValue::Date(value), BindValue::DateTime(_)) => {
+                        lower = BindValue::DateTime(value.and_time(NaiveTime::MIN));
+                    }
+                    (BindValue::DateTime(_), BindValue::Date(value)) => {
+                        upper = BindValue::DateTime(value.and_time(END_OF_DAY));
+                    }
+                    _ => {}
+                }
+                let expression = typed_expression(&column, &filter.data_type, &lower);
+                let sql = if ignore_case {
+                    format!("LOWER({}) BETWEEN LOWER(?) AND LOWER(?)", expression)
+                } else {
+                    format!("{} BETWEEN ? AND ?", expression)
+                };
+                Fragment {
+                    sql,
+                    binds: [column.binds, vec![lower, upper]].concat(),
+                }
+            }
+            FilterMatchMode::IN | FilterMatchMode::NOTIN => {
+                let values = filter.values.as_deref().unwrap_or_default();
+                if values.is_empty() || values.len() > MAX_IN_VALUES {
+                    return Err(AppError::BadRequest(format!(
+                        "{:?} needs 1 to {} values, id: {}",
+                        filter.match_mode, MAX_IN_VALUES, filter.id
+                    )));
+                }
+                let values = values
+                    .iter()
+                    .map(|value| parse_filter_value(filter, value, &column.data_type))
+                    .collect::<Result<Vec<BindValue>, AppError>>()?;
+                let is_day = |value: &BindValue| matches!(value, BindValue::Date(_));
+                if values.iter().any(is_day) && !values.iter().all(is_day) {
+                    return Err(AppError::BadRequest(format!(
+                        "values must all be days or all be times, id: {}",
+                        filter.id
+                    )));
+                }
+                let expression = typed_expression(&column, &filter.data_type, &values[0]);
+                let placeholder = if ignore_case { "LOWER(?)" } else { "?" };
+                let placeholders = vec![placeholder; values.len()].join(", ");
+                let operator = if filter.match_mode == FilterMatchMode::IN {
+                    "IN"
+                } else {
+                    "NOT IN"
+                };
+                let sql = if ignore_case {
+                    format!("LOWER({}) {} ({})", expression, operator, placeholders)
+                } else {
+                    format!("{} {} ({})", expression, operator, placeholders)
+                };
+                Fragment {
+                    sql,
+                    binds: [column.binds, values].concat(),
+                }
+            }
+            FilterMatchMode::ISNULL | FilterMatchMode::NOTNULL => {
+                let operator = if filter.match_mode == FilterMatchMode::ISNULL {
+                    "IS NULL"
+                } else {
+                    "IS NOT NULL"
+                };
+                Fragment {
+                    sql: format!("{} {}", column.expression, operator),
+                    binds: column.binds,
+                }
+            }
+        };
+        Ok(condition)
+    }
+
+    /// Sort by one more column, a column sorted twice keeps its first direction.
+    pub fn sort(&mut self, sort: &Sort) -> Result<(), AppError> {
+        let column = self
+            .columns
+            .iter()
+            .find(|column| column.id == sort.id && column.sortable)
+            .ok_or_else(|| AppError::BadRequest(format!("cannot sort on {}", sort.id)))?;
+        if self.orders.iter().any(|order| order.id == column.id) {
+            return Ok(());
+        }
+        self.orders.push(Order {
+            id: column.id,
+            expression: column.expression,
+            data_type: column.data_type.clone(),
+            desc: sort.desc,
+        });
+        Ok(())
+    }
+
+    /// Continue from a cursor of a previous page, made with the same sorts.
+    pub fn cursor(&mut self, cursor: &str) -> Result<(), AppError> {
+        let invalid_cursor = || AppError::BadRequest("invalid cursor".to_string());
+        let cursor: Cursor = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|value| serde_json::from_slice(&value).ok())
+            .ok_or_else(invalid_cursor)?;
+        let orders = self.sorted_orders();
+        if cursor.sort != sort_signature(&orders) || cursor.values.len() != orders.len() {
+            return Err(AppError::BadRequest(
+                "cursor was made for another sort".to_string(),
+            ));
+        }
+        for (order, value) in orders.iter().zip(cursor.values.iter()) {
+            if !value.is_null() && json_bind_value(value, &order.data_type).is_none() {
+                return Err(invalid_cursor());
+            }
+        }
+        self.cursor = Some(cursor);
+        Ok(())
+    }
+
+    /// Rows of page `page` (from 0), or of the page at the cursor, plus one row telling whether
+    /// more rows follow. Each row carries its `sort_key`, see `page`. Fails with 400 when the
+    /// offset of the page does not fit in an i64.
+    pub fn select(&self, page: i64, size: i64) -> Result<BoxedQuery, AppError> {
+        let too_far = || AppError::BadRequest(format!("page {page} of size {size} is too far"));
+        let limit = size.checked_add(1).ok_or_else(too_far)?;
+        let offset = page.checked_mul(size).ok_or_else(too_far)?;
+        let orders = self.sorted_orders();
+        // pages before a cursor are read backwards, then put back in order by `page`
+        let reverse = self
+            .cursor
+            .as_ref()
+            .is_some_and(|cursor| cursor.direction == CursorDirection::Prev);
+
+        let mut conditions: Vec<Fragment> = self
+            .conditions
+            .iter()
+            .map(|condition| Fragment {
+                sql: condition.sql.clone(),
+                binds: condition.binds.clone(),
+            })
+            .collect();
+        if let Some(cursor) = &self.cursor
+            && let Some(condition) = keyset_condition(&orders, &cursor.values, reverse)
+        {
+            conditions.push(condition);
+        }
+        let (where_clause, mut binds) = where_clause(&conditions);
+
+        let sort_key = orders
+            .iter()
+            .map(|order| order.expression)
+            .collect::<Vec<&str>>()
+            .join(", ");
+        let order_clause = orders
+            .iter()
+            .map(|order| {
+                let direction = if order.desc != reverse { "DESC" } else { "ASC" };
+                format!("{} {}", order.expression, direction)
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        binds.push(BindValue::Integer(limit));
+        let mut sql = format!(
+            "SELECT *, JSON_ARRAY({}) AS sort_key FROM {}{} ORDER BY {} LIMIT ?",
+            sort_key, self.table, where_clause, order_clause
+        );
+        if self.cursor.is_none() {
+            sql.push_str(" OFFSET ?");
+            binds.push(BindValue::Integer(offset));
+        }
+        Ok(bind_all(sql, binds))
+    }
+
+    /// Number of rows matching the conditions, as `count`. The cursor is ignored.
+    pub fn count(&self) -> BoxedQuery {
+        let (where_clause, binds) = where_clause(&self.conditions);
+        bind_all(
+            format!("SELECT COUNT(*) AS count FROM {}{}", self.table, where_clause),
+            binds,
+        )
+    }
+
+    /// Number of rows matching the conditions per value of the facet as `value` and `count`,
+    /// most frequent first. The cursor is ignored.
+    pub fn facet(&self, facet: &Facet, limit: i64) -> BoxedQuery {
+        let (where_clause, mut binds) = where_clause(&self.conditions);
+        binds.push(BindValue::Integer(limit));
+        bind_all(
+            format!(
+                "SELECT CAST({} AS CHAR) AS value, COUNT(*) AS count FROM {}{} GROUP BY value ORDER BY count DESC, value ASC LIMIT ?",
+                facet.expression, self.table, where_clause
+            ),
+            binds,
+        )
+    }
+
+    /// The rows of a page read with `select`, in order, and the cursors around them.
+    pub fn page<T>(&self, page: i64, size: i64, mut rows: Vec<KeyedRow<T>>) -> (Vec<T>, Cursors) {
+        let has_more = rows.len() as i64 > size;
+        rows.truncate(size.max(0) as usize);
+        let direction = self.cursor.as_ref().map(|cursor| cursor.direction);
+        if direction == Some(CursorDirection::Prev) {
+            rows.reverse();
+        }
+        let (has_prev, has_next) = match direction {
+            None => (page > 0, has_more),
+            Some(CursorDirection::Next) => (true, has_more),
+            Some(CursorDirection::Prev) => (has_more, true),
+        };
+
+        let orders = self.sorted_orders();
+        let encode = |row: &KeyedRow<T>, direction: CursorDirection| {
+            let cursor = Cursor {
+                direction,
+                sort: sort_signature(&orders),
+                values: serde_json::from_str(&row.sort_key).unwrap_or_default(),
+            };
+            URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).unwrap_or_default())
+        };
+        let cursors = Cursors {
+            next: rows
+                .last()
+                .filter(|_| has_next)
+                .map(|row| encode(row, CursorDirection::Next)),
+            prev: rows
+                .first()
+                .filter(|_| has_prev)
+                .map(|row| encode(row, CursorDirection::Prev)),
+        };
+        (rows.into_iter().map(|row| row.row).collect(), cursors)
+    }
+
+    // requested sorts, then the key column ascending unless it is sorted already
+    fn sorted_orders(&self) -> Vec<Order> {
+        let mut orders = self.orders.clone();
+        if !orders.iter().any(|order| order.id == self.key_column)
+            && let Some(column) = self
+                .columns
+                .iter()
+                .find(|column| column.id == self.key_column)
+        {
+            orders.push(Order {
+                id: column.id,
+                expression: column.expression,
+                data_type: column.data_type.clone(),
+                desc: false,
+            });
+        }
+        orders
+    }
+
+    fn column(&self, id: &str) -> Result<ResolvedColumn, AppError> {
+        if let Some(column) = self.columns.iter().find(|column| column.id == id) {
+            return Ok(ResolvedColumn {
+                expression: column.expression.to_string(),
+                binds: Vec::new(),
+                data_type: column.data_type.clone(),
+            });
+        }
+
+        // `metadata.media.duration` reads `$.media.duration` of the metadata column
+        if let Some(json_column) = self.json_column
+            && let Some(path) = id
+                .strip_prefix(json_column)
+                .and_then(|value| value.strip_prefix('.'))
+        {
+            let is_valid = path.split('.').all(|key| {
+                !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|character| character.is_ascii_alphanumeric() || character == '_')
+            });
+            if is_valid {
+                return Ok(ResolvedColumn {
+                    expression: format!("JSON_UNQUOTE(JSON_EXTRACT({}, ?))", json_column),
+                    binds: vec![BindValue::Text(format!("$.{}", path))],
+                    data_type: FilterDataType::TEXT,
+                });
+            }
+        }
+        Err(AppError::BadRequest(format!("cannot filter on {}", id)))
+    }
+}
+
+fn where_clause(conditions: &[Fragment]) -> (String, Vec<BindValue>) {
+    if conditions.is_empty() {
+        return (String::new(), Vec::new());
+    }
+    let condition = join(conditions, " AND ");
+    (format!(" WHERE {}", condition.sql), condition.binds)
+}
+
+fn sort_signature(orders: &[Order]) -> String {
+    orders
+        .iter()
+        .map(|order| format!("{}:{}", order.id, if order.desc { "desc" } else { "asc" }))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Rows after the cursor row in the sort order, `(a, b) > (x, y)` spelled out per column so
+/// each column keeps its own direction. MySQL puts NULL first when ascending and last when
+/// descending. `None` when no row can follow.
+fn keyset_condition(orders: &[Order], values: &[JsonValue], reverse: bool) -> Option<Fragment> {
+    let mut branches = Vec::new();
+    for (index, order) in orders.iter().enumerate() {
+        let mut parts = Vec::new();
+        // equal on every previous column
+        for (previous, value) in orders.iter().zip(values.iter()).take(index) {
+            parts.push(match json_bind_value(value, &previous.data_type) {
+                Some(bind) => Fragment {
+                    sql: format!("{} = ?", previous.expression),
+                    binds: vec![bind],
+                },
+                None => Fragment {
+                    sql: format!("{} IS NULL", previous.expression),
+                    binds: Vec::new(),
+                },
+            });
+        }
+        // and after the cursor on this one
+        let desc = order.desc != reverse;
+        let after = match (json_bind_value(&values[index], &order.data_type), desc) {
+            (None, false) => Fragment {
+                sql: format!("{} IS NOT NULL", order.expression),
+                binds: Vec::new(),
+            },
+            // nothing sorts after NULL when descending
+            (None, true) => continue,
+            (Some(bind), false) => Fragment {
+                sql: format!("{} > ?", order.expression),
+                binds: vec![bind],
+            },
+            (Some(bind), true) => Fragment {
+                sql: format!("{0} < ? OR {0} IS NULL", order.expression),
+                binds: vec![bind],
+            },
+        };
+        parts.push(after);
+        branches.push(join(&parts, " AND "));
+    }
+    if branches.is_empty() {
+        return None;
+    }
+    Some(join(&branches, " OR "))
+}
+
+// value of a sort column read back from JSON, `None` for NULL or a value of another type
+fn json_bind_value(value: &JsonValue, data_type: &FilterDataType) -> Option<BindValue> {
+    match (data_type, value) {
+        (_, JsonValue::Null) => None,
+        (FilterDataType::NUMBER, JsonValue::Number(number)) => match number.as_i64() {
+            Some(integer) => Some(BindValue::Integer(integer)),
+            None => number.as_f64().map(BindValue::Number),
+        },
+        (FilterDataType::DATE, JsonValue::String(text)) => {
+            NaiveDateTime::parse_from_str(text, JSON_DATE_TIME_FORMAT)
+                .ok()
+                .map(BindValue::DateTime)
+                .or_else(|| {
+                    NaiveDate::parse_from_str(text, DATE_FORMAT)
+                        .ok()
+                        .map(BindValue::Date)
+                })
+        }
+        (FilterDataType::BOOLEAN, JsonValue::Bool(boolean)) => Some(BindValue::Boolean(*boolean)),
+        // MySQL booleans are TINYINT(1)
+        (FilterDataType::BOOLEAN, JsonValue::Number(number)) => {
+            Some(BindValue::Boolean(number.as_i64()? != 0))
+        }
+        (FilterDataType::TEXT, JsonValue::String(text)) => Some(BindValue::Text(text.clone())),
+        _ => None,
+    }
+}
+
+// the fragments in parentheses, separated by AND or OR
+fn join(fragments: &[Fragment], separator: &str) -> Fragment {
+    let sql = fragments
+        .iter()
+        .map(|fragment| format!("({})", fragment.sql))
+        .collect::<Vec<String>>()
+        .join(separator);
+    let binds = fragments
+        .iter()
+        .flat_map(|fragment| fragment.binds.clone())
+        .collect();
+    Fragment { sql, binds }
+}
+
+struct ResolvedColumn {
+    expression: String,
+    // values of placeholders inside the expression
+    binds: Vec<BindValue>,
+    data_type: FilterDataType,
+}
+
+fn text_expression(column: &ResolvedColumn) -> String {
+    if column.data_type == FilterDataType::TEXT {
+        return column.expression.clone();
+    }
+    format!("CAST({} AS CHAR)", column.expression)
+}
+
+// the column converted to the type of the filter value
+fn typed_expression(column: &ResolvedColumn, data_type: &FilterDataType, value: &BindValue) -> String {
+    // a day matches every time of that day
+    if let BindValue::Date(_) = value {
+        return format!("DATE({})", column.expression);
+    }
+    if column.data_type == *data_type {
+        return column.expression.clone();
+    }
+    match data_type {
+        FilterDataType::NUMBER => format!("CAST({} AS DECIMAL(20,6))", column.expression),
+        FilterDataType::DATE => format!("CAST({} AS DATETIME)", column.expression),
+        FilterDataType::TEXT => format!("CAST({} AS CHAR)", column.expression),
+        // compared with the text `true` or `false`, see parse_value
+        FilterDataType::BOOLEAN => column.expression.clone(),
+    }
+}
+
+fn parse_filter_value(
+    filter: &Filter,
+    value: &str,
+    column_type: &FilterDataType,
+) -> Result<BindValue, AppError> {
+    parse_value(value, &filter.data_type, column_type).ok_or_else(|| {
+        AppError::BadRequest(format!(
+            "{} is not a valid {:?} for {}",
+            value, filter.data_type, filter.id
+        ))
+    })
+}
+
+fn parse_value(value: &str, data_type: &FilterDataType, column_type: &FilterDataType) -> Option<BindValue> {
+    let value = value.trim();
+    match data_type {
+        FilterDataType::TEXT => Some(BindValue::Text(value.to_string())),
+        FilterDataType::NUMBER => {
+            if let Ok(number) = value.parse::<i64>() {
+                return Some(BindValue::Integer(number));
+            }
+            let number = value.parse::<f64>().ok()?;
+            number.is_finite().then_some(BindValue::Number(number))
+        }
+        FilterDataType::DATE => {
+            for format in DATE_TIME_FORMATS {
+                if let Ok(date_time) = NaiveDateTime::parse_from_str(value, format) {
+                    return Some(BindValue::DateTime(date_time));
+                }
+            }
+            NaiveDate::parse_from_str(value, DATE_FORMAT)
+                .ok()
+                .map(BindValue::Date)
+        }
+        FilterDataType::BOOLEAN => {
+            let boolean = match value.to_lowercase().as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return None,
+            };
+            // JSON and text columns hold booleans as text
+            if *column_type != FilterDataType::BOOLEAN {
+                return Some(BindValue::Text(boolean.to_string()));
+            }
+            Some(BindValue::Boolean(boolean))
+        }
+    }
+}
+
+/// `sql_query` of `sql` with `binds` bound to its placeholders, in order.
+pub fn bind_all(sql: String, binds: Vec<BindValue>) -> BoxedQuery {
+    let mut query = sql_query(sql).into_boxed::<Mysql>();
+    for bind in binds {
+        query = match bind {
+            BindValue::Text(value) => query.bind::<sql_types::Text, _>(value),
+            BindValue::Integer(value) => query.bind::<sql_types::BigInt, _>(value),
+            BindValue::Number(value) => query.bind::<sql_types::Double, _>(value),
+            BindValue::Date(value) => query.bind::<sql_types::Date, _>(value),
+            BindValue::DateTime(value) => query.bind::<sql_types::Datetime, _>(value),
+            BindValue::Boolean(value) => query.bind::<sql_types::Bool, _>(value),
+        };
+    }
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static COLUMNS: [Column; 2] = [
+        Column {
+            id: "name",
+            expression: "file_name",
+            data_type: FilterDataType::TEXT,
+            sortable: true,
+        },
+        Column {
+            id: "size",
+            expression: "file_size",
+            data_type: FilterDataType::NUMBER,
+            sortable: true,
+        },
+    ];
+
+    fn order(id: &'static str, data_type: FilterDataType, desc: bool) -> Order {
+        Order {
+            id,
+            expression: id,
+            data_type,
+            desc,
+        }
+    }
+
+    #[test]
+    fn keyset_condition_keeps_the_direction_of_each_column() {
+        let orders = [
+            order("created_on", FilterDataType::DATE, true),
+            order("id", FilterDataType::NUMBER, false),
+        ];
+        let values = [serde_json::json!("2024-01-02 03:04:05.000"), serde_json::json!(7)];
+
+        let condition = keyset_condition(&orders, &values, false).unwrap();
+        assert_eq!(
+            condition.sql,
+            "((created_on < ? OR created_on IS NULL)) OR ((created_on = ?) AND (id > ?))"
+        );
+        assert_eq!(condition.binds.len(), 3);
+        assert!(matches!(condition.binds[0], BindValue::DateTime(_)));
+        assert!(matches!(condition.binds[2], BindValue::Integer(7)));
+    }
+
+    #[test]
+    fn keyset_condition_reversed_flips_the_comparisons() {
+        let orders = [order("id", FilterDataType::NUMBER, false)];
+        let values = [serde_json::json!(7)];
+
+        let condition = keyset_condition(&orders, &values, true).unwrap();
+        assert_eq!(condition.sql, "((id < ? OR id IS NULL))");
+    }
+
+    #[test]
+    fn keyset_condition_handles_null_values() {
+        let orders = [
+            order("name", FilterDataType::TEXT, false),
+            order("id", FilterDataType::NUMBER, false),
+        ];
+        let values = [JsonValue::Null, serde_json::json!(3)];
+
+        let condition = keyset_condition(&orders, &values, false).unwrap();
+        assert_eq!(condition.sql, "((name IS NOT NULL)) OR ((name IS NULL) AND (id > ?))");
+        assert_eq!(condition.binds.len(), 1);
+
+        // nothing follows a NULL when descending
+        let orders = [order("name", FilterDataType::TEXT, true)];
+        assert!(keyset_condition(&orders, &[JsonValue::Null], false).is_none());
+    }
+
+    #[test]
+    fn parse_value_reads_each_data_type() {
+        let number = FilterDataType::NUMBER;
+        assert!(matches!(parse_value(" 42 ", &number, &number), Some(BindValue::Integer(42))));
+        assert!(matches!(parse_value("1.5", &number, &number), Some(BindValue::Number(_))));
+        assert!(parse_value("inf", &number, &number).is_none());
+        assert!(parse_value("NaN", &number, &number).is_none());
+        assert!(parse_value("ten", &number, &number).is_none());
+
+        let date = FilterDataType::DATE;
+        assert!(matches!(parse_value("2024-01-02", &date, &date), Some(BindValue::Date(_))));
+        assert!(matches!(
+            parse_value("2024-01-02T03:04:05", &date, &date),
+            Some(BindValue::DateTime(_))
+        ));
+        assert!(parse_value("02/01/2024", &date, &date).is_none());
+
+        let text = FilterDataType::TEXT;
+        assert!(matches!(
+            parse_value("abc", &text, &text),
+            Some(BindValue::Text(value)) if value == "abc"
+        ));
+    }
+
+    #[test]
+    fn parse_value_keeps_booleans_of_text_columns_as_text() {
+        let boolean = FilterDataType::BOOLEAN;
+        assert!(matches!(
+            parse_value("TRUE", &boolean, &boolean),
+            Some(BindValue::Boolean(true))
+        ));
+        assert!(matches!(
+            parse_value("0", &boolean, &FilterDataType::TEXT),
+            Some(BindValue::Text(value)) if value == "false"
+        ));
+        assert!(parse_value("yes", &boolean, &boolean).is_none());
+    }
+
+    #[test]
+    fn typed_expression_casts_to_the_filter_type() {
+        let column = ResolvedColumn {
+            expression: "file_size".to_string(),
+            binds: Vec::new(),
+            data_type: FilterDataType::NUMBER,
+        };
+        assert_eq!(
+            typed_expression(&column, &FilterDataType::NUMBER, &BindValue::Integer(1)),
+            "file_size"
+        );
+        assert_eq!(
+            typed_expression(&column, &FilterDataType::TEXT, &BindValue::Text("1".to_string())),
+            "CAST(file_size AS CHAR)"
+        );
+
+        let column = ResolvedColumn {
+            expression: "created_on".to_string(),
+            binds: Vec::new(),
+            data_type: FilterDataType::DATE,
+        };
+        let day = NaiveDate::from_ymd_opt(2024, 1, 2).unwrap();
+        assert_eq!(
+            typed_expression(&column, &FilterDataType::DATE, &BindValue::Date(day)),
+            "DATE(created_on)"
+        );
+    }
+
+    #[test]
+    fn column_resolves_whitelisted_ids_and_json_paths() {
+        let builder = QueryBuilder::new("m_file", &COLUMNS, "id").json_column("metadata");
+
+        let Ok(column) = builder.column("size") else {
+            panic!("size is a column");
+        };
+        assert_eq!(column.expression, "file_size");
+
+        let Ok(column) = builder.column("metadata.media.duration") else {
+            panic!("metadata.media.duration is a JSON path");
+        };
+        assert_eq!(column.expression, "JSON_UNQUOTE(JSON_EXTRACT(metadata, ?))");
+        assert!(matches!(
+            &column.binds[..],
+            [BindValue::Text(path)] if path == "$.media.duration"
+        ));
+
+        assert!(builder.column("file_name").is_err());
+        assert!(builder.column("metadata").is_err());
+        assert!(builder.column("metadata.").is_err());
+        assert!(builder.column("metadata.media..duration").is_err());
+        assert!(builder.column("metadata.a') OR 1=1 -- ").is_err());
+    }
+}
@@ -0,0 +1,455 @@
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use levenshtein_automata::{Distance, LevenshteinAutomatonBuilder, DFA};
+use serde::{Deserialize, Serialize};
+use tantivy::{
+    collector::TopDocs,
+    columnar::Column,
+    directory::MmapDirectory,
+    query::{BooleanQuery, BoostQuery, Occur, Query, TermQuery},
+    schema::{Field, IndexRecordOption, Schema, FAST, INDEXED, STORED, STRING, TEXT},
+    snippet::SnippetGenerator,
+    tokenizer::TokenStream,
+    DocAddress, Index, IndexReader, IndexWriter, ReloadPolicy, Searcher, TantivyDocument, Term,
+};
+use tantivy_fst::Automaton;
+use tokio::sync::{mpsc, oneshot};
+
+const WRITER_MEMORY: usize = 50 * 1024 * 1024;
+// expanded terms per query word and field, keeps typo tolerant queries bounded
+const MAX_EXPANSIONS: usize = 50;
+
+pub struct SearchDocument {
+    pub id: i64,
+    pub file_name: String,
+    pub file_type: String,
+    pub module_id: i64,
+    pub tags: Vec<String>,
+    pub metadata: String,
+    pub content: String,
+}
+
+pub enum IndexChange {
+    // add the document, replacing the one with the same id
+    Upsert(SearchDocument),
+    Delete(i64),
+}
+
+// builds a document on the index worker, building may read the file
+pub type DocumentSource = Box<dyn FnOnce() -> SearchDocument + Send>;
+
+// every document of a rebuild, read one at a time on the index worker
+pub type DocumentSources = Box<dyn Iterator<Item = tantivy::Result<SearchDocument>> + Send>;
+
+pub enum IndexUpdate {
+    Upsert(DocumentSource),
+    Delete(i64),
+    // replace every document, the number indexed is sent back
+    Rebuild(DocumentSources, oneshot::Sender<tantivy::Result<usize>>),
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SearchHit {
+    pub id: i64,
+    pub score: f32,
+    // field name -> HTML snippet, matched words are wrapped in <b>
+    pub highlights: BTreeMap<String, String>,
+}
+
+struct SearchMatch {
+    id: i64,
+    score: f32,
+    address: DocAddress,
+}
+
+/// Matches of `SearchIndex::search`, best first. Snippets are made for the ones a caller keeps,
+/// see `SearchIndex::hits`.
+pub struct SearchMatches {
+    searcher: Searcher,
+    // None when nothing can match
+    query: Option<BooleanQuery>,
+    matches: Vec<SearchMatch>,
+}
+
+impl SearchMatches {
+    fn empty(searcher: Searcher) -> SearchMatches {
+        SearchMatches {
+            searcher,
+            query: None,
+            matches: Vec::new(),
+        }
+    }
+
+    pub fn ids(&self) -> Vec<i64> {
+        self.matches.iter().map(|value| value.id).collect()
+    }
+}
+
+struct SearchFields {
+    id: Field,
+    file_name: Field,
+    file_type: Field,
+    module_id: Field,
+    tags: Field,
+    metadata: Field,
+    content: Field,
+}
+
+/// Embedded tantivy index over file names, tags, metadata and extracted text.
+///
+/// Writes go through `IndexQueue`, which commits them shortly after an upload so a search sees
+/// the file.
+pub struct SearchIndex {
+    index: Index,
+    reader: IndexReader,
+    writer: Mutex<IndexWriter>,
+    fields: SearchFields,
+}
+
+impl SearchIndex {
+    pub fn open(dir_path: &Path) -> tantivy::Result<SearchIndex> {
+        let mut schema_builder = Schema::builder();
+        let fields = SearchFields {
+            id: schema_builder.add_i64_field("id", INDEXED | STORED | FAST),
+            file_name: schema_builder.add_text_field("file_name", TEXT | STORED),
+            file_type: schema_builder.add_text_field("file_type", STRING),
+            module_id: schema_builder.add_i64_field("module_id", INDEXED),
+            tags: schema_builder.add_text_field("tags", TEXT | STORED),
+            metadata: schema_builder.add_text_field("metadata", TEXT),
+            content: schema_builder.add_text_field("content", TEXT | STORED),
+        };
+        let schema = schema_builder.build();
+
+        std::fs::create_dir_all(dir_path)?;
+        let index = Index::open_or_create(MmapDirectory::open(dir_path)?, schema)?;
+        let reader = index
+            .reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let writer = Mutex::new(index.writer(WRITER_MEMORY)?);
+        Ok(SearchIndex {
+            index,
+            reader,
+            writer,
+            fields,
+        })
+    }
+
+    /// Apply the changes in order and commit them at once.
+    pub fn write(&self, changes: Vec<IndexChange>) -> tantivy::Result<()> {
+        let mut writer = self.writer.lock().map_err(|_| {
+            tantivy::TantivyError::SystemError("search index writer poisoned".to_string())
+        })?;
+        for change in changes {
+            match change {
+                IndexChange::Upsert(document) => {
+                    writer.delete_term(Term::from_field_i64(self.fields.id, document.id));
+                    writer.add_document(self.index_document(document))?;
+                }
+                IndexChange::Delete(id) => {
+                    writer.delete_term(Term::from_field_i64(self.fields.id, id));
+                }
+            }
+        }
+        writer.commit()?;
+        self.reader.reload()
+    }
+
+    /// Replace every document with `documents`, added one at a time and committed at once.
+    /// Nothing changes when reading a document fails. Returns the number of documents.
+    pub fn rebuild(
+        &self,
+        documents: impl Iterator<Item = tantivy::Result<SearchDocument>>,
+    ) -> tantivy::Result<usize> {
+        let mut writer = self.writer.lock().map_err(|_| {
+            tantivy::TantivyError::SystemError("search index writer poisoned".to_string())
+        })?;
+        writer.delete_all_documents()?;
+        let mut indexed = 0;
+        for document in documents {
+            let added = document
+                .and_then(|value| writer.add_document(self.index_document(value)).map(|_| ()));
+            if let Err(error) = added {
+                writer.rollback()?;
+                return Err(error);
+            }
+            indexed += 1;
+        }
+        writer.commit()?;
+        self.reader.reload()?;
+        Ok(indexed)
+    }
+
+    fn index_document(&self, document: SearchDocument) -> TantivyDocument {
+        let fields = &self.fields;
+        let mut index_document = TantivyDocument::default();
+        index_document.add_i64(fields.id, document.id);
+        index_document.add_text(fields.file_name, &document.file_name);
+        index_document.add_text(fields.file_type, &document.file_type);
+        index_document.add_i64(fields.module_id, document.module_id);
+        for tag in document.tags.iter() {
+            index_document.add_text(fields.tags, tag);
+        }
+        index_document.add_text(fields.metadata, &document.metadata);
+        index_document.add_text(fields.content, &document.content);
+        index_document
+    }
+
+    /// Ranked search, returns the best `limit` matches.
+    ///
+    /// Every word must match one of the fields, either exactly or within a small edit distance
+    /// (1 for words of 4 to 7 characters, 2 above), the last word also matches as a prefix.
+    /// Exact matches and matches in the file name rank higher.
+    pub fn search(
+        &self,
+        text: &str,
+        module_id: Option<i64>,
+        limit: usize,
+    ) -> tantivy::Result<SearchMatches> {
+        let fields = &self.fields;
+        let searcher = self.reader.searcher();
+        let words = self.tokenize(text);
+        if words.is_empty() {
+            return Ok(SearchMatches::empty(searcher));
+        }
+
+        let searched_fields = [
+            (fields.file_name, 3.0),
+            (fields.tags, 2.0),
+            (fields.metadata, 1.0),
+            (fields.content, 1.0),
+        ];
+        let mut clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+        for (index, word) in words.iter().enumerate() {
+            let is_last = index == words.len() - 1;
+            let mut word_clauses: Vec<(Occur, Box<dyn Query>)> = Vec::new();
+            for (field, boost) in searched_fields {
+                for (term, distance) in self.expand(&searcher, field, word, is_last)? {
+                    let term_query = TermQuery::new(term, IndexRecordOption::WithFreqs);
+                    let term_boost = boost / (1.0 + distance as f32);
+                    word_clauses.push((
+                        Occur::Should,
+                        Box::new(BoostQuery::new(Box::new(term_query), term_boost)),
+                    ));
+                }
+            }
+            if word_clauses.is_empty() {
+                return Ok(SearchMatches::empty(searcher));
+            }
+            clauses.push((Occur::Must, Box::new(BooleanQuery::new(word_clauses))));
+        }
+        if let Some(value) = module_id {
+            let term = Term::from_field_i64(fields.module_id, value);
+            clauses.push((
+                Occur::Must,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+        let query = BooleanQuery::new(clauses);
+
+        let top_docs =
+            searcher.search(&query, &TopDocs::with_limit(limit.max(1)).order_by_score())?;
+        let mut id_columns: BTreeMap<u32, Column<i64>> = BTreeMap::new();
+        let mut matches = Vec::new();
+        for (score, address) in top_docs {
+            let id_column = match id_columns.entry(address.segment_ord) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(
+                    searcher.segment_reader(address.segment_ord).fast_fields().i64("id")?,
+                ),
+            };
+            if let Some(id) = id_column.first(address.doc_id) {
+                matches.push(SearchMatch { id, score, address });
+            }
+        }
+        Ok(SearchMatches {
+            searcher,
+            query: Some(query),
+            matches,
+        })
+    }
+
+    /// Hits of the matches with the given ids, in the order of `ids`, with their snippets.
+    pub fn hits(&self, matches: &SearchMatches, ids: &[i64]) -> tantivy::Result<Vec<SearchHit>> {
+        let Some(query) = &matches.query else {
+            return Ok(Vec::new());
+        };
+        let fields = &self.fields;
+        let searcher = &matches.searcher;
+        let mut snippet_generators = Vec::new();
+        for (name, field) in [
+            ("file_name", fields.file_name),
+            ("tags", fields.tags),
+            ("content", fields.content),
+        ] {
+            let mut generator = SnippetGenerator::create(searcher, query, field)?;
+            generator.set_max_num_chars(200);
+            snippet_generators.push((name, generator));
+        }
+
+        let mut hits = Vec::new();
+        for id in ids {
+            if let Some(value) = matches.matches.iter().find(|value| value.id == *id) {
+                let hit = self.hit(searcher, value, &snippet_generators)?;
+                hits.push(hit);
+            }
+        }
+        Ok(hits)
+    }
+
+    fn hit(
+        &self,
+        searcher: &tantivy::Searcher,
+        search_match: &SearchMatch,
+        snippet_generators: &[(&str, SnippetGenerator)],
+    ) -> tantivy::Result<SearchHit> {
+        let document: TantivyDocument = searcher.doc(search_match.address)?;
+        let mut highlights = BTreeMap::new();
+        for (name, generator) in snippet_generators {
+            let snippet = generator.snippet_from_doc(&document);
+            if !snippet.highlighted().is_empty() {
+                highlights.insert(name.to_string(), snippet.to_html());
+            }
+        }
+        Ok(SearchHit {
+            id: search_match.id,
+            score: search_match.score,
+            highlights,
+        })
+    }
+
+    fn tokenize(&self, text: &str) -> Vec<String> {
+        let mut tokenizer = match self.index.tokenizer_for_field(self.fields.file_name) {
+            Ok(value) => value,
+            Err(_) => return Vec::new(),
+        };
+        let mut words = Vec::new();
+        let mut stream = tokenizer.token_stream(text);
+        while stream.advance() {
+            words.push(stream.token().text.clone());
+        }
+        words
+    }
+
+    // indexed terms of `field` close to `word`, with their edit distance
+    fn expand(
+        &self,
+        searcher: &tantivy::Searcher,
+        field: Field,
+        word: &str,
+        prefix: bool,
+    ) -> tantivy::Result<Vec<(Term, u8)>> {
+        let max_distance = match word.chars().count() {
+            0..=3 => 0,
+            4..=7 => 1,
+            _ => 2,
+        };
+        let builder = LevenshteinAutomatonBuilder::new(max_distance, true);
+        let dfa = if prefix {
+            builder.build_prefix_dfa(word)
+        } else {
+            builder.build_dfa(word)
+        };
+
+        let mut terms: BTreeMap<Vec<u8>, u8> = BTreeMap::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let mut stream = inverted_index
+                .terms()
+                .search(DfaAutomaton(&dfa))
+                .into_stream()?;
+            while stream.advance() && terms.len() < MAX_EXPANSIONS {
+                let distance = match dfa.eval(stream.key()) {
+                    Distance::Exact(value) => value,
+                    Distance::AtLeast(value) => value,
+                };
+                terms.entry(stream.key().to_vec()).or_insert(distance);
+            }
+        }
+        Ok(terms
+            .into_iter()
+            .map(|(key, distance)| {
+                let text = String::from_utf8_lossy(&key).to_string();
+                (Term::from_field_text(field, &text), distance)
+            })
+            .collect())
+    }
+}
+
+/// Applies index updates one after another, in the order they were sent, on a single worker
+/// thread. Updates sent while the worker is busy are committed together.
+pub struct IndexQueue {
+    sender: mpsc::UnboundedSender<IndexUpdate>,
+}
+
+impl IndexQueue {
+    pub fn start(search_index: Arc<SearchIndex>) -> IndexQueue {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<IndexUpdate>();
+        std::thread::spawn(move || {
+            while let Some(update) = receiver.blocking_recv() {
+                let mut updates = vec![update];
+                while let Ok(value) = receiver.try_recv() {
+                    updates.push(value);
+                }
+                apply_updates(&search_index, updates);
+            }
+        });
+        IndexQueue { sender }
+    }
+
+    pub fn send(&self, update: IndexUpdate) {
+        if self.sender.send(update).is_err() {
+            log::error!("search index queue closed");
+        }
+    }
+}
+
+fn apply_updates(search_index: &SearchIndex, updates: Vec<IndexUpdate>) {
+    let mut changes: Vec<IndexChange> = Vec::new();
+    for update in updates {
+        match update {
+            IndexUpdate::Upsert(source) => changes.push(IndexChange::Upsert(source())),
+            IndexUpdate::Delete(id) => changes.push(IndexChange::Delete(id)),
+            IndexUpdate::Rebuild(documents, reply) => {
+                write_changes(search_index, std::mem::take(&mut changes));
+                let _ = reply.send(search_index.rebuild(documents));
+            }
+        }
+    }
+    write_changes(search_index, changes);
+}
+
+fn write_changes(search_index: &SearchIndex, changes: Vec<IndexChange>) {
+    if changes.is_empty() {
+        return;
+    }
+    if let Err(error) = search_index.write(changes) {
+        log::error!("update search index failed: {}", error);
+    }
+}
+
+struct DfaAutomaton<'a>(&'a DFA);
+
+impl Automaton for DfaAutomaton<'_> {
+    type State = u32;
+
+    fn start(&self) -> u32 {
+        self.0.initial_state()
+    }
+
+    fn is_match(&self, state: &u32) -> bool {
+        matches!(self.0.distance(*state), Distance::Exact(_))
+    }
+
+    fn can_match(&self, state: &u32) -> bool {
+        *state != levenshtein_automata::SINK_STATE
+    }
+
+    fn accept(&self, state: &u32, byte: u8) -> u32 {
+        self.0.transition(*state, byte)
+    }
+}
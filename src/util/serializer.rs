@@ -101,10 +101,10 @@ pub mod sorts_serializer {
 pub mod filters_serializer {
     use serde::{de::Error, Deserialize, Deserializer, Serializer};
 
-    use crate::dto::request::filter_request::Filter;
+    use crate::dto::request::filter_request::FilterNode;
 
     pub fn serialize<S: Serializer>(
-        sorts: &Option<Vec<Filter>>,
+        sorts: &Option<Vec<FilterNode>>,
         serializer: S,
     ) -> Result<S::Ok, S::Error> {
         match sorts {
@@ -118,7 +118,7 @@ pub mod filters_serializer {
 
     pub fn deserialize<'de, D: Deserializer<'de>>(
         deserializer: D,
-    ) -> Result<Option<Vec<Filter>>, D::Error> {
+    ) -> Result<Option<Vec<FilterNode>>, D::Error> {
         let sorts_json_result = Deserialize::deserialize(deserializer);
         let sorts_json: String;
         match sorts_json_result {
@@ -132,7 +132,7 @@ pub mod filters_serializer {
         };
         match serde_json::from_str(&sorts_json) {
             Ok(value) => {
-                let vec: Vec<Filter> = value;
+                let vec: Vec<FilterNode> = value;
                 return Ok(Some(vec));
             }
             Err(error) => {
@@ -143,3 +143,49 @@ pub mod filters_serializer {
         };
     }
 }
+
+
+pub mod option_json_serializer {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    // the column holds a JSON document, it is embedded as is instead of as a string
+    pub fn serialize<S: Serializer>(
+        json: &Option<String>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match json.as_deref().and_then(|value| serde_json::from_str::<serde_json::Value>(value).ok()) {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<String>, D::Error> {
+        let json: Option<serde_json::Value> = Option::deserialize(deserializer)?;
+        Ok(json.map(|value| value.to_string()))
+    }
+}
+
+pub mod json_serializer {
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    // like option_json_serializer, for columns that are never null
+    pub fn serialize<S: Serializer>(
+        json: &str,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(serde::ser::Error::custom)?;
+        value.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<String, D::Error> {
+        let json: serde_json::Value = Deserialize::deserialize(deserializer)?;
+        if json.is_null() {
+            return Err(D::Error::custom("must not be null"));
+        }
+        Ok(json.to_string())
+    }
+}
@@ -0,0 +1,347 @@
+use std::{fs::File, io::Read, path::Path};
+
+use calamine::{open_workbook, Reader as WorkbookReader, Xlsx};
+use lopdf::{decode_text_string, Document, Object};
+use quick_xml::{escape::resolve_predefined_entity, events::Event, Reader, XmlVersion};
+use serde_json::{json, Value as JsonValue};
+use zip::ZipArchive;
+
+// text beyond this is not indexed
+pub const MAX_TEXT_SIZE: usize = 1024 * 1024;
+// cache key of the extracted text, read by the search index
+pub const TEXT_CACHE_KEY: &str = "text.txt";
+
+// limit for a single XML part of an office document and for the content of a PDF page
+const MAX_PART_SIZE: u64 = 64 * 1024 * 1024;
+
+const TEXT_EXTENSIONS: [&str; 16] = [
+    "txt", "md", "csv", "tsv", "json", "xml", "html", "htm", "yml", "yaml", "ini", "log", "sql",
+    "rs", "js", "py",
+];
+const DOCUMENT_EXTENSIONS: [&str; 5] = ["pdf", "docx", "xlsx", "pptx", "odt"];
+
+pub struct ExtractedText {
+    pub text: String,
+    // title, author, page_count and word_count
+    pub properties: JsonValue,
+}
+
+#[derive(Default)]
+struct Properties {
+    title: Option<String>,
+    author: Option<String>,
+    // pages of a PDF, DOCX or ODT, slides of a PPTX, sheets of a XLSX
+    page_count: Option<u64>,
+}
+
+fn extension(file_name: &str) -> String {
+    Path::new(file_name)
+        .extension()
+        .and_then(|value| value.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+pub fn is_plain_text(file_name: &str) -> bool {
+    TEXT_EXTENSIONS.contains(&extension(file_name).as_str())
+}
+
+pub fn is_supported(file_name: &str) -> bool {
+    is_plain_text(file_name) || DOCUMENT_EXTENSIONS.contains(&extension(file_name).as_str())
+}
+
+/// Read the text and the document properties of a stored file, the text is cut at
+/// `MAX_TEXT_SIZE` bytes. Returns `None` for formats without extractable text or unreadable
+/// files.
+pub fn extract(path: &Path, file_name: &str) -> Option<ExtractedText> {
+    let (mut text, properties) = match extension(file_name).as_str() {
+        "pdf" => extract_pdf(path)?,
+        "docx" => extract_ooxml(path, "word/document.xml", "Pages")?,
+        "pptx" => extract_pptx(path)?,
+        "xlsx" => extract_xlsx(path)?,
+        "odt" => extract_odt(path)?,
+        _ if is_plain_text(file_name) => (read_plain_text(path)?, Properties::default()),
+        _ => return None,
+    };
+    truncate(&mut text);
+
+    let properties = json!({
+        "title": properties.title,
+        "author": properties.author,
+        "page_count": properties.page_count,
+        "word_count": text.split_whitespace().count(),
+    });
+    Some(ExtractedText { text, properties })
+}
+
+fn read_plain_text(path: &Path) -> Option<String> {
+    let file = File::open(path).ok()?;
+    let mut bytes = Vec::new();
+    file.take(MAX_TEXT_SIZE as u64)
+        .read_to_end(&mut bytes)
+        .ok()?;
+    Some(String::from_utf8_lossy(&bytes).to_string())
+}
+
+fn truncate(text: &mut String) {
+    if text.len() <= MAX_TEXT_SIZE {
+        return;
+    }
+    let mut index = MAX_TEXT_SIZE;
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    text.truncate(index);
+}
+
+fn extract_pdf(path: &Path) -> Option<(String, Properties)> {
+    let document = Document::load(path).ok()?;
+    let pages = document.get_pages();
+
+    // pages that cannot be decoded (unknown fonts, broken streams) are skipped
+    let mut text = String::new();
+    for page_number in pages.keys() {
+        let chunks =
+            document.extract_text_chunks_with_limit(&[*page_number], MAX_PART_SIZE as usize);
+        for chunk in chunks.into_iter().flatten() {
+            text.push_str(&chunk);
+        }
+        text.push('\n');
+        if text.len() >= MAX_TEXT_SIZE {
+            break;
+        }
+    }
+
+    let info = document
+        .trailer
+        .get(b"Info")
+        .ok()
+        .and_then(|value| document.dereference(value).ok())
+        .and_then(|(_, value)| value.as_dict().ok());
+    let info_text = |key: &[u8]| -> Option<String> {
+        let value: &Object = info?.get(key).ok()?;
+        let (_, value) = document.dereference(value).ok()?;
+        non_empty(decode_text_string(value).ok()?)
+    };
+    let properties = Properties {
+        title: info_text(b"Title"),
+        author: info_text(b"Author"),
+        page_count: Some(pages.len() as u64),
+    };
+    Some((text, properties))
+}
+
+// DOCX and other OOXML packages: text of `main_part`, properties from docProps
+fn extract_ooxml(
+    path: &Path,
+    main_part: &str,
+    count_element: &str,
+) -> Option<(String, Properties)> {
+    let mut archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+    let text = xml_text(&read_part(&mut archive, main_part)?, &["t"], &["p"]);
+    let mut properties = ooxml_properties(&mut archive);
+    properties.page_count = read_part(&mut archive, "docProps/app.xml")
+        .and_then(|value| xml_element_text(&value, count_element))
+        .and_then(|value| value.parse().ok());
+    Some((text, properties))
+}
+
+fn extract_pptx(path: &Path) -> Option<(String, Properties)> {
+    let mut archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+
+    // slides are named slide1.xml, slide2.xml, ... and sorted by their number
+    let mut slides: Vec<(u64, String)> = archive
+        .file_names()
+        .filter_map(|name| {
+            let number = name
+                .strip_prefix("ppt/slides/slide")?
+                .strip_suffix(".xml")?
+                .parse()
+                .ok()?;
+            Some((number, name.to_string()))
+        })
+        .collect();
+    slides.sort();
+
+    let mut text = String::new();
+    for (_, name) in slides.iter() {
+        if let Some(value) = read_part(&mut archive, name) {
+            text.push_str(&xml_text(&value, &["t"], &["p"]));
+            text.push('\n');
+        }
+        if text.len() >= MAX_TEXT_SIZE {
+            break;
+        }
+    }
+    let mut properties = ooxml_properties(&mut archive);
+    properties.page_count = Some(slides.len() as u64);
+    Some((text, properties))
+}
+
+fn extract_xlsx(path: &Path) -> Option<(String, Properties)> {
+    // calamine reads whole worksheets without a limit, oversized parts are not read at all
+    let mut archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+    for index in 0..archive.len() {
+        let part = archive.by_index(index).ok()?;
+        let is_sheet_part = part.name().starts_with("xl/worksheets/")
+            || part.name() == "xl/sharedStrings.xml";
+        if is_sheet_part && part.size() > MAX_PART_SIZE {
+            return None;
+        }
+    }
+    let mut properties = ooxml_properties(&mut archive);
+
+    let mut workbook: Xlsx<_> = open_workbook(path).ok()?;
+    let sheet_names = workbook.sheet_names();
+
+    // one line per row, cells separated by tabs
+    let mut text = String::new();
+    'sheets: for sheet_name in sheet_names.iter() {
+        let Ok(range) = workbook.worksheet_range(sheet_name) else {
+            continue;
+        };
+        for row in range.rows() {
+            let cells: Vec<String> = row.iter().map(|cell| cell.to_string()).collect();
+            text.push_str(cells.join("\t").trim_end());
+            text.push('\n');
+            if text.len() >= MAX_TEXT_SIZE {
+                break 'sheets;
+            }
+        }
+    }
+
+    properties.page_count = Some(sheet_names.len() as u64);
+    Some((text, properties))
+}
+
+fn extract_odt(path: &Path) -> Option<(String, Properties)> {
+    let mut archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+    let text = xml_text(
+        &read_part(&mut archive, "content.xml")?,
+        &["p", "h"],
+        &["p", "h"],
+    );
+
+    let meta = read_part(&mut archive, "meta.xml").unwrap_or_default();
+    let properties = Properties {
+        title: xml_element_text(&meta, "title"),
+        author: xml_element_text(&meta, "initial-creator")
+            .or_else(|| xml_element_text(&meta, "creator")),
+        page_count: xml_attribute(&meta, "document-statistic", "page-count")
+            .and_then(|value| value.parse().ok()),
+    };
+    Some((text, properties))
+}
+
+fn ooxml_properties(archive: &mut ZipArchive<File>) -> Properties {
+    let core = read_part(archive, "docProps/core.xml").unwrap_or_default();
+    Properties {
+        title: xml_element_text(&core, "title"),
+        author: xml_element_text(&core, "creator"),
+        page_count: None,
+    }
+}
+
+fn read_part(archive: &mut ZipArchive<File>, name: &str) -> Option<String> {
+    let part = archive.by_name(name).ok()?;
+    let mut contents = String::new();
+    part.take(MAX_PART_SIZE)
+        .read_to_string(&mut contents)
+        .ok()?;
+    Some(contents)
+}
+
+/// Text inside the `text_elements` of an XML part, a line break is added after each of the
+/// `paragraph_elements`. Elements are matched by their local name.
+fn xml_text(xml: &str, text_elements: &[&str], paragraph_elements: &[&str]) -> String {
+    let mut reader = Reader::from_str(xml);
+    let mut text = String::new();
+    let mut depth: usize = 0;
+    let mut in_tabs = false;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => {
+                let name = element.local_name();
+                if text_elements.contains(&name.as_ref()) {
+                    depth += 1;
+                }
+                // tab stop definitions of a DOCX paragraph are not text
+                if name.as_ref() == "tabs" {
+                    in_tabs = true;
+                }
+            }
+            Ok(Event::End(element)) => {
+                let name = element.local_name();
+                if text_elements.contains(&name.as_ref()) {
+                    depth = depth.saturating_sub(1);
+                }
+                if name.as_ref() == "tabs" {
+                    in_tabs = false;
+                }
+                if paragraph_elements.contains(&name.as_ref()) {
+                    text.push('\n');
+                }
+            }
+            Ok(Event::Empty(element)) => match element.local_name().as_ref() {
+                "tab" if !in_tabs => text.push('\t'),
+                "br" | "line-break" => text.push('\n'),
+                "s" => text.push(' '),
+                _ => {}
+            },
+            Ok(Event::Text(value)) if depth > 0 => text.push_str(&value.xml10_content()),
+            Ok(Event::CData(value)) if depth > 0 => text.push_str(&value.into_inner()),
+            Ok(Event::GeneralRef(value)) if depth > 0 => {
+                if let Ok(Some(character)) = value.resolve_char_ref() {
+                    text.push(character);
+                } else if let Some(entity) = resolve_predefined_entity(&value) {
+                    text.push_str(entity);
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+        if text.len() >= MAX_TEXT_SIZE {
+            break;
+        }
+    }
+    text
+}
+
+// text of the first element named `name`
+fn xml_element_text(xml: &str, name: &str) -> Option<String> {
+    non_empty(
+        xml_text(xml, &[name], &[])
+            .lines()
+            .next()?
+            .trim()
+            .to_string(),
+    )
+}
+
+fn xml_attribute(xml: &str, element_name: &str, attribute_name: &str) -> Option<String> {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) | Ok(Event::Empty(element))
+                if element.local_name().as_ref() == element_name =>
+            {
+                return element
+                    .attributes()
+                    .flatten()
+                    .find(|attribute| attribute.key.local_name().as_ref() == attribute_name)
+                    .and_then(|attribute| attribute.normalized_value(XmlVersion::Implicit1_0).ok())
+                    .map(|value| value.to_string());
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            _ => {}
+        }
+    }
+}
+
+fn non_empty(value: String) -> Option<String> {
+    let value = value.trim().to_string();
+    if value.is_empty() {
+        return None;
+    }
+    Some(value)
+}
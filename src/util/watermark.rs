@@ -0,0 +1,293 @@
+use std::{
+    collections::HashMap,
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    path::Path,
+    sync::Arc,
+};
+
+use ab_glyph::{point, Font, FontArc, Glyph, GlyphId, PxScale, ScaleFont};
+use image::{
+    imageops::{self, FilterType},
+    DynamicImage, Rgba, RgbaImage,
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::dto::enumerator::watermark_position::WatermarkPosition;
+
+// module id -> watermark of its images
+pub type WatermarkPolicies = HashMap<i64, Arc<Watermark>>;
+
+/// Watermark policy of a module, as written in the policy file.
+#[derive(Debug, Deserialize, Serialize, Validate, Clone)]
+pub struct WatermarkPolicy {
+    // either a text drawn with `font_file`, or a logo image
+    #[validate(length(min = 1, max = 255))]
+    pub text: Option<String>,
+    pub font_file: Option<String>,
+    // text colour as #rrggbb, white by default
+    pub color: Option<String>,
+    pub logo_file: Option<String>,
+
+    #[serde(default = "default_position")]
+    pub position: WatermarkPosition,
+    #[serde(default = "default_opacity")]
+    #[validate(range(exclusive_min = 0.0, max = 1.0))]
+    pub opacity: f32,
+    // width of the mark relative to the image width
+    #[serde(default = "default_scale")]
+    #[validate(range(exclusive_min = 0.0, max = 1.0))]
+    pub scale: f32,
+    // repeat the mark over the whole image, the position is ignored
+    #[serde(default)]
+    pub tiled: bool,
+}
+
+fn default_position() -> WatermarkPosition {
+    WatermarkPosition::SOUTHEAST
+}
+
+fn default_opacity() -> f32 {
+    0.5
+}
+
+fn default_scale() -> f32 {
+    0.25
+}
+
+enum Mark {
+    Text {
+        text: String,
+        font: FontArc,
+        color: Rgba<u8>,
+    },
+    Logo(RgbaImage),
+}
+
+/// A policy with its font or logo loaded, ready to be drawn.
+pub struct Watermark {
+    policy: WatermarkPolicy,
+    mark: Mark,
+    // changes with the policy and the content of its files, part of cache keys
+    pub fingerprint: String,
+}
+
+/// Read the policies of a JSON file shaped `{"<module id>": {policy}, ...}`.
+pub fn load_policies(path: &Path) -> Result<WatermarkPolicies, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|error| format!("read {} failed: {error}", path.display()))?;
+    let policies: HashMap<i64, WatermarkPolicy> = serde_json::from_str(&contents)
+        .map_err(|error| format!("parse {} failed: {error}", path.display()))?;
+    policies
+        .into_iter()
+        .map(|(module_id, policy)| {
+            let watermark = Watermark::load(policy)
+                .map_err(|error| format!("watermark of module {module_id}: {error}"))?;
+            Ok((module_id, Arc::new(watermark)))
+        })
+        .collect()
+}
+
+impl Watermark {
+    pub fn load(policy: WatermarkPolicy) -> Result<Self, String> {
+        if let Err(error) = policy.validate() {
+            return Err(error.to_string());
+        };
+
+        let mut hasher = DefaultHasher::new();
+        serde_json::to_string(&policy)
+            .unwrap_or_default()
+            .hash(&mut hasher);
+        let mark = match (&policy.text, &policy.logo_file) {
+            (Some(text), None) => {
+                let font_file = policy
+                    .font_file
+                    .as_ref()
+                    .ok_or("a text watermark needs a font_file")?;
+                let font_bytes =
+                    fs::read(font_file).map_err(|error| format!("read {font_file} failed: {error}"))?;
+                font_bytes.hash(&mut hasher);
+                let font = FontArc::try_from_vec(font_bytes)
+                    .map_err(|error| format!("unsupported font {font_file}: {error}"))?;
+                let color = parse_color(policy.color.as_deref().unwrap_or("#ffffff"))?;
+                Mark::Text {
+                    text: text.clone(),
+                    font,
+                    color,
+                }
+            }
+            (None, Some(logo_file)) => {
+                let logo_bytes =
+                    fs::read(logo_file).map_err(|error| format!("read {logo_file} failed: {error}"))?;
+                logo_bytes.hash(&mut hasher);
+                let logo = image::load_from_memory(&logo_bytes)
+                    .map_err(|error| format!("unsupported logo {logo_file}: {error}"))?;
+                Mark::Logo(logo.to_rgba8())
+            }
+            _ => return Err("exactly one of text and logo_file must be set".to_string()),
+        };
+
+        Ok(Self {
+            policy,
+            mark,
+            fingerprint: format!("{:016x}", hasher.finish()),
+        })
+    }
+
+    /// Draw the watermark on a copy of the image.
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let mut canvas = image.to_rgba8();
+        let (width, height) = canvas.dimensions();
+        let Some(mark) = self.render_mark(width, height) else {
+            return image.clone();
+        };
+        let (mark_width, mark_height) = (mark.width() as i64, mark.height() as i64);
+        let margin = (width.min(height) / 50) as i64;
+
+        if self.policy.tiled {
+            // staggered grid, half a mark apart
+            let step_x = mark_width + mark_width / 2;
+            let step_y = mark_height + mark_height / 2;
+            let mut y = margin;
+            let mut row = 0;
+            while y < height as i64 {
+                let mut x = margin - (row % 2) * step_x / 2;
+                while x < width as i64 {
+                    imageops::overlay(&mut canvas, &mark, x, y);
+                    x += step_x;
+                }
+                y += step_y;
+                row += 1;
+            }
+        } else {
+            let (x, y) = placement(
+                &self.policy.position,
+                (width as i64, height as i64),
+                (mark_width, mark_height),
+                margin,
+            );
+            imageops::overlay(&mut canvas, &mark, x, y);
+        }
+
+        if image.color().has_alpha() {
+            return DynamicImage::ImageRgba8(canvas);
+        }
+        DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(canvas).to_rgb8())
+    }
+
+    // the mark sized for an image of width x height, with the policy opacity
+    fn render_mark(&self, width: u32, height: u32) -> Option<RgbaImage> {
+        let mark_width = ((width as f32 * self.policy.scale).round() as u32).max(1);
+        let mut mark = match &self.mark {
+            Mark::Logo(logo) => {
+                let mark_height =
+                    (logo.height() as u64 * mark_width as u64 / logo.width().max(1) as u64).max(1);
+                imageops::resize(logo, mark_width, mark_height as u32, FilterType::Lanczos3)
+            }
+            Mark::Text { text, font, color } => render_text(text, font, *color, mark_width)?,
+        };
+        if mark.height() > height {
+            let fitted_width = (mark.width() as u64 * height as u64 / mark.height() as u64).max(1);
+            mark = imageops::resize(&mark, fitted_width as u32, height, FilterType::Lanczos3);
+        }
+
+        for pixel in mark.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * self.policy.opacity).round() as u8;
+        }
+        Some(mark)
+    }
+}
+
+// top left corner of the mark for the position, `margin` away from the image edges
+fn placement(
+    position: &WatermarkPosition,
+    (width, height): (i64, i64),
+    (mark_width, mark_height): (i64, i64),
+    margin: i64,
+) -> (i64, i64) {
+    let left = margin;
+    let center_x = (width - mark_width) / 2;
+    let right = width - mark_width - margin;
+    let top = margin;
+    let center_y = (height - mark_height) / 2;
+    let bottom = height - mark_height - margin;
+    match position {
+        WatermarkPosition::NORTHWEST => (left, top),
+        WatermarkPosition::NORTH => (center_x, top),
+        WatermarkPosition::NORTHEAST => (right, top),
+        WatermarkPosition::WEST => (left, center_y),
+        WatermarkPosition::CENTER => (center_x, center_y),
+        WatermarkPosition::EAST => (right, center_y),
+        WatermarkPosition::SOUTHWEST => (left, bottom),
+        WatermarkPosition::SOUTH => (center_x, bottom),
+        WatermarkPosition::SOUTHEAST => (right, bottom),
+    }
+}
+
+// single line of text, sized so it is `mark_width` pixels wide
+fn render_text(text: &str, font: &FontArc, color: Rgba<u8>, mark_width: u32) -> Option<RgbaImage> {
+    let (measured_width, _) = layout(font, text, 100.0);
+    if measured_width <= 0.0 {
+        return None;
+    }
+    let size = 100.0 * mark_width as f32 / measured_width;
+    let (text_width, glyphs) = layout(font, text, size);
+    let scaled_font = font.as_scaled(PxScale::from(size));
+    let text_height = scaled_font.ascent() - scaled_font.descent();
+
+    let mut canvas = RgbaImage::new(
+        text_width.ceil().max(1.0) as u32,
+        text_height.ceil().max(1.0) as u32,
+    );
+    for glyph in glyphs {
+        let Some(outlined_glyph) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined_glyph.px_bounds();
+        outlined_glyph.draw(|x, y, coverage| {
+            let x = bounds.min.x as i64 + x as i64;
+            let y = bounds.min.y as i64 + y as i64;
+            if x < 0 || y < 0 || x >= canvas.width() as i64 || y >= canvas.height() as i64 {
+                return;
+            }
+            let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+            let pixel = canvas.get_pixel_mut(x as u32, y as u32);
+            // overlapping glyphs keep the strongest coverage
+            if alpha > pixel[3] {
+                *pixel = Rgba([color[0], color[1], color[2], alpha]);
+            }
+        });
+    }
+    Some(canvas)
+}
+
+// glyphs of the text on one line and the line width, at `size` pixels
+fn layout(font: &FontArc, text: &str, size: f32) -> (f32, Vec<Glyph>) {
+    let scaled_font = font.as_scaled(PxScale::from(size));
+    let mut glyphs = Vec::new();
+    let mut caret = 0.0;
+    let mut previous: Option<GlyphId> = None;
+    for character in text.chars() {
+        let glyph_id = scaled_font.glyph_id(character);
+        if let Some(previous) = previous {
+            caret += scaled_font.kern(previous, glyph_id);
+        }
+        glyphs.push(glyph_id.with_scale_and_position(size, point(caret, scaled_font.ascent())));
+        caret += scaled_font.h_advance(glyph_id);
+        previous = Some(glyph_id);
+    }
+    (caret, glyphs)
+}
+
+fn parse_color(value: &str) -> Result<Rgba<u8>, String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    let channel = |index: usize| {
+        hex.get(index..index + 2)
+            .and_then(|part| u8::from_str_radix(part, 16).ok())
+    };
+    match (hex.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(red), Some(green), Some(blue)) => Ok(Rgba([red, green, blue, 255])),
+        _ => Err(format!("color must be #rrggbb, got {value}")),
+    }
+}
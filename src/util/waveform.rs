@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, fs::File, path::Path};
+
+use serde::{Deserialize, Serialize};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error,
+    formats::FormatOptions,
+    io::{MediaSourceStream, MediaSourceStreamOptions},
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct Waveform {
+    pub sample_rate: u32,
+    pub channels: usize,
+    pub duration: f64,
+    // peaks per resolution, each peak is the [min, max] sample of its slice in -1.0..=1.0
+    pub peaks: BTreeMap<usize, Vec<[f32; 2]>>,
+}
+
+/// Decode the first audio track and compute its peaks, once per requested resolution.
+///
+/// A resolution is the number of peaks over the whole file; short files may return fewer. All
+/// channels are mixed into one peak list.
+pub fn generate(path: &Path, file_name: &str, resolutions: &[usize]) -> Result<Waveform, Error> {
+    let file = File::open(path)?;
+    let source = MediaSourceStream::new(Box::new(file), MediaSourceStreamOptions::default());
+    let mut hint = Hint::new();
+    if let Some(value) = Path::new(file_name).extension().and_then(|value| value.to_str()) {
+        hint.with_extension(value);
+    }
+    let mut format = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?
+        .format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or(Error::Unsupported("no audio track"))?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate.unwrap_or(0);
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    // the finest peak list is computed while decoding, coarser ones are merged from it
+    let max_resolution = resolutions.iter().copied().max().unwrap_or(1).max(1) as u64;
+    let slice_frames = match track.codec_params.n_frames {
+        Some(value) => (value / max_resolution).max(1),
+        None => (sample_rate as u64 / 100).max(1),
+    };
+
+    let mut fine_peaks: Vec<[f32; 2]> = Vec::new();
+    let mut current = [0f32, 0f32];
+    let mut current_frames: u64 = 0;
+    let mut total_frames: u64 = 0;
+    let mut channels: usize = 0;
+    let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(value) => value,
+            Err(Error::IoError(error)) if error.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(Error::ResetRequired) => break,
+            Err(error) => return Err(error),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(value) => value,
+            // a corrupt packet is skipped, the rest of the stream is still usable
+            Err(Error::DecodeError(_)) => continue,
+            Err(error) => return Err(error),
+        };
+
+        let spec = *decoded.spec();
+        channels = spec.channels.count().max(1);
+        let capacity = decoded.capacity() as u64;
+        let too_small = sample_buffer
+            .as_ref()
+            .map(|value| (value.capacity() as u64) < capacity * channels as u64)
+            .unwrap_or(true);
+        if too_small {
+            sample_buffer = Some(SampleBuffer::new(capacity, spec));
+        }
+        let Some(buffer) = sample_buffer.as_mut() else {
+            continue;
+        };
+        buffer.copy_interleaved_ref(decoded);
+
+        for frame in buffer.samples().chunks(channels) {
+            for sample in frame {
+                current[0] = current[0].min(*sample);
+                current[1] = current[1].max(*sample);
+            }
+            current_frames += 1;
+            total_frames += 1;
+            if current_frames == slice_frames {
+                fine_peaks.push(current);
+                current = [0f32, 0f32];
+                current_frames = 0;
+            }
+        }
+    }
+    if current_frames > 0 {
+        fine_peaks.push(current);
+    }
+
+    let mut peaks = BTreeMap::new();
+    for resolution in resolutions {
+        peaks.insert(*resolution, merge_peaks(&fine_peaks, *resolution));
+    }
+    let duration = if sample_rate > 0 {
+        (total_frames as f64 / sample_rate as f64 * 1000.0).round() / 1000.0
+    } else {
+        0.0
+    };
+
+    Ok(Waveform {
+        sample_rate,
+        channels,
+        duration,
+        peaks,
+    })
+}
+
+fn merge_peaks(fine_peaks: &[[f32; 2]], resolution: usize) -> Vec<[f32; 2]> {
+    if resolution == 0 || fine_peaks.len() <= resolution {
+        return fine_peaks.iter().map(|peak| round_peak(*peak)).collect();
+    }
+    (0..resolution)
+        .map(|index| {
+            let start = index * fine_peaks.len() / resolution;
+            let end = ((index + 1) * fine_peaks.len() / resolution).max(start + 1);
+            let peak = fine_peaks[start..end]
+                .iter()
+                .fold([0f32, 0f32], |merged, peak| {
+                    [merged[0].min(peak[0]), merged[1].max(peak[1])]
+                });
+            round_peak(peak)
+        })
+        .collect()
+}
+
+// 4 decimals is below what a waveform can show and keeps the JSON small
+fn round_peak(peak: [f32; 2]) -> [f32; 2] {
+    [
+        (peak[0] * 10000.0).round() / 10000.0,
+        (peak[1] * 10000.0).round() / 10000.0,
+    ]
+}